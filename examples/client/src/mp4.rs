@@ -66,9 +66,10 @@ pub struct Opts {
     #[arg(long, name = "secs")]
     duration: Option<u64>,
 
-    /// The transport to use: `tcp` or `udp` (experimental).
+    /// The transport to use: `tcp`, `udp` (experimental), or `udp-multicast`
+    /// (experimental).
     ///
-    /// Note: `--allow-loss` is strongly recommended with `udp`.
+    /// Note: `--allow-loss` is strongly recommended with `udp` and `udp-multicast`.
     #[arg(default_value_t, long)]
     transport: retina::client::Transport,
 
@@ -713,8 +714,15 @@ async fn write_mp4(
 }
 
 pub async fn run(opts: Opts) -> Result<(), Error> {
-    if matches!(opts.transport, Transport::Udp(_)) && !opts.allow_loss {
-        warn!("Using --transport=udp without strongly recommended --allow-loss!");
+    if matches!(
+        opts.transport,
+        Transport::Udp(_) | Transport::UdpMulticast(_)
+    ) && !opts.allow_loss
+    {
+        warn!(
+            "Using --transport={} without strongly recommended --allow-loss!",
+            opts.transport
+        );
     }
 
     let creds = super::creds(opts.src.username.clone(), opts.src.password.clone());
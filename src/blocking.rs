@@ -0,0 +1,145 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Blocking [`Connection`] using `std::net`, for [`crate::client::blocking`].
+
+use bytes::{Bytes, BytesMut};
+use rtsp_types::Message;
+use std::convert::TryFrom;
+use std::io::{Read as _, Write as _};
+use std::net::TcpStream;
+use std::time::Instant;
+use url::Host;
+
+use crate::framing::{Codec, CodecError};
+use crate::{ConnectionContext, Error, ErrorInt, ReceivedMessage, RtspMessageContext, WallTime};
+
+/// The number of bytes to read from the socket at once.
+const READ_SIZE: usize = 16 * 1024;
+
+/// A RTSP connection with blocking reads and writes.
+pub(crate) struct Connection {
+    stream: TcpStream,
+    codec: Codec,
+    read_buf: BytesMut,
+}
+
+/// The result of [`Connection::read`].
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Read {
+    Message(ReceivedMessage),
+
+    /// The deadline passed before a full message arrived.
+    TimedOut,
+
+    /// The peer cleanly closed the connection.
+    Eof,
+}
+
+impl Connection {
+    pub(crate) fn connect(host: Host<&str>, port: u16) -> Result<Self, std::io::Error> {
+        let stream = match host {
+            Host::Domain(h) => TcpStream::connect((h, port)),
+            Host::Ipv4(h) => TcpStream::connect((h, port)),
+            Host::Ipv6(h) => TcpStream::connect((h, port)),
+        }?;
+        Self::from_stream(stream)
+    }
+
+    pub(crate) fn from_stream(stream: TcpStream) -> Result<Self, std::io::Error> {
+        let local_addr = stream.local_addr()?;
+        let peer_addr = stream.peer_addr()?;
+        Ok(Self {
+            stream,
+            codec: Codec::new(ConnectionContext {
+                local_addr,
+                peer_addr,
+                established_wall: WallTime::now(),
+                peer_certificate: None,
+            }),
+            read_buf: BytesMut::new(),
+        })
+    }
+
+    pub(crate) fn ctx(&self) -> &ConnectionContext {
+        &self.codec.ctx
+    }
+
+    pub(crate) fn eof_ctx(&self) -> RtspMessageContext {
+        RtspMessageContext {
+            pos: self.codec.read_pos
+                + u64::try_from(self.read_buf.len()).expect("usize fits in u64"),
+            received_wall: WallTime::now(),
+            received: Instant::now(),
+        }
+    }
+
+    /// Writes a message in its entirety.
+    pub(crate) fn send(&mut self, msg: Message<Bytes>) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        msg.write(&mut buf).expect("Vec Writer is infallible");
+        self.stream.write_all(&buf).map_err(|source| {
+            wrap!(ErrorInt::WriteError {
+                conn_ctx: self.ctx().clone(),
+                source,
+            })
+        })
+    }
+
+    /// Reads the next message, giving up at `deadline` if supplied.
+    pub(crate) fn read(&mut self, deadline: Option<Instant>) -> Result<Read, Error> {
+        let mut chunk = [0u8; READ_SIZE];
+        loop {
+            match self.codec.decode(&mut self.read_buf) {
+                Ok(Some(msg)) => return Ok(Read::Message(msg)),
+                Ok(None) => {}
+                Err(CodecError::ParseError { description, pos }) => {
+                    bail!(ErrorInt::RtspFramingError {
+                        conn_ctx: self.ctx().clone(),
+                        msg_ctx: RtspMessageContext {
+                            pos,
+                            received_wall: WallTime::now(),
+                            received: Instant::now(),
+                        },
+                        description,
+                    })
+                }
+                Err(CodecError::IoError(source)) => bail!(self.read_err(source)),
+            }
+            let timeout = match deadline {
+                None => None,
+                Some(d) => match d.checked_duration_since(Instant::now()) {
+                    Some(t) if !t.is_zero() => Some(t),
+                    _ => return Ok(Read::TimedOut),
+                },
+            };
+            self.stream
+                .set_read_timeout(timeout)
+                .map_err(|e| wrap!(self.read_err(e)))?;
+            match self.stream.read(&mut chunk) {
+                Ok(0) if self.read_buf.is_empty() => return Ok(Read::Eof),
+                Ok(0) => bail!(self.read_err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "EOF within RTSP message",
+                ))),
+                Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock
+                            | std::io::ErrorKind::TimedOut
+                            | std::io::ErrorKind::Interrupted
+                    ) => {}
+                Err(e) => bail!(self.read_err(e)),
+            }
+        }
+    }
+
+    fn read_err(&self, source: std::io::Error) -> ErrorInt {
+        ErrorInt::RtspReadError {
+            conn_ctx: self.ctx().clone(),
+            msg_ctx: self.eof_ctx(),
+            source,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Choosing among authentication challenges, as in
+//! [RFC 7616 section 3.7](https://datatracker.ietf.org/doc/html/rfc7616#section-3.7).
+
+use std::convert::TryFrom;
+
+use http_auth::digest::Algorithm;
+use http_auth::{ChallengeParser, PasswordClient};
+
+/// Returns a preference rank for `client`; higher is better.
+fn rank(client: &PasswordClient) -> u8 {
+    match client {
+        PasswordClient::Digest(d) => match d.algorithm() {
+            Algorithm::Sha512Trunc256 => 4,
+            Algorithm::Sha256 => 3,
+            Algorithm::Md5 => 2,
+            _ => 1,
+        },
+        _ => 0,
+    }
+}
+
+/// Returns a client for the strongest supported challenge within
+/// `www_authenticate`.
+///
+/// The value may hold several challenges, either as a single comma-separated
+/// list or as several `WWW-Authenticate` headers combined into one. Digest is
+/// preferred over Basic, and stronger digest algorithms over weaker ones.
+/// Unparseable or unsupported challenges are ignored if there's at least one
+/// supported one.
+pub(crate) fn choose(www_authenticate: &str) -> Result<PasswordClient, String> {
+    let mut best: Option<PasswordClient> = None;
+    let mut first_err = None;
+    for challenge in ChallengeParser::new(www_authenticate) {
+        let client = challenge
+            .map_err(|e| e.to_string())
+            .and_then(|c| PasswordClient::try_from(&c));
+        match client {
+            Ok(c) if best.as_ref().map(|b| rank(&c) > rank(b)).unwrap_or(true) => best = Some(c),
+            Ok(_) => {}
+            Err(e) => {
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+    best.ok_or_else(|| first_err.unwrap_or_else(|| "no challenges given".to_owned()))
+}
+
+/// Returns a client for a challenge which followed a request authenticated
+/// via `prior`, if the request is worth retrying.
+///
+/// This is the case if the server says the prior nonce was stale or issued a
+/// different one, as servers do when they rotate nonces periodically.
+/// Otherwise, the credentials were likely rejected.
+pub(crate) fn rechallenge(
+    prior: &PasswordClient,
+    www_authenticate: &str,
+) -> Option<PasswordClient> {
+    match (prior, choose(www_authenticate).ok()?) {
+        (PasswordClient::Digest(p), PasswordClient::Digest(n))
+            if n.stale() || n.nonce() != p.nonce() =>
+        {
+            Some(PasswordClient::Digest(n))
+        }
+        _ => None,
+    }
+}
+
+/// Returns true iff `client` is a digest client whose nonce was reported stale.
+pub(crate) fn is_stale(client: &PasswordClient) -> bool {
+    matches!(client, PasswordClient::Digest(d) if d.stale())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strongest() {
+        let c = choose(
+            "Basic realm=\"cam\", \
+             Digest realm=\"cam\", nonce=\"a\", algorithm=MD5, \
+             Unsupported foo=bar, \
+             Digest realm=\"cam\", nonce=\"b\", algorithm=SHA-256, qop=\"auth\", \
+             Digest realm=\"cam\", nonce=\"c\", algorithm=MD5-sess, qop=\"auth\"",
+        )
+        .unwrap();
+        match c {
+            PasswordClient::Digest(d) => {
+                assert_eq!(d.algorithm(), Algorithm::Sha256);
+                assert_eq!(d.nonce(), "b");
+            }
+            o => panic!("unexpected client {o:?}"),
+        }
+
+        assert!(matches!(
+            choose("Unsupported foo=bar, Basic realm=\"cam\"").unwrap(),
+            PasswordClient::Basic(_)
+        ));
+        choose("Unsupported foo=bar").unwrap_err();
+    }
+
+    #[test]
+    fn rechallenges() {
+        let prior = choose("Digest realm=\"cam\", nonce=\"a\"").unwrap();
+        assert!(rechallenge(&prior, "Digest realm=\"cam\", nonce=\"a\"").is_none());
+        let stale = rechallenge(&prior, "Digest realm=\"cam\", nonce=\"a\", stale=true").unwrap();
+        assert!(is_stale(&stale));
+        let rotated = rechallenge(&prior, "Digest realm=\"cam\", nonce=\"b\"").unwrap();
+        assert!(!is_stale(&rotated));
+        assert!(rechallenge(&prior, "Basic realm=\"cam\"").is_none());
+    }
+}
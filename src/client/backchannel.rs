@@ -0,0 +1,338 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Sending audio on ONVIF backchannel streams.
+//!
+//! See the [ONVIF Streaming
+//! Specification](https://www.onvif.org/specs/stream/ONVIF-Streaming-Spec.pdf)
+//! section 5.3.
+
+use std::num::NonZeroU32;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::{Sink, SinkExt};
+
+use super::Stream;
+use crate::codec::{aac, MAX_PAYLOAD_SIZE};
+use crate::rtp::RawPacketBuilder;
+use crate::{Error, ErrorInt, Timestamp};
+
+/// The number of frames which may be queued between [`BackchannelSink`]s and
+/// the session.
+pub(super) const QUEUE_LEN: usize = 16;
+
+/// A packetized frame, queued for the session to write.
+pub(super) struct QueuedFrame {
+    pub(super) stream_id: usize,
+    pub(super) packets: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+enum Format {
+    /// G.711 (`PCMU` or `PCMA`): one byte per sample per channel.
+    G711 { channels: u16 },
+
+    /// AAC in `mode=AAC-hbr`, as described in
+    /// [RFC 3640 section 3.3.6](https://datatracker.ietf.org/doc/html/rfc3640#section-3.3.6).
+    Aac {
+        packetizer: aac::Packetizer,
+        clock_rate: NonZeroU32,
+        frame_length: NonZeroU32,
+    },
+}
+
+#[derive(Debug)]
+struct Packetizer {
+    format: Format,
+    payload_type: u8,
+    ssrc: u32,
+    next_seq: u16,
+    next_timestamp: u32,
+
+    /// True iff no packet has been sent yet. RFC 3551 section 4.1 says the
+    /// marker bit of the first G.711 packet of a talkspurt should be set.
+    first: bool,
+}
+
+impl Packetizer {
+    fn new(stream_id: usize, stream: &Stream) -> Result<Self, String> {
+        if stream.srtp.is_some() {
+            return Err("SRTP backchannel streams aren't supported".to_owned());
+        }
+        let payload_type = stream.rtp_payload_type();
+        let ssrc = rand::random();
+        let next_seq = rand::random();
+        let format = match stream.encoding_name() {
+            "pcmu" | "pcma" => Format::G711 {
+                channels: stream.channels().map(|c| c.get()).unwrap_or(1),
+            },
+            "mpeg4-generic" => {
+                let frame_length = match stream.parameters() {
+                    Some(crate::codec::ParametersRef::Audio(p)) => {
+                        p.frame_length().ok_or_else(|| {
+                            "AAC backchannel stream has unknown frame length".to_owned()
+                        })?
+                    }
+                    _ => return Err("AAC backchannel stream has no valid parameters".to_owned()),
+                };
+                aac::Packetizer::check_format_specific_params(
+                    stream.format_specific_params.as_deref().unwrap_or_default(),
+                )
+                .map_err(|e| format!("unsupported AAC backchannel stream: {e}"))?;
+                Format::Aac {
+                    packetizer: aac::Packetizer::new(
+                        MAX_PAYLOAD_SIZE,
+                        stream_id,
+                        next_seq,
+                        payload_type,
+                        ssrc,
+                    )?,
+                    clock_rate: NonZeroU32::new(stream.clock_rate_hz())
+                        .ok_or_else(|| "AAC backchannel stream has zero clock rate".to_owned())?,
+                    frame_length,
+                }
+            }
+            e => return Err(format!("unsupported backchannel encoding {e:?}")),
+        };
+        Ok(Packetizer {
+            format,
+            payload_type,
+            ssrc,
+            next_seq,
+            next_timestamp: rand::random(),
+            first: true,
+        })
+    }
+
+    fn packet(&mut self, timestamp: u32, mark: bool, payload: &[u8]) -> Result<Bytes, Error> {
+        let (raw, _) = RawPacketBuilder {
+            sequence_number: self.next_seq,
+            timestamp,
+            payload_type: self.payload_type,
+            ssrc: self.ssrc,
+            mark,
+        }
+        .build(payload.iter().copied())
+        .map_err(|e| wrap!(ErrorInt::InvalidArgument(e.to_owned())))?;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        Ok(raw.0)
+    }
+
+    /// Packetizes a single frame, advancing the RTP timestamp past it.
+    fn packetize(&mut self, frame: Bytes) -> Result<Vec<Bytes>, Error> {
+        if frame.is_empty() {
+            bail!(ErrorInt::InvalidArgument(
+                "empty backchannel frame".to_owned()
+            ));
+        }
+        let timestamp = self.next_timestamp;
+        let mut packets = Vec::new();
+        match &mut self.format {
+            Format::G711 { channels } => {
+                let channels = usize::from(*channels);
+                if frame.len() % channels != 0 {
+                    bail!(ErrorInt::InvalidArgument(format!(
+                        "G.711 frame of {} bytes isn't a whole number of {}-channel samples",
+                        frame.len(),
+                        channels
+                    )));
+                }
+                let samples = u32::try_from(frame.len() / channels).map_err(|_| {
+                    wrap!(ErrorInt::InvalidArgument("G.711 frame too long".to_owned()))
+                })?;
+                let max_payload_size = usize::from(MAX_PAYLOAD_SIZE);
+                let chunk_len = max_payload_size - max_payload_size % channels;
+                for (i, chunk) in frame.chunks(chunk_len).enumerate() {
+                    let offset = (i * chunk_len / channels) as u32;
+                    let mark = self.first && i == 0;
+                    packets.push(self.packet(timestamp.wrapping_add(offset), mark, chunk)?);
+                }
+                self.next_timestamp = timestamp.wrapping_add(samples);
+            }
+            Format::Aac {
+                packetizer,
+                clock_rate,
+                frame_length,
+            } => {
+                let rtp_timestamp = Timestamp {
+                    timestamp: i64::from(timestamp),
+                    clock_rate: *clock_rate,
+                    start: 0,
+                };
+                packetizer.push(rtp_timestamp, frame)?;
+                while let Some(pkt) = packetizer
+                    .pull()
+                    .map_err(|e| wrap!(ErrorInt::InvalidArgument(e)))?
+                {
+                    self.next_seq = pkt.sequence_number().wrapping_add(1);
+                    packets.push(pkt.raw.0);
+                }
+                self.next_timestamp = timestamp.wrapping_add(frame_length.get());
+            }
+        }
+        self.first = false;
+        Ok(packets)
+    }
+}
+
+/// Sends audio frames on an ONVIF backchannel stream.
+///
+/// Obtain via [`super::Session::backchannel`] or [`super::Demuxed::backchannel`].
+/// Each item is a single encoded frame in the stream's encoding:
+///
+/// *   for `PCMU` and `PCMA` (G.711), any whole number of samples.
+/// *   for `MPEG4-GENERIC` (AAC), a single raw access unit, without ADTS headers.
+///
+/// Frames are assumed to be contiguous: the RTP timestamp advances by the
+/// frame's duration.
+///
+/// The sink only queues packets; the session writes them as it is polled, so
+/// keep polling the session (or its [`super::Demuxed`]) while sending. Sending
+/// fails once the session has been dropped.
+pub struct BackchannelSink {
+    stream_id: usize,
+    packetizer: Packetizer,
+    tx: mpsc::Sender<QueuedFrame>,
+}
+
+impl BackchannelSink {
+    pub(super) fn new(
+        stream_id: usize,
+        stream: &Stream,
+        tx: mpsc::Sender<QueuedFrame>,
+    ) -> Result<Self, Error> {
+        let packetizer =
+            Packetizer::new(stream_id, stream).map_err(|e| wrap!(ErrorInt::InvalidArgument(e)))?;
+        Ok(BackchannelSink {
+            stream_id,
+            packetizer,
+            tx,
+        })
+    }
+
+    /// Returns the index of the stream within [`super::Session::streams`].
+    pub fn stream_id(&self) -> usize {
+        self.stream_id
+    }
+
+    /// Returns the RTP synchronization source (SSRC) identifier used for sending.
+    pub fn ssrc(&self) -> u32 {
+        self.packetizer.ssrc
+    }
+}
+
+impl std::fmt::Debug for BackchannelSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackchannelSink")
+            .field("stream_id", &self.stream_id)
+            .field("packetizer", &self.packetizer)
+            .finish()
+    }
+}
+
+fn session_closed() -> Error {
+    wrap!(ErrorInt::FailedPrecondition("session closed".into()))
+}
+
+impl Sink<Bytes> for BackchannelSink {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.tx.poll_ready_unpin(cx).map_err(|_| session_closed())
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, frame: Bytes) -> Result<(), Error> {
+        let packets = self.packetizer.packetize(frame)?;
+        let stream_id = self.stream_id;
+        self.tx
+            .start_send_unpin(QueuedFrame { stream_id, packets })
+            .map_err(|_| session_closed())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.tx.poll_flush_unpin(cx).map_err(|_| session_closed())
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.tx.poll_close_unpin(cx).map_err(|_| session_closed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packetizer(format: Format) -> Packetizer {
+        Packetizer {
+            format,
+            payload_type: 0,
+            ssrc: 0x1234_5678,
+            next_seq: 0xffff,
+            next_timestamp: 1000,
+            first: true,
+        }
+    }
+
+    #[test]
+    fn g711() {
+        let mut p = packetizer(Format::G711 { channels: 1 });
+        let packets = p.packetize(Bytes::from(vec![0xff; 160])).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(
+            &packets[0][..12],
+            b"\x80\x80\xff\xff\x00\x00\x03\xe8\x12\x34\x56\x78"
+        );
+        assert_eq!(packets[0].len(), 12 + 160);
+
+        // A long frame is split. Only the first packet overall has the marker bit.
+        let packets = p.packetize(Bytes::from(vec![0xff; 2000])).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(
+            &packets[0][..12],
+            b"\x80\x00\x00\x00\x00\x00\x04\x88\x12\x34\x56\x78"
+        );
+        assert_eq!(packets[0].len(), 12 + usize::from(MAX_PAYLOAD_SIZE));
+        assert_eq!(
+            &packets[1][..12],
+            b"\x80\x00\x00\x01\x00\x00\x0a\x00\x12\x34\x56\x78"
+        );
+        assert_eq!(packets[1].len(), 12 + 600);
+        assert_eq!(p.next_timestamp, 1000 + 160 + 2000);
+
+        let mut p = packetizer(Format::G711 { channels: 2 });
+        p.packetize(Bytes::from(vec![0xff; 3])).unwrap_err();
+    }
+
+    #[test]
+    fn aac() {
+        let mut p = packetizer(Format::Aac {
+            packetizer: aac::Packetizer::new(MAX_PAYLOAD_SIZE, 0, 0xffff, 0, 0x1234_5678).unwrap(),
+            clock_rate: NonZeroU32::new(48_000).unwrap(),
+            frame_length: NonZeroU32::new(1024).unwrap(),
+        });
+        let packets = p.packetize(Bytes::from(vec![0x21; 300])).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(
+            &packets[0][..16],
+            b"\x80\x80\xff\xff\x00\x00\x03\xe8\x12\x34\x56\x78\x00\x10\x09\x60"
+        );
+        assert_eq!(packets[0].len(), 16 + 300);
+
+        // Fragmented: the mark bit is on the last fragment.
+        let packets = p.packetize(Bytes::from(vec![0x21; 2000])).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(
+            &packets[0][..16],
+            b"\x80\x00\x00\x00\x00\x00\x07\xe8\x12\x34\x56\x78\x00\x10\x3e\x80"
+        );
+        assert_eq!(
+            &packets[1][..16],
+            b"\x80\x80\x00\x01\x00\x00\x07\xe8\x12\x34\x56\x78\x00\x10\x3e\x80"
+        );
+        assert_eq!(packets[0].len() + packets[1].len(), 2 * 16 + 2000);
+        assert_eq!(p.next_timestamp, 1000 + 2048);
+    }
+}
@@ -0,0 +1,754 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Recording and replaying sessions' wire traffic; see [`Capture`] and [`Replay`].
+//!
+//! A capture file starts with the 8-byte magic `RTSPCAP\x01`, followed by
+//! records. Each record starts with a one-byte kind and a four-byte
+//! connection id; all integers are big-endian. Kinds:
+//!
+//! *   `0`: a connection was established. Wall time (`i64` seconds and `i32`
+//!     nanoseconds), then local and peer addresses (each a `u8` length and a
+//!     string), then the peer's certificate (a `u32` length and DER bytes,
+//!     with zero length meaning none).
+//! *   `1`: an RTSP message was received. Common fields (below), the message's
+//!     `u64` position within the input stream, then the message.
+//! *   `2`: an RTSP message was sent. Common fields, then the message, with
+//!     any `Authorization` and `Proxy-Authorization` headers removed.
+//! *   `3`: a UDP packet was received. Common fields, the `u16` stream index,
+//!     a `u8` which is `1` for RTCP and `0` for RTP, then the packet.
+//!
+//! The common fields are the `u64` nanoseconds since the connection was
+//! established and the wall time. Messages and packets are a `u32` length
+//! followed by their bytes; RTSP messages are as sent on the wire, with
+//! interleaved data in `$` framing.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use log::{debug, warn};
+use rtsp_types::{Message, Method};
+
+use crate::{ConnectionContext, Error, ReceivedMessage, RtspMessageContext, WallTime};
+
+const MAGIC: [u8; 8] = *b"RTSPCAP\x01";
+
+const KIND_CONNECTION: u8 = 0;
+const KIND_RECEIVED: u8 = 1;
+const KIND_SENT: u8 = 2;
+const KIND_UDP: u8 = 3;
+
+/// Records sessions' wire traffic to a file; see [`super::SessionOptions::capture`].
+///
+/// This is a cheap handle which may be cloned and shared by several
+/// sessions, such as successive ones of a [`super::Reconnecting`]. Write
+/// errors are logged once, and then recording stops; they don't affect the
+/// sessions.
+#[derive(Clone)]
+pub struct Capture(Arc<Mutex<CaptureState>>);
+
+struct CaptureState {
+    /// The destination, or `None` after a write error.
+    writer: Option<Box<dyn Write + Send>>,
+    next_conn_id: u32,
+}
+
+impl Capture {
+    /// Starts a capture, writing its header to `writer`.
+    ///
+    /// Records are written as traffic happens, so `writer` should usually be
+    /// buffered, eg via [`std::io::BufWriter`]. It's flushed when the last
+    /// handle is dropped or on [`Capture::flush`].
+    pub fn new<W: Write + Send + 'static>(mut writer: W) -> Result<Self, std::io::Error> {
+        writer.write_all(&MAGIC)?;
+        Ok(Capture(Arc::new(Mutex::new(CaptureState {
+            writer: Some(Box::new(writer)),
+            next_conn_id: 0,
+        }))))
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&self) -> Result<(), std::io::Error> {
+        match self.0.lock().unwrap().writer.as_mut() {
+            Some(w) => w.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Records a new connection, returning its recorder.
+    pub(crate) fn connection(&self, ctx: &ConnectionContext) -> ConnectionCapture {
+        let id = {
+            let mut l = self.0.lock().unwrap();
+            let id = l.next_conn_id;
+            l.next_conn_id = l.next_conn_id.wrapping_add(1);
+            id
+        };
+        let mut r = record(KIND_CONNECTION, id);
+        put_wall(&mut r, ctx.established_wall);
+        put_addr(&mut r, ctx.local_addr);
+        put_addr(&mut r, ctx.peer_addr);
+        put_bytes(&mut r, ctx.peer_certificate.as_deref().unwrap_or(&[]));
+        self.write(&r);
+        ConnectionCapture {
+            capture: self.clone(),
+            id,
+            established: Instant::now(),
+        }
+    }
+
+    fn write(&self, record: &[u8]) {
+        let mut l = self.0.lock().unwrap();
+        if let Some(w) = l.writer.as_mut() {
+            if let Err(e) = w.write_all(record) {
+                warn!("Unable to write capture; stopping: {}", e);
+                l.writer = None;
+            }
+        }
+    }
+}
+
+/// Records a single connection's traffic to a [`Capture`].
+pub(crate) struct ConnectionCapture {
+    capture: Capture,
+    id: u32,
+    established: Instant,
+}
+
+impl ConnectionCapture {
+    pub(crate) fn received(&self, msg: &ReceivedMessage) {
+        let mut r = self.common(KIND_RECEIVED, msg.ctx.received, msg.ctx.received_wall);
+        r.put_u64(msg.ctx.pos);
+        put_message(&mut r, &msg.msg);
+        self.capture.write(&r);
+    }
+
+    /// Records a sent message, minus any credentials.
+    pub(crate) fn sent(&self, msg: &Message<Bytes>) {
+        let mut r = self.common(KIND_SENT, Instant::now(), WallTime::now());
+        match msg {
+            Message::Request(req)
+                if req.header(&rtsp_types::headers::AUTHORIZATION).is_some()
+                    || req
+                        .header(&rtsp_types::headers::PROXY_AUTHORIZATION)
+                        .is_some() =>
+            {
+                let mut req = req.clone();
+                req.remove_header(&rtsp_types::headers::AUTHORIZATION);
+                req.remove_header(&rtsp_types::headers::PROXY_AUTHORIZATION);
+                put_message(&mut r, &Message::Request(req));
+            }
+            _ => put_message(&mut r, msg),
+        }
+        self.capture.write(&r);
+    }
+
+    pub(crate) fn udp(&self, stream_i: usize, rtcp: bool, received_wall: WallTime, data: &[u8]) {
+        let mut r = self.common(KIND_UDP, Instant::now(), received_wall);
+        r.put_u16(u16::try_from(stream_i).expect("stream index fits in u16"));
+        r.put_u8(u8::from(rtcp));
+        put_bytes(&mut r, data);
+        self.capture.write(&r);
+    }
+
+    fn common(&self, kind: u8, when: Instant, wall: WallTime) -> Vec<u8> {
+        let mut r = record(kind, self.id);
+        let elapsed = when.saturating_duration_since(self.established);
+        r.put_u64(u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX));
+        put_wall(&mut r, wall);
+        r
+    }
+}
+
+fn record(kind: u8, conn_id: u32) -> Vec<u8> {
+    let mut r = Vec::new();
+    r.put_u8(kind);
+    r.put_u32(conn_id);
+    r
+}
+
+fn put_wall(r: &mut Vec<u8>, wall: WallTime) {
+    r.put_i64(wall.0.sec);
+    r.put_i32(wall.0.nsec);
+}
+
+fn put_addr(r: &mut Vec<u8>, addr: SocketAddr) {
+    let addr = addr.to_string();
+    r.put_u8(u8::try_from(addr.len()).expect("socket address fits in 255 bytes"));
+    r.put_slice(addr.as_bytes());
+}
+
+fn put_bytes(r: &mut Vec<u8>, b: &[u8]) {
+    r.put_u32(u32::try_from(b.len()).expect("message fits in 4 GiB"));
+    r.put_slice(b);
+}
+
+fn put_message(r: &mut Vec<u8>, msg: &Message<Bytes>) {
+    let mut raw = Vec::new();
+    msg.write(&mut raw).expect("Vec writer is infallible");
+    put_bytes(r, &raw);
+}
+
+/// A capture to replay in place of a server; see [`super::SessionOptions::replay`].
+///
+/// This is a cheap handle which may be cloned. All clones share a position
+/// within the capture: each connection opened with this replay takes the
+/// next recorded connection.
+#[derive(Clone)]
+pub struct Replay(Arc<Mutex<VecDeque<ReplayConnection>>>);
+
+impl Replay {
+    /// Reads a capture written via [`Capture`].
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, std::io::Error> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        let mut buf = Bytes::from(buf);
+        if !buf.starts_with(&MAGIC) {
+            return Err(invalid("not a capture file".to_owned()));
+        }
+        buf.advance(MAGIC.len());
+        let mut conns = Vec::new();
+        let mut ids = HashMap::new();
+        let mut codec = crate::framing::Codec::new(ConnectionContext::dummy());
+        while buf.has_remaining() {
+            let kind = get_u8(&mut buf)?;
+            let id = get_u32(&mut buf)?;
+            if kind == KIND_CONNECTION {
+                let established_wall = get_wall(&mut buf)?;
+                let local_addr = get_addr(&mut buf)?;
+                let peer_addr = get_addr(&mut buf)?;
+                let peer_certificate = get_bytes(&mut buf)?;
+                ids.insert(id, conns.len());
+                conns.push(ReplayConnection {
+                    ctx: ConnectionContext {
+                        local_addr,
+                        peer_addr,
+                        established_wall,
+                        peer_certificate: if peer_certificate.is_empty() {
+                            None
+                        } else {
+                            Some(peer_certificate[..].into())
+                        },
+                    },
+                    started: Instant::now(),
+                    events: VecDeque::new(),
+                    cseqs: HashMap::new(),
+                    stalled: false,
+                    next_pos: 0,
+                });
+                continue;
+            }
+            let conn = match ids.get(&id) {
+                Some(&i) => &mut conns[i],
+                None => return Err(invalid(format!("record for unknown connection {id}"))),
+            };
+            let elapsed = Duration::from_nanos(get_u64(&mut buf)?);
+            let wall = get_wall(&mut buf)?;
+            let event = match kind {
+                KIND_RECEIVED => {
+                    let pos = get_u64(&mut buf)?;
+                    let msg = get_message(&mut codec, &mut buf)?;
+                    Event::Received {
+                        elapsed,
+                        wall,
+                        pos,
+                        msg,
+                    }
+                }
+                KIND_SENT => Event::Sent {
+                    request: match get_message(&mut codec, &mut buf)? {
+                        Message::Request(r) => Some((
+                            r.method().clone(),
+                            r.header(&rtsp_types::headers::CSEQ)
+                                .and_then(|c| u32::from_str_radix(c.as_str(), 10).ok()),
+                        )),
+                        _ => None,
+                    },
+                },
+                KIND_UDP => {
+                    let stream_i = usize::from(get_u16(&mut buf)?);
+                    let rtcp = get_u8(&mut buf)? != 0;
+                    Event::Udp {
+                        stream_i,
+                        rtcp,
+                        wall,
+                        data: get_bytes(&mut buf)?,
+                    }
+                }
+                _ => return Err(invalid(format!("unknown record kind {kind}"))),
+            };
+            conn.events.push_back(event);
+        }
+        Ok(Replay(Arc::new(Mutex::new(conns.into()))))
+    }
+
+    /// Takes the next recorded connection, if any.
+    pub(crate) fn next_connection(&self) -> Option<ReplayConnection> {
+        let mut c = self.0.lock().unwrap().pop_front()?;
+        c.started = Instant::now();
+        Some(c)
+    }
+}
+
+fn invalid(description: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, description)
+}
+
+fn need(buf: &Bytes, len: usize) -> Result<(), std::io::Error> {
+    if buf.remaining() < len {
+        return Err(invalid("truncated capture".to_owned()));
+    }
+    Ok(())
+}
+
+fn get_u8(buf: &mut Bytes) -> Result<u8, std::io::Error> {
+    need(buf, 1)?;
+    Ok(buf.get_u8())
+}
+
+fn get_u16(buf: &mut Bytes) -> Result<u16, std::io::Error> {
+    need(buf, 2)?;
+    Ok(buf.get_u16())
+}
+
+fn get_u32(buf: &mut Bytes) -> Result<u32, std::io::Error> {
+    need(buf, 4)?;
+    Ok(buf.get_u32())
+}
+
+fn get_u64(buf: &mut Bytes) -> Result<u64, std::io::Error> {
+    need(buf, 8)?;
+    Ok(buf.get_u64())
+}
+
+fn get_wall(buf: &mut Bytes) -> Result<WallTime, std::io::Error> {
+    need(buf, 12)?;
+    let sec = buf.get_i64();
+    let nsec = buf.get_i32();
+    Ok(WallTime(time::Timespec { sec, nsec }))
+}
+
+fn get_addr(buf: &mut Bytes) -> Result<SocketAddr, std::io::Error> {
+    let len = usize::from(get_u8(buf)?);
+    need(buf, len)?;
+    let addr = buf.split_to(len);
+    std::str::from_utf8(&addr)
+        .ok()
+        .and_then(|a| a.parse().ok())
+        .ok_or_else(|| invalid("bad socket address".to_owned()))
+}
+
+fn get_bytes(buf: &mut Bytes) -> Result<Bytes, std::io::Error> {
+    let len = usize::try_from(get_u32(buf)?).expect("u32 fits in usize");
+    need(buf, len)?;
+    Ok(buf.split_to(len))
+}
+
+fn get_message(
+    codec: &mut crate::framing::Codec,
+    buf: &mut Bytes,
+) -> Result<Message<Bytes>, std::io::Error> {
+    let raw = get_bytes(buf)?;
+    let mut raw = BytesMut::from(&raw[..]);
+    match codec.decode(&mut raw) {
+        Ok(Some(m)) if raw.is_empty() => Ok(m.msg),
+        _ => Err(invalid("bad RTSP message".to_owned())),
+    }
+}
+
+/// A recorded event within a [`ReplayConnection`].
+enum Event {
+    Received {
+        elapsed: Duration,
+        wall: WallTime,
+        pos: u64,
+        msg: Message<Bytes>,
+    },
+
+    /// A message sent by the recorded session, with the method and `CSeq`
+    /// if it was a request.
+    Sent {
+        request: Option<(Method, Option<u32>)>,
+    },
+
+    Udp {
+        stream_i: usize,
+        rtcp: bool,
+        wall: WallTime,
+        data: Bytes,
+    },
+}
+
+/// A single recorded connection, which stands in for a real one while
+/// replaying.
+///
+/// Received messages are returned with their recorded contexts. Sent
+/// messages are discarded, but requests are matched to recorded ones of the
+/// same method so that responses can be returned with the replaying
+/// session's `CSeq`s. Responses to recorded requests which the replaying
+/// session doesn't send, such as keepalives, are dropped.
+pub(crate) struct ReplayConnection {
+    ctx: ConnectionContext,
+
+    /// When the replay of this connection started; received messages'
+    /// `Instant`s are offset from it.
+    started: Instant,
+
+    events: VecDeque<Event>,
+
+    /// Maps recorded requests' `CSeq`s to the replaying session's, or `None`
+    /// if the replaying session didn't send them.
+    cseqs: HashMap<u32, Option<u32>>,
+
+    /// True iff a poll found a UDP packet next and it hasn't been taken since.
+    stalled: bool,
+
+    /// The input stream position after the last returned message.
+    next_pos: u64,
+}
+
+impl ReplayConnection {
+    pub(crate) fn ctx(&self) -> &ConnectionContext {
+        &self.ctx
+    }
+
+    pub(crate) fn eof_ctx(&self) -> RtspMessageContext {
+        RtspMessageContext {
+            pos: self.next_pos,
+            received_wall: WallTime::now(),
+            received: Instant::now(),
+        }
+    }
+
+    /// Returns the next recorded message, skipping anything the replaying
+    /// session shouldn't see.
+    ///
+    /// When the next event is a UDP packet, returns `Poll::Pending` so the
+    /// session can take it via [`ReplayConnection::take_udp`]. If it's still
+    /// there on the following poll, no playing stream wants it, so it's
+    /// dropped.
+    pub(crate) fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<ReceivedMessage, Error>>> {
+        loop {
+            match self.events.front() {
+                None => return Poll::Ready(None),
+                Some(Event::Udp { stream_i, .. }) => {
+                    if !std::mem::replace(&mut self.stalled, true) {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                    debug!("Dropping replayed UDP packet for stream {}", stream_i);
+                    self.events.pop_front();
+                    self.stalled = false;
+                }
+                Some(Event::Sent { .. }) => {
+                    if let Some(Event::Sent {
+                        request: Some((_, Some(cseq))),
+                    }) = self.events.pop_front()
+                    {
+                        self.cseqs.insert(cseq, None);
+                    }
+                }
+                Some(Event::Received { .. }) => {
+                    let (elapsed, wall, pos, mut msg) = match self.events.pop_front() {
+                        Some(Event::Received {
+                            elapsed,
+                            wall,
+                            pos,
+                            msg,
+                        }) => (elapsed, wall, pos, msg),
+                        _ => unreachable!(),
+                    };
+                    if let Message::Response(r) = &mut msg {
+                        match super::parse::get_cseq(r).and_then(|c| self.cseqs.get(&c)) {
+                            Some(Some(cseq)) => {
+                                r.insert_header(rtsp_types::headers::CSEQ, cseq.to_string())
+                            }
+                            Some(None) => {
+                                debug!("Dropping replayed response to unreplayed request");
+                                continue;
+                            }
+                            None => {}
+                        }
+                    }
+                    let mut raw = Vec::new();
+                    msg.write(&mut raw).expect("Vec writer is infallible");
+                    self.next_pos = pos + u64::try_from(raw.len()).expect("usize fits in u64");
+                    return Poll::Ready(Some(Ok(ReceivedMessage {
+                        ctx: RtspMessageContext {
+                            pos,
+                            received_wall: wall,
+                            received: self.started + elapsed,
+                        },
+                        msg,
+                    })));
+                }
+            }
+        }
+    }
+
+    /// Notes a message sent by the replaying session.
+    pub(crate) fn sent(&mut self, msg: &Message<Bytes>) {
+        let req = match msg {
+            Message::Request(r) => r,
+            _ => return,
+        };
+        if !self
+            .events
+            .iter()
+            .any(|e| matches!(e, Event::Sent { request: Some((m, _)) } if m == req.method()))
+        {
+            debug!("No recorded {:?} request to replay", req.method());
+            return;
+        }
+        let cseq = req
+            .header(&rtsp_types::headers::CSEQ)
+            .and_then(|c| u32::from_str_radix(c.as_str(), 10).ok());
+
+        // Any requests recorded before this one weren't replayed.
+        let mut j = 0;
+        loop {
+            let (matched, recorded) = match &self.events[j] {
+                Event::Sent {
+                    request: Some((m, recorded)),
+                } => (m == req.method(), *recorded),
+                _ => {
+                    j += 1;
+                    continue;
+                }
+            };
+            self.events.remove(j);
+            if let Some(recorded) = recorded {
+                self.cseqs
+                    .insert(recorded, if matched { cseq } else { None });
+            }
+            if matched {
+                break;
+            }
+        }
+    }
+
+    /// Takes the next event if it's a UDP packet for the given stream,
+    /// returning whether it's RTCP, its received time, and its data.
+    pub(crate) fn take_udp(&mut self, stream_i: usize) -> Option<(bool, WallTime, Bytes)> {
+        match self.events.front() {
+            Some(Event::Udp { stream_i: s, .. }) if *s == stream_i => {}
+            _ => return None,
+        }
+        self.stalled = false;
+        match self.events.pop_front() {
+            Some(Event::Udp {
+                rtcp, wall, data, ..
+            }) => Some((rtcp, wall, data)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        PacketItem, PlayOptions, Session, SessionOptions, SetupOptions, Transport,
+    };
+    use crate::testing::{rtp_packet, MockServer, Script};
+    use crate::testutil::init_logging;
+    use futures::StreamExt;
+
+    const DESCRIBE: &[u8] = include_bytes!("testdata/reolink_describe.txt");
+    const SETUP: &[u8] = include_bytes!("testdata/reolink_setup.txt");
+    const PLAY: &[u8] = include_bytes!("testdata/reolink_play.txt");
+
+    /// A `Write` impl whose contents can be read after the capture is done.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Plays the first stream, returning the contexts and sequence numbers
+    /// of the first two RTP packets.
+    async fn play(url: url::Url, options: SessionOptions, transport: Transport) -> Vec<String> {
+        let mut session = Session::describe(url, options).await.unwrap();
+        session
+            .setup(0, SetupOptions::default().transport(transport))
+            .await
+            .unwrap();
+        let mut session = session.play(PlayOptions::default()).await.unwrap();
+        let mut out = Vec::new();
+        while out.len() < 2 {
+            match session.next().await {
+                Some(Ok(PacketItem::Rtp(p))) => {
+                    out.push(format!("{} {}", p.ctx(), p.sequence_number()))
+                }
+                Some(Ok(_)) => {}
+                o => panic!("unexpected item {o:#?}"),
+            }
+        }
+        out
+    }
+
+    async fn roundtrip(transport: Transport, script: Script) {
+        init_logging();
+        let server = MockServer::start([script]).await.unwrap();
+        let buf = SharedBuf::default();
+        let capture = Capture::new(buf.clone()).unwrap();
+        let url = server.url("/h264Preview_01_main");
+        let recorded = play(
+            url.clone(),
+            SessionOptions::default().capture(Some(capture)),
+            transport.clone(),
+        )
+        .await;
+        server.finish().await.unwrap();
+
+        // The replay needs no server and matches exactly.
+        let replay = Replay::read(&buf.0.lock().unwrap()[..]).unwrap();
+        let replayed = play(
+            url,
+            SessionOptions::default().replay(Some(replay)),
+            transport,
+        )
+        .await;
+        assert_eq!(recorded, replayed);
+    }
+
+    #[tokio::test]
+    async fn tcp() {
+        roundtrip(
+            Transport::Tcp(Default::default()),
+            Script::new()
+                .respond("DESCRIBE", DESCRIBE)
+                .respond("SETUP", SETUP)
+                .respond("PLAY", PLAY)
+                .interleaved(
+                    0,
+                    rtp_packet(96, 16852, 1070938629, 0xdcc4a0d8, true, b"\x06"),
+                )
+                .interleaved(
+                    0,
+                    rtp_packet(96, 16853, 1070938629, 0xdcc4a0d8, true, b"\x06"),
+                ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn udp() {
+        roundtrip(
+            Transport::Udp(Default::default()),
+            Script::new()
+                .respond("DESCRIBE", DESCRIBE)
+                .respond("SETUP", SETUP)
+                .respond("PLAY", PLAY)
+                .udp_rtp(
+                    0,
+                    rtp_packet(96, 16852, 1070938629, 0xdcc4a0d8, true, b"\x06"),
+                )
+                .udp_rtp(
+                    0,
+                    rtp_packet(96, 16853, 1070938629, 0xdcc4a0d8, true, b"\x06"),
+                ),
+        )
+        .await;
+    }
+
+    /// Credentials sent to the server aren't recorded.
+    #[test]
+    fn strips_credentials() {
+        let buf = SharedBuf::default();
+        let capture = Capture::new(buf.clone()).unwrap();
+        let conn = capture.connection(&ConnectionContext::dummy());
+        conn.sent(&Message::Request(
+            rtsp_types::Request::builder(Method::Describe, rtsp_types::Version::V1_0)
+                .request_uri(url::Url::parse("rtsp://example.com/").unwrap())
+                .header(rtsp_types::headers::CSEQ, "1")
+                .header(rtsp_types::headers::AUTHORIZATION, "Basic c2VjcmV0")
+                .header(rtsp_types::headers::PROXY_AUTHORIZATION, "Basic c2VjcmV0")
+                .build(Bytes::new()),
+        ));
+        let raw = buf.0.lock().unwrap().clone();
+        let text = String::from_utf8_lossy(&raw);
+        assert!(text.contains("DESCRIBE rtsp://example.com/"), "{text}");
+        assert!(!text.contains("c2VjcmV0"), "{text}");
+
+        // Replay still sees the method and CSeq.
+        let replay = Replay::read(&raw[..]).unwrap();
+        let conn = replay.0.lock().unwrap().pop_front().unwrap();
+        assert!(matches!(
+            conn.events.front(),
+            Some(Event::Sent {
+                request: Some((Method::Describe, Some(1)))
+            })
+        ));
+    }
+
+    /// Responses to recorded requests which aren't replayed, such as
+    /// keepalives, are dropped; others get the replaying session's `CSeq`.
+    #[test]
+    fn cseq_mapping() {
+        let response = |cseq: u32| Event::Received {
+            elapsed: Duration::ZERO,
+            wall: WallTime::now(),
+            pos: 0,
+            msg: Message::Response(
+                rtsp_types::Response::builder(
+                    rtsp_types::Version::V1_0,
+                    rtsp_types::StatusCode::Ok,
+                )
+                .header(rtsp_types::headers::CSEQ, cseq.to_string())
+                .build(Bytes::new()),
+            ),
+        };
+        let mut conn = ReplayConnection {
+            ctx: ConnectionContext::dummy(),
+            started: Instant::now(),
+            events: vec![
+                Event::Sent {
+                    request: Some((Method::Options, Some(4))),
+                },
+                response(4),
+                Event::Sent {
+                    request: Some((Method::Teardown, Some(5))),
+                },
+                response(5),
+            ]
+            .into(),
+            cseqs: HashMap::new(),
+            stalled: false,
+            next_pos: 0,
+        };
+        let url = url::Url::parse("rtsp://example.com/").unwrap();
+        conn.sent(&Message::Request(
+            rtsp_types::Request::builder(Method::Teardown, rtsp_types::Version::V1_0)
+                .request_uri(url)
+                .header(rtsp_types::headers::CSEQ, "4")
+                .build(Bytes::new()),
+        ));
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        match conn.poll_next(&mut cx) {
+            Poll::Ready(Some(Ok(ReceivedMessage {
+                msg: Message::Response(r),
+                ..
+            }))) => assert_eq!(crate::client::parse::get_cseq(&r), Some(4)),
+            _ => panic!("expected response"),
+        }
+        assert!(matches!(conn.poll_next(&mut cx), Poll::Ready(None)));
+    }
+}
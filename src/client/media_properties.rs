@@ -0,0 +1,240 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The RTSP/2.0 `Media-Properties` header, as described in
+//! [RFC 7826 section 18.29](https://datatracker.ietf.org/doc/html/rfc7826#section-18.29).
+
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use super::range::{parse_clock, parse_digits, parse_nanos};
+use crate::NtpTimestamp;
+
+/// Properties of the media, as sent by RTSP/2.0 servers in `SETUP` and `PLAY` responses.
+///
+/// Available via [`super::Session::media_properties`]. Each property is `None`
+/// if the server didn't specify it. Unknown properties are ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaProperties {
+    random_access: Option<RandomAccess>,
+    content_modifications: Option<ContentModifications>,
+    retention: Option<Retention>,
+    scales: Vec<RangeInclusive<f32>>,
+}
+
+/// Whether and how the client may seek within the media.
+///
+/// See [RFC 7826 section 4.9.1](https://datatracker.ietf.org/doc/html/rfc7826#section-4.9.1).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RandomAccess {
+    /// `Random-Access`: seeking to any point is supported.
+    ///
+    /// `max_delta`, if specified, is the maximum time between random access
+    /// points, such as I frames.
+    RandomAccess { max_delta: Option<Duration> },
+
+    /// `Beginning-Only`: seeking is only supported to the beginning of the content.
+    BeginningOnly,
+
+    /// `No-Seeking`: seeking isn't supported, as for a live stream.
+    NoSeeking,
+}
+
+/// How the content may change over time.
+///
+/// See [RFC 7826 section 4.9.2](https://datatracker.ietf.org/doc/html/rfc7826#section-4.9.2).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ContentModifications {
+    /// `Immutable`: the content won't change.
+    Immutable,
+
+    /// `Dynamic`: the content may change, e.g. as a playlist is edited.
+    Dynamic,
+
+    /// `Time-Progressing`: the content grows over time, as for a live stream.
+    TimeProgressing,
+}
+
+/// How long the content is retained.
+///
+/// See [RFC 7826 section 4.9.3](https://datatracker.ietf.org/doc/html/rfc7826#section-4.9.3).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Retention {
+    /// `Unlimited`: the content is retained for the life of the session.
+    Unlimited,
+
+    /// `Time-Limited`: the content is retained until the given wallclock time.
+    TimeLimited(NtpTimestamp),
+
+    /// `Time-Duration`: each unit of media is retained for the given duration.
+    TimeDuration(Duration),
+}
+
+impl MediaProperties {
+    pub fn random_access(&self) -> Option<RandomAccess> {
+        self.random_access
+    }
+
+    pub fn content_modifications(&self) -> Option<ContentModifications> {
+        self.content_modifications
+    }
+
+    pub fn retention(&self) -> Option<Retention> {
+        self.retention
+    }
+
+    /// Returns the supported playback scales, as given by the `Scales` property.
+    ///
+    /// Each entry is either a single value (represented as a range with equal
+    /// bounds) or an inclusive range of values. This is empty if the server
+    /// didn't specify the property.
+    pub fn scales(&self) -> &[RangeInclusive<f32>] {
+        &self.scales
+    }
+
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        let mut props = MediaProperties::default();
+        for prop in split_unquoted(s, ',') {
+            let prop = prop.trim();
+            if prop.is_empty() {
+                continue;
+            }
+            let (name, value) = match prop.split_once('=') {
+                Some((n, v)) => (n.trim(), Some(v.trim())),
+                None => (prop, None),
+            };
+            let bad = || format!("bad media property {prop:?}");
+            match (name, value) {
+                ("Random-Access", None) => {
+                    props.random_access = Some(RandomAccess::RandomAccess { max_delta: None });
+                }
+                ("Random-Access", Some(v)) => {
+                    props.random_access = Some(RandomAccess::RandomAccess {
+                        max_delta: Some(parse_pos_float(v).ok_or_else(bad)?),
+                    });
+                }
+                ("Beginning-Only", None) => props.random_access = Some(RandomAccess::BeginningOnly),
+                ("No-Seeking", None) => props.random_access = Some(RandomAccess::NoSeeking),
+                ("Immutable", None) => {
+                    props.content_modifications = Some(ContentModifications::Immutable)
+                }
+                ("Dynamic", None) => {
+                    props.content_modifications = Some(ContentModifications::Dynamic)
+                }
+                ("Time-Progressing", None) => {
+                    props.content_modifications = Some(ContentModifications::TimeProgressing)
+                }
+                ("Unlimited", None) => props.retention = Some(Retention::Unlimited),
+                ("Time-Limited", Some(v)) => {
+                    props.retention = Some(Retention::TimeLimited(parse_clock(v)?));
+                }
+                ("Time-Duration", Some(v)) => {
+                    props.retention =
+                        Some(Retention::TimeDuration(parse_pos_float(v).ok_or_else(bad)?));
+                }
+                ("Scales", Some(v)) => {
+                    let v = v
+                        .strip_prefix('"')
+                        .and_then(|v| v.strip_suffix('"'))
+                        .ok_or_else(bad)?;
+                    props.scales = v
+                        .split(',')
+                        .map(|e| {
+                            let (lo, hi) = e.split_once(':').unwrap_or((e, e));
+                            let lo = lo.trim().parse::<f32>().map_err(|_| bad())?;
+                            let hi = hi.trim().parse::<f32>().map_err(|_| bad())?;
+                            Ok(lo..=hi)
+                        })
+                        .collect::<Result<_, String>>()?;
+                }
+                (
+                    "Beginning-Only" | "No-Seeking" | "Immutable" | "Dynamic" | "Time-Progressing"
+                    | "Unlimited" | "Time-Limited" | "Time-Duration" | "Scales",
+                    _,
+                ) => return Err(bad()),
+                _ => {}
+            }
+        }
+        Ok(props)
+    }
+}
+
+/// Splits `s` on `sep`, except within double-quoted strings.
+pub(crate) fn split_unquoted(s: &str, sep: char) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    s.split(move |c| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == sep && !quoted
+    })
+}
+
+/// Parses a `POS-FLOAT` number of seconds such as `2.5`.
+fn parse_pos_float(s: &str) -> Option<Duration> {
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    Some(Duration::new(parse_digits(whole)?, parse_nanos(frac)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live() {
+        let p = MediaProperties::parse("No-Seeking, Time-Progressing, Time-Duration=0.0").unwrap();
+        assert_eq!(p.random_access(), Some(RandomAccess::NoSeeking));
+        assert_eq!(
+            p.content_modifications(),
+            Some(ContentModifications::TimeProgressing)
+        );
+        assert_eq!(p.retention(), Some(Retention::TimeDuration(Duration::ZERO)));
+        assert!(p.scales().is_empty());
+    }
+
+    /// Tests an on-demand presentation which supports a variety of scales.
+    #[test]
+    fn on_demand() {
+        let p = MediaProperties::parse(
+            "Random-Access=2.5, Unlimited, Immutable, Scales=\"-20, -10, -4, 0.5:1.5, 4, 8, 10, 15, 20\"",
+        )
+        .unwrap();
+        assert_eq!(
+            p.random_access(),
+            Some(RandomAccess::RandomAccess {
+                max_delta: Some(Duration::from_millis(2_500))
+            })
+        );
+        assert_eq!(
+            p.content_modifications(),
+            Some(ContentModifications::Immutable)
+        );
+        assert_eq!(p.retention(), Some(Retention::Unlimited));
+        assert_eq!(p.scales().len(), 9);
+        assert_eq!(p.scales()[0], -20.0..=-20.0);
+        assert_eq!(p.scales()[3], 0.5..=1.5);
+    }
+
+    #[test]
+    fn time_limited() {
+        let p = MediaProperties::parse("Random-Access, Time-Limited=20081128T165900Z").unwrap();
+        assert_eq!(
+            p.random_access(),
+            Some(RandomAccess::RandomAccess { max_delta: None })
+        );
+        assert!(matches!(p.retention(), Some(Retention::TimeLimited(_))));
+    }
+
+    #[test]
+    fn ignores_unknown() {
+        let p = MediaProperties::parse("Beginning-Only, Foo=\"a,b\"").unwrap();
+        assert_eq!(p.random_access(), Some(RandomAccess::BeginningOnly));
+    }
+
+    #[test]
+    fn bad() {
+        MediaProperties::parse("Random-Access=fast").unwrap_err();
+        MediaProperties::parse("No-Seeking=1").unwrap_err();
+        MediaProperties::parse("Scales=1").unwrap_err();
+    }
+}
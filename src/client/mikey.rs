@@ -0,0 +1,611 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! MIKEY (Multimedia Internet KEYing) messages carrying SRTP keys, as
+//! described in [RFC 3830](https://datatracker.ietf.org/doc/html/rfc3830) and
+//! carried in SDP `a=key-mgmt` attributes as described in
+//! [RFC 4567](https://datatracker.ietf.org/doc/html/rfc4567).
+//!
+//! Only the pre-shared key method is supported. This includes messages with
+//! `NULL` encryption and MAC algorithms, which servers send when the RTSP
+//! connection itself is protected, as with `rtsps` URLs. Public-key and
+//! Diffie-Hellman messages aren't supported.
+
+use aes::cipher::{InnerIvInit, KeyInit, StreamCipher};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use super::srtp::{MasterKey, Suite};
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+// Payload types; see RFC 3830 section 6.
+const PAYLOAD_LAST: u8 = 0;
+const PAYLOAD_KEMAC: u8 = 1;
+const PAYLOAD_T: u8 = 5;
+const PAYLOAD_ID: u8 = 6;
+const PAYLOAD_SP: u8 = 10;
+const PAYLOAD_RAND: u8 = 11;
+const PAYLOAD_GENERAL_EXT: u8 = 21;
+
+/// The `IDR` payload type, from [RFC 6043](https://datatracker.ietf.org/doc/html/rfc6043).
+const PAYLOAD_IDR: u8 = 14;
+
+/// The `Initiator's pre-shared key message` data type.
+const DATA_TYPE_PSK_INIT: u8 = 0;
+
+/// The `SRTP-ID` crypto session ID map type.
+const CS_ID_MAP_SRTP: u8 = 0;
+
+// Key derivation constants; see RFC 3830 sections 4.1.3 and 4.1.4.
+const LABEL_TEK: u32 = 0x2AD0_1C64;
+const LABEL_TEK_SALT: u32 = 0x39A2_C14B;
+const LABEL_ENCR_KEY: u32 = 0x1505_33E1;
+const LABEL_AUTH_KEY: u32 = 0x2D22_AC75;
+const LABEL_SALT_KEY: u32 = 0x29B8_8916;
+
+/// The crypto session ID used when deriving keys for the MIKEY message
+/// itself rather than for a crypto session.
+const CS_ID_MESSAGE: u8 = 0xFF;
+
+const HMAC_SHA1_LEN: usize = 20;
+
+/// A parsed MIKEY message, as offered for a single stream.
+#[derive(Clone)]
+pub(crate) struct Message {
+    /// The raw message, needed to verify the MAC.
+    raw: Box<[u8]>,
+
+    csb_id: u32,
+    rand: Box<[u8]>,
+
+    /// The `T` payload's timestamp, zero-extended to 64 bits.
+    timestamp: u64,
+
+    suite: Suite,
+    kemac: Kemac,
+}
+
+impl std::fmt::Debug for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't log the (possibly unencrypted) key material.
+        f.debug_struct("Message")
+            .field("csb_id", &self.csb_id)
+            .field("suite", &self.suite)
+            .field("encryption", &self.kemac.encryption)
+            .field("mac", &self.kemac.mac)
+            .finish()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Encryption {
+    Null,
+    AesCm128,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MacAlgorithm {
+    Null,
+    HmacSha1_160,
+}
+
+/// The location of the `KEMAC` payload's contents within [`Message::raw`].
+#[derive(Clone)]
+struct Kemac {
+    encryption: Encryption,
+    encrypted: std::ops::Range<usize>,
+    mac: MacAlgorithm,
+
+    /// The start of the MAC, which covers everything before it.
+    mac_pos: usize,
+}
+
+/// A simple cursor over a MIKEY message.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let b = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| format!("MIKEY message truncated at byte {}", self.pos))?;
+        self.pos += len;
+        Ok(b)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+impl Message {
+    /// Parses the value of an `a=key-mgmt` attribute, as described in RFC 4567
+    /// section 3.1.
+    ///
+    /// Returns `Ok(None)` for key management protocols other than MIKEY.
+    pub(crate) fn parse_key_mgmt(value: &str) -> Result<Option<Self>, String> {
+        // key-mgmt-att = "key-mgmt:" prtcl-id SP keymgmt-data
+        let (protocol, data) = value
+            .split_once(' ')
+            .ok_or_else(|| format!("bad key-mgmt attribute {value:?}"))?;
+        if !protocol.eq_ignore_ascii_case("mikey") {
+            return Ok(None);
+        }
+        let raw = base64::decode(data.trim()).map_err(|_| "bad MIKEY base64".to_owned())?;
+        Self::parse(raw.into_boxed_slice()).map(Some)
+    }
+
+    fn parse(raw: Box<[u8]>) -> Result<Self, String> {
+        let mut r = Reader { data: &raw, pos: 0 };
+
+        // Common header; see RFC 3830 section 6.1.
+        let version = r.u8()?;
+        if version != 1 {
+            return Err(format!("unsupported MIKEY version {version}"));
+        }
+        let data_type = r.u8()?;
+        if data_type != DATA_TYPE_PSK_INIT {
+            return Err(format!(
+                "unsupported MIKEY data type {data_type}; only pre-shared keys are supported"
+            ));
+        }
+        let mut next_payload = r.u8()?;
+        let prf = r.u8()? & 0x7f;
+        if prf != 0 {
+            return Err(format!("unsupported MIKEY PRF {prf}"));
+        }
+        let csb_id = r.u32()?;
+        let num_cs = r.u8()?;
+        let cs_id_map_type = r.u8()?;
+        if cs_id_map_type != CS_ID_MAP_SRTP {
+            return Err(format!("unsupported MIKEY CS ID map type {cs_id_map_type}"));
+        }
+
+        // Each entry is Policy_no_i || SSRC_i || ROC_i. Use the first crypto
+        // session's policy for the stream. Rollover counters are assumed to
+        // start at zero, as they do for keys from SDES.
+        let mut policy_no = None;
+        for _ in 0..num_cs {
+            let p = r.u8()?;
+            r.bytes(8)?;
+            policy_no.get_or_insert(p);
+        }
+
+        let mut rand = None;
+        let mut timestamp = None;
+        let mut suite = None;
+        let mut kemac = None;
+        while next_payload != PAYLOAD_LAST {
+            let payload = next_payload;
+            next_payload = r.u8()?;
+            match payload {
+                PAYLOAD_T => {
+                    // RFC 3830 section 6.6.
+                    let ts = match r.u8()? {
+                        0 | 1 => r.bytes(8)?,
+                        2 => r.bytes(4)?,
+                        t => return Err(format!("unknown MIKEY timestamp type {t}")),
+                    };
+                    timestamp = Some(ts.iter().fold(0u64, |acc, &b| acc << 8 | u64::from(b)));
+                }
+                PAYLOAD_RAND => {
+                    // RFC 3830 section 6.11.
+                    let len = r.u8()?;
+                    rand = Some(Box::<[u8]>::from(r.bytes(usize::from(len))?));
+                }
+                PAYLOAD_ID => {
+                    // RFC 3830 section 6.7.
+                    r.u8()?;
+                    let len = r.u16()?;
+                    r.bytes(usize::from(len))?;
+                }
+                PAYLOAD_IDR => {
+                    // RFC 6043 section 6.6.
+                    r.bytes(2)?;
+                    let len = r.u16()?;
+                    r.bytes(usize::from(len))?;
+                }
+                PAYLOAD_GENERAL_EXT => {
+                    // RFC 3830 section 6.15.
+                    r.u8()?;
+                    let len = r.u16()?;
+                    r.bytes(usize::from(len))?;
+                }
+                PAYLOAD_SP => {
+                    // RFC 3830 section 6.10.
+                    let no = r.u8()?;
+                    let prot_type = r.u8()?;
+                    let len = r.u16()?;
+                    let params = r.bytes(usize::from(len))?;
+                    if prot_type == 0 && (policy_no.is_none() || policy_no == Some(no)) {
+                        suite = Some(parse_srtp_policy(params)?);
+                    }
+                }
+                PAYLOAD_KEMAC => {
+                    // RFC 3830 section 6.2. This must be the last payload of
+                    // a pre-shared key message.
+                    if next_payload != PAYLOAD_LAST {
+                        return Err("MIKEY KEMAC payload isn't last".to_owned());
+                    }
+                    let encryption = match r.u8()? {
+                        0 => Encryption::Null,
+                        1 => Encryption::AesCm128,
+                        a => return Err(format!("unsupported MIKEY encryption algorithm {a}")),
+                    };
+                    let len = r.u16()?;
+                    let start = r.pos;
+                    r.bytes(usize::from(len))?;
+                    let encrypted = start..r.pos;
+                    let (mac, mac_len) = match r.u8()? {
+                        0 => (MacAlgorithm::Null, 0),
+                        1 => (MacAlgorithm::HmacSha1_160, HMAC_SHA1_LEN),
+                        a => return Err(format!("unsupported MIKEY MAC algorithm {a}")),
+                    };
+                    let mac_pos = r.pos;
+                    r.bytes(mac_len)?;
+                    kemac = Some(Kemac {
+                        encryption,
+                        encrypted,
+                        mac,
+                        mac_pos,
+                    });
+                }
+                p => return Err(format!("unsupported MIKEY payload type {p}")),
+            }
+        }
+        let kemac = kemac.ok_or_else(|| "MIKEY message has no KEMAC payload".to_owned())?;
+        let rand = rand.ok_or_else(|| "MIKEY message has no RAND payload".to_owned())?;
+        let timestamp = timestamp.ok_or_else(|| "MIKEY message has no T payload".to_owned())?;
+        Ok(Message {
+            csb_id,
+            rand,
+            timestamp,
+            suite: suite.unwrap_or(Suite::AesCm128HmacSha1_80),
+            kemac,
+            raw,
+        })
+    }
+
+    /// Verifies and decrypts the `KEMAC` payload, returning the SRTP master key.
+    ///
+    /// `psk` is required unless the message uses `NULL` encryption and MAC algorithms.
+    pub(crate) fn master_key(&self, psk: Option<&[u8]>) -> Result<MasterKey, String> {
+        let kemac = &self.kemac;
+        let needs_psk = kemac.encryption != Encryption::Null || kemac.mac != MacAlgorithm::Null;
+        let psk = match psk {
+            Some(psk) => psk,
+            None if needs_psk => {
+                return Err("MIKEY message requires a pre-shared key; see \
+                            SessionOptions::mikey_psk"
+                    .to_owned())
+            }
+            None => &[],
+        };
+        if kemac.mac == MacAlgorithm::HmacSha1_160 {
+            let mut auth_key = [0; HMAC_SHA1_LEN];
+            self.prf(psk, LABEL_AUTH_KEY, CS_ID_MESSAGE, &mut auth_key);
+            let mut hmac = <Hmac<Sha1> as KeyInit>::new_from_slice(&auth_key)
+                .expect("HMAC accepts any key length");
+            hmac.update(&self.raw[..kemac.mac_pos]);
+            hmac.verify_slice(&self.raw[kemac.mac_pos..kemac.mac_pos + HMAC_SHA1_LEN])
+                .map_err(|_| "MIKEY MAC mismatch; is the pre-shared key correct?".to_owned())?;
+        }
+        let mut key_data = self.raw[kemac.encrypted.clone()].to_vec();
+        if kemac.encryption == Encryption::AesCm128 {
+            // RFC 3830 section 4.2.3: IV = (S XOR (0x0000 || CSB ID || T)) * 2^16.
+            let mut encr_key = [0; 16];
+            self.prf(psk, LABEL_ENCR_KEY, CS_ID_MESSAGE, &mut encr_key);
+            let mut iv = [0; 16];
+            self.prf(psk, LABEL_SALT_KEY, CS_ID_MESSAGE, &mut iv[..14]);
+            for (i, b) in self.csb_id.to_be_bytes().into_iter().enumerate() {
+                iv[2 + i] ^= b;
+            }
+            for (i, b) in self.timestamp.to_be_bytes().into_iter().enumerate() {
+                iv[6 + i] ^= b;
+            }
+            Aes128Ctr::from_core(ctr::CtrCore::inner_iv_init(
+                aes::Aes128::new(&encr_key.into()),
+                &iv.into(),
+            ))
+            .apply_keystream(&mut key_data);
+        }
+        self.parse_key_data(&key_data)
+    }
+
+    /// Parses the first `Key data` sub-payload, as described in RFC 3830
+    /// section 6.13, into an SRTP master key.
+    fn parse_key_data(&self, data: &[u8]) -> Result<MasterKey, String> {
+        let mut r = Reader { data, pos: 0 };
+        r.u8()?; // next payload
+        let type_kv = r.u8()?;
+        let len = r.u16()?;
+        let key = r.bytes(usize::from(len))?;
+        let (is_tgk, salt) = match type_kv >> 4 {
+            0 => (true, None),
+            1 => (true, Some(())),
+            2 => (false, None),
+            3 => (false, Some(())),
+            t => return Err(format!("unknown MIKEY key data type {t}")),
+        };
+        let salt = match salt {
+            Some(()) => {
+                let len = r.u16()?;
+                Some(r.bytes(usize::from(len))?)
+            }
+            None => None,
+        };
+        match type_kv & 0xf {
+            0 | 2 => {} // no key validity data, or an interval, which is ignored as for SDES.
+            1 => return Err("MIKEY master key identifiers (MKIs) aren't supported".to_owned()),
+            kv => return Err(format!("unknown MIKEY key validity type {kv}")),
+        }
+        let salt_len = self.suite.salt_len();
+        if !is_tgk {
+            // A TEK is the SRTP master key itself.
+            let salt = salt.ok_or_else(|| "MIKEY TEK has no salt".to_owned())?;
+            return MasterKey::new(self.suite, key, salt);
+        }
+
+        // Derive the master key and salt from the TGK. RFC 3830 section
+        // 4.1.3. Crypto sessions are numbered from 1.
+        let mut tek = [0; 16];
+        self.prf(key, LABEL_TEK, 1, &mut tek);
+        let mut derived_salt = [0; 14];
+        let derived_salt = match salt {
+            Some(s) => s,
+            None => {
+                self.prf(key, LABEL_TEK_SALT, 1, &mut derived_salt[..salt_len]);
+                &derived_salt[..salt_len]
+            }
+        };
+        MasterKey::new(self.suite, &tek, derived_salt)
+    }
+
+    /// Fills `out` via the MIKEY PRF, as described in RFC 3830 section 4.1.2,
+    /// with `label = constant || cs_id || csb_id || RAND`.
+    fn prf(&self, inkey: &[u8], constant: u32, cs_id: u8, out: &mut [u8]) {
+        let mut label = Vec::with_capacity(9 + self.rand.len());
+        label.extend_from_slice(&constant.to_be_bytes());
+        label.push(cs_id);
+        label.extend_from_slice(&self.csb_id.to_be_bytes());
+        label.extend_from_slice(&self.rand);
+        out.fill(0);
+        if inkey.is_empty() {
+            p_sha1(&[], &label, out);
+        }
+        for s in inkey.chunks(32) {
+            p_sha1(s, &label, out);
+        }
+    }
+}
+
+/// XORs `P(s, label, m)` into `out`, as described in RFC 3830 section 4.1.2.
+fn p_sha1(s: &[u8], label: &[u8], out: &mut [u8]) {
+    let hmac = <Hmac<Sha1> as KeyInit>::new_from_slice(s).expect("HMAC accepts any key length");
+    let mut a = label.to_vec(); // A_0
+    for chunk in out.chunks_mut(HMAC_SHA1_LEN) {
+        let mut h = hmac.clone();
+        h.update(&a);
+        a = h.finalize().into_bytes().to_vec(); // A_i
+        let mut h = hmac.clone();
+        h.update(&a);
+        h.update(label);
+        for (o, b) in chunk.iter_mut().zip(h.finalize().into_bytes()) {
+            *o ^= b;
+        }
+    }
+}
+
+/// Parses the parameters of an SRTP security policy payload, as described in
+/// RFC 3830 section 6.10.1, into a crypto suite.
+fn parse_srtp_policy(mut params: &[u8]) -> Result<Suite, String> {
+    let mut encryption = 1; // AES-CM
+    let mut tag_len = None;
+    while !params.is_empty() {
+        let (type_, value, rest) = match params {
+            [type_, len, rest @ ..] if rest.len() >= usize::from(*len) => {
+                let (value, rest) = rest.split_at(usize::from(*len));
+                (*type_, value, rest)
+            }
+            _ => return Err("truncated MIKEY SRTP policy".to_owned()),
+        };
+        params = rest;
+        let n = value.iter().fold(0u64, |acc, &b| acc << 8 | u64::from(b));
+        match (type_, n) {
+            (0, _) => encryption = n,
+            (1, 16) => {}                   // session encryption key length
+            (2, 1) | (3, 20) => {}          // HMAC-SHA1 authentication
+            (2, 0) if encryption == 7 => {} // NULL authentication, with GCM
+            (4, _) => {}                    // session salt length, checked with the key
+            (5, 0) => {}                    // AES-CM PRF
+            (6, 0) => {}                    // key derivation rate
+            (7, 1) | (8, 1) | (10, 1) => {} // SRTP encryption, SRTCP encryption, SRTP authentication on
+            (9, _) => {}                    // FEC order
+            (11, _) => tag_len = Some(n),
+            (12, 0) => {} // SRTP prefix length
+            _ => {
+                return Err(format!(
+                    "unsupported MIKEY SRTP policy parameter {type_} = {n}"
+                ))
+            }
+        }
+    }
+    match (encryption, tag_len) {
+        (1, None | Some(10)) => Ok(Suite::AesCm128HmacSha1_80),
+        (1, Some(4)) => Ok(Suite::AesCm128HmacSha1_32),
+
+        // AES-GCM, as registered in RFC 7714 section 14.2.
+        (7, None | Some(16)) => Ok(Suite::AeadAes128Gcm),
+        _ => Err(format!(
+            "unsupported MIKEY SRTP encryption algorithm {encryption} with tag length {tag_len:?}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSB_ID: u32 = 0x1234_5678;
+    const T: [u8; 8] = *b"\xe5\x6a\x26\x1a\x00\x00\x00\x00";
+    const RAND: [u8; 16] = *b"\x7c\x8d\x51\x49\x2e\x5a\x3b\x45\x11\x9d\x7b\x2f\x04\xaa\x80\x3d";
+    const TEK: [u8; 16] = *b"\xe1\xf9\x7a\x0d\x3e\x01\x8b\xe0\xd6\x4f\xa3\x2c\x06\xde\x41\x39";
+    const SALT: [u8; 14] = *b"\x0e\xc6\x75\xad\x49\x8a\xfe\xeb\xb6\x96\x0b\x3a\xab\xe6";
+
+    /// Builds a pre-shared key message with the given policy and key data
+    /// sub-payload, encrypting and authenticating it if `psk` is given.
+    fn build(policy: &[u8], key_data: &[u8], psk: Option<&[u8]>) -> Vec<u8> {
+        let mut m = vec![1, DATA_TYPE_PSK_INIT, PAYLOAD_T, 0];
+        m.extend_from_slice(&CSB_ID.to_be_bytes());
+        m.extend_from_slice(&[1, CS_ID_MAP_SRTP, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        m.extend_from_slice(&[PAYLOAD_RAND, 0]);
+        m.extend_from_slice(&T);
+        m.extend_from_slice(&[PAYLOAD_SP, RAND.len() as u8]);
+        m.extend_from_slice(&RAND);
+        m.extend_from_slice(&[PAYLOAD_KEMAC, 0, 0]);
+        m.extend_from_slice(&(policy.len() as u16).to_be_bytes());
+        m.extend_from_slice(policy);
+
+        // The KEMAC payload. Build a template message to get the keys.
+        let template = Message {
+            raw: Box::new([]),
+            csb_id: CSB_ID,
+            rand: RAND.into(),
+            timestamp: u64::from_be_bytes(T),
+            suite: Suite::AesCm128HmacSha1_80,
+            kemac: Kemac {
+                encryption: Encryption::Null,
+                encrypted: 0..0,
+                mac: MacAlgorithm::Null,
+                mac_pos: 0,
+            },
+        };
+        let mut key_data = key_data.to_vec();
+        m.extend_from_slice(&[PAYLOAD_LAST, u8::from(psk.is_some())]);
+        m.extend_from_slice(&(key_data.len() as u16).to_be_bytes());
+        if let Some(psk) = psk {
+            let mut encr_key = [0; 16];
+            template.prf(psk, LABEL_ENCR_KEY, CS_ID_MESSAGE, &mut encr_key);
+            let mut iv = [0; 16];
+            template.prf(psk, LABEL_SALT_KEY, CS_ID_MESSAGE, &mut iv[..14]);
+            for (i, b) in CSB_ID.to_be_bytes().into_iter().chain(T).enumerate() {
+                iv[2 + i] ^= b;
+            }
+            Aes128Ctr::from_core(ctr::CtrCore::inner_iv_init(
+                aes::Aes128::new(&encr_key.into()),
+                &iv.into(),
+            ))
+            .apply_keystream(&mut key_data);
+            m.extend_from_slice(&key_data);
+            m.push(1);
+            let mut auth_key = [0; HMAC_SHA1_LEN];
+            template.prf(psk, LABEL_AUTH_KEY, CS_ID_MESSAGE, &mut auth_key);
+            let mut hmac = <Hmac<Sha1> as KeyInit>::new_from_slice(&auth_key).unwrap();
+            hmac.update(&m);
+            m.extend_from_slice(&hmac.finalize().into_bytes());
+        } else {
+            m.extend_from_slice(&key_data);
+            m.push(0);
+        }
+        m
+    }
+
+    /// A `TEK+SALT` key data sub-payload with no key validity data.
+    fn tek_and_salt(salt: &[u8]) -> Vec<u8> {
+        let mut d = vec![0, 3 << 4, 0, 16];
+        d.extend_from_slice(&TEK);
+        d.extend_from_slice(&(salt.len() as u16).to_be_bytes());
+        d.extend_from_slice(salt);
+        d
+    }
+
+    fn key_mgmt(raw: &[u8]) -> String {
+        format!("mikey {}", base64::encode(raw))
+    }
+
+    /// Tests a message with `NULL` encryption and MAC, as typically sent over
+    /// `rtsps`. The default policy is `AES_CM_128_HMAC_SHA1_80`.
+    #[test]
+    fn null() {
+        let raw = build(&[], &tek_and_salt(&SALT), None);
+        let m = Message::parse_key_mgmt(&key_mgmt(&raw)).unwrap().unwrap();
+        assert_eq!(
+            m.master_key(None).unwrap(),
+            MasterKey::new(Suite::AesCm128HmacSha1_80, &TEK, &SALT).unwrap()
+        );
+    }
+
+    /// Tests a message encrypted and authenticated with a pre-shared key.
+    #[test]
+    fn psk() {
+        let psk = b"correct horse battery staple";
+        let policy = [11, 1, 4]; // 32-bit authentication tag
+        let raw = build(&policy, &tek_and_salt(&SALT), Some(psk));
+        let m = Message::parse_key_mgmt(&key_mgmt(&raw)).unwrap().unwrap();
+        m.master_key(None).unwrap_err();
+        m.master_key(Some(b"wrong")).unwrap_err();
+        assert_eq!(
+            m.master_key(Some(psk)).unwrap(),
+            MasterKey::new(Suite::AesCm128HmacSha1_32, &TEK, &SALT).unwrap()
+        );
+
+        // Any modification should fail authentication.
+        let mut tampered = raw.clone();
+        let last = tampered.len() - HMAC_SHA1_LEN - 1;
+        tampered[last - 1] ^= 1;
+        let m = Message::parse_key_mgmt(&key_mgmt(&tampered))
+            .unwrap()
+            .unwrap();
+        m.master_key(Some(psk)).unwrap_err();
+    }
+
+    /// Tests deriving the master key and salt from a TGK, for AES-GCM.
+    #[test]
+    fn tgk() {
+        let policy = [0, 1, 7, 2, 1, 0, 4, 1, 12, 11, 1, 16];
+        let mut key_data = vec![0, 0, 0, 16];
+        key_data.extend_from_slice(&TEK);
+        let raw = build(&policy, &key_data, None);
+        let m = Message::parse_key_mgmt(&key_mgmt(&raw)).unwrap().unwrap();
+        let k = m.master_key(None).unwrap();
+        assert_eq!(format!("{k:?}"), "MasterKey { suite: AeadAes128Gcm }");
+        assert_ne!(
+            k,
+            MasterKey::new(Suite::AeadAes128Gcm, &TEK, &SALT[..12]).unwrap()
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        // Other key management protocols are skipped.
+        assert!(Message::parse_key_mgmt("kmp AAAA").unwrap().is_none());
+
+        // Diffie-Hellman messages.
+        let mut raw = build(&[], &tek_and_salt(&SALT), None);
+        raw[1] = 4;
+        Message::parse_key_mgmt(&key_mgmt(&raw)).unwrap_err();
+
+        // Unencrypted SRTP.
+        let raw = build(&[7, 1, 0], &tek_and_salt(&SALT), None);
+        Message::parse_key_mgmt(&key_mgmt(&raw)).unwrap_err();
+
+        // A salt of the wrong length for the suite.
+        let raw = build(&[], &tek_and_salt(&SALT[..12]), None);
+        let m = Message::parse_key_mgmt(&key_mgmt(&raw)).unwrap().unwrap();
+        m.master_key(None).unwrap_err();
+    }
+}
@@ -0,0 +1,628 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Publishing streams via `ANNOUNCE` and `RECORD`; see [`Publisher`].
+
+use std::num::NonZeroU32;
+
+use bytes::Bytes;
+use futures::{FutureExt, SinkExt, StreamExt};
+use log::{debug, trace, warn};
+use rtsp_types::{Data, Method};
+use url::Url;
+
+use super::parse::{self, SessionHeader};
+use super::{
+    handle_keepalive_response, keepalive_interval, take_url_credentials, KeepaliveMethod,
+    ResponseMode, RtspConnection, SessionOptions, STALE_KEEPALIVE_RETRY,
+};
+use crate::codec::{ParametersRef, MAX_PAYLOAD_SIZE};
+use crate::rtp::ReceivedPacket;
+use crate::runtime::Instant;
+use crate::sdp::{self, MediaDescription};
+use crate::{Error, ErrorInt, Timestamp};
+
+/// The dynamic RTP payload type of the first stream; later streams count up.
+const FIRST_PAYLOAD_TYPE: u8 = 96;
+
+enum Packetizer {
+    H264(crate::codec::h264::Packetizer),
+    Aac(crate::codec::aac::Packetizer),
+}
+
+impl Packetizer {
+    fn push(&mut self, timestamp: Timestamp, data: Bytes) -> Result<(), Error> {
+        match self {
+            Packetizer::H264(p) => p.push(timestamp, data),
+            Packetizer::Aac(p) => p.push(timestamp, data),
+        }
+    }
+
+    fn pull(&mut self) -> Result<Option<ReceivedPacket>, String> {
+        match self {
+            Packetizer::H264(p) => p.pull(),
+            Packetizer::Aac(p) => p.pull(),
+        }
+    }
+}
+
+struct PublishStream {
+    packetizer: Packetizer,
+    clock_rate: NonZeroU32,
+    channel_id: u8,
+
+    /// Added to the caller's timestamps to produce RTP timestamps.
+    timestamp_offset: u32,
+}
+
+/// A session which sends streams to a server, such as
+/// [mediamtx](https://github.com/bluenviron/mediamtx).
+///
+/// [`Publisher::record`] sends an `ANNOUNCE` with a generated SDP session
+/// description, a `SETUP` with `mode=record` for each stream, and a `RECORD`.
+/// Frames are then written with [`Publisher::write`].
+///
+/// Currently there are some limitations:
+///
+/// *   Only H.264 video and AAC audio are supported.
+/// *   Only RTSP/1.0 and RTP over interleaved TCP are supported.
+/// *   RTCP sender reports aren't sent.
+///
+/// ```no_run
+/// # async fn f(params: retina::codec::VideoParameters, frames: Vec<retina::codec::VideoFrame>) -> Result<(), retina::Error> {
+/// use retina::client::{Publisher, SessionOptions};
+/// use retina::codec::ParametersRef;
+///
+/// let url = url::Url::parse("rtsp://mediamtx:8554/clip").unwrap();
+/// let mut publisher = Publisher::record(
+///     url,
+///     [ParametersRef::Video(&params)],
+///     SessionOptions::default(),
+/// )
+/// .await?;
+/// for frame in frames {
+///     publisher
+///         .write(0, frame.timestamp(), frame.into_data().into())
+///         .await?;
+/// }
+/// publisher.teardown().await
+/// # }
+/// ```
+pub struct Publisher {
+    conn: RtspConnection,
+    options: SessionOptions,
+    requested_auth: Option<http_auth::PasswordClient>,
+    url: Url,
+    session: SessionHeader,
+    streams: Vec<PublishStream>,
+
+    /// [`super::SessionFlag`]s, as used to choose a keepalive method.
+    flags: u8,
+
+    /// The outstanding keepalive request, if any.
+    keepalive: Option<(u32, KeepaliveMethod)>,
+    next_keepalive: Instant,
+}
+
+impl Publisher {
+    /// Announces the given streams at `url` and starts recording.
+    ///
+    /// Each stream must have H.264 video or AAC audio parameters. Stream ids as
+    /// passed to [`Publisher::write`] are indices into `streams`.
+    pub async fn record<'a>(
+        mut url: Url,
+        streams: impl IntoIterator<Item = ParametersRef<'a>>,
+        mut options: SessionOptions,
+    ) -> Result<Self, Error> {
+        take_url_credentials(&mut url, &mut options)?;
+        let medias = streams
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let payload_type = u8::try_from(i)
+                    .ok()
+                    .and_then(|i| FIRST_PAYLOAD_TYPE.checked_add(i))
+                    .filter(|&pt| pt < 128)
+                    .ok_or_else(|| {
+                        wrap!(ErrorInt::InvalidArgument(
+                            "too many streams to publish".into()
+                        ))
+                    })?;
+                MediaDescription::new(p, payload_type, format!("trackID={i}"))
+                    .map_err(|e| wrap!(ErrorInt::InvalidArgument(format!("stream {i}: {e}"))))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if medias.is_empty() {
+            bail!(ErrorInt::InvalidArgument("no streams to publish".into()));
+        }
+        let mut conn = RtspConnection::connect(&url, &options).await?;
+        let mut requested_auth = None;
+        let sdp = sdp::write(rand::random(), conn.inner.ctx().local_addr.ip(), &medias);
+        let mut req = rtsp_types::Request::builder(Method::Announce, rtsp_types::Version::V1_0)
+            .request_uri(url.clone())
+            .header(rtsp_types::headers::CONTENT_TYPE, "application/sdp")
+            .build(sdp);
+        conn.send(
+            ResponseMode::Normal,
+            &options,
+            None,
+            &mut requested_auth,
+            &mut req,
+        )
+        .await?;
+        if let Some(u) = req.request_uri() {
+            url = u.clone(); // may have followed a redirect.
+        }
+
+        let mut session: Option<SessionHeader> = None;
+        let mut streams = Vec::with_capacity(medias.len());
+        for (i, m) in medias.iter().enumerate() {
+            let proposed_channel_id = conn.channels.next_unassigned().ok_or_else(|| {
+                wrap!(ErrorInt::FailedPrecondition(
+                    "no unassigned channels".into()
+                ))
+            })?;
+            let control = parse::join_control(&url, &m.control)
+                .map_err(|e| wrap!(ErrorInt::InvalidArgument(e)))?;
+            let mut req = rtsp_types::Request::builder(Method::Setup, rtsp_types::Version::V1_0)
+                .request_uri(control)
+                .header(
+                    rtsp_types::headers::TRANSPORT,
+                    format!(
+                        "RTP/AVP/TCP;unicast;interleaved={}-{};mode=record",
+                        proposed_channel_id,
+                        proposed_channel_id + 1
+                    ),
+                );
+            if let Some(ref s) = session {
+                req = req.header(rtsp_types::headers::SESSION, s.id.to_string());
+            }
+            let (msg_ctx, cseq, response) = conn
+                .send(
+                    ResponseMode::Normal,
+                    &options,
+                    None,
+                    &mut requested_auth,
+                    &mut req.build(Bytes::new()),
+                )
+                .await?;
+            debug!("SETUP response: {:#?}", &response);
+            let status = response.status();
+            let bad = |description: String| {
+                wrap!(ErrorInt::RtspResponseError {
+                    conn_ctx: conn.inner.ctx().clone(),
+                    msg_ctx,
+                    method: rtsp_types::Method::Setup,
+                    cseq,
+                    status,
+                    description,
+                })
+            };
+            let response = parse::parse_setup(&response).map_err(bad)?;
+            match session {
+                Some(ref s) if s.id != response.session.id => {
+                    return Err(bad(format!(
+                        "session id changed from {:?} to {:?}",
+                        s.id, response.session.id,
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    debug!(
+                        "established session {:?}, timeout={}s",
+                        response.session.id, response.session.timeout_sec
+                    );
+                    session = Some(response.session);
+                }
+            }
+            let channel_id = response.channel_id.unwrap_or(proposed_channel_id);
+            conn.channels.assign(channel_id, i).map_err(bad)?;
+            let ssrc = rand::random();
+            let initial_seq = rand::random();
+            let payload_type = m.payload_type;
+            let packetizer = match m.media {
+                "video" => crate::codec::h264::Packetizer::new(
+                    MAX_PAYLOAD_SIZE,
+                    i,
+                    initial_seq,
+                    payload_type,
+                    ssrc,
+                )
+                .map(Packetizer::H264),
+                _ => crate::codec::aac::Packetizer::new(
+                    MAX_PAYLOAD_SIZE,
+                    i,
+                    initial_seq,
+                    payload_type,
+                    ssrc,
+                )
+                .map(Packetizer::Aac),
+            }
+            .map_err(|e| wrap!(ErrorInt::Internal(e.into())))?;
+            streams.push(PublishStream {
+                packetizer,
+                clock_rate: NonZeroU32::new(m.clock_rate).expect("clock rate is non-zero"),
+                channel_id,
+                timestamp_offset: rand::random(),
+            });
+        }
+        let session = session.expect("at least one stream was set up");
+
+        let mut req = rtsp_types::Request::builder(Method::Record, rtsp_types::Version::V1_0)
+            .request_uri(url.clone())
+            .header(rtsp_types::headers::SESSION, session.id.to_string())
+            .header(rtsp_types::headers::RANGE, "npt=0.000-")
+            .build(Bytes::new());
+        conn.send(
+            ResponseMode::Normal,
+            &options,
+            None,
+            &mut requested_auth,
+            &mut req,
+        )
+        .await?;
+        let next_keepalive = Instant::now() + keepalive_interval(&session);
+        Ok(Publisher {
+            conn,
+            options,
+            requested_auth,
+            url,
+            session,
+            streams,
+            flags: 0,
+            keepalive: None,
+            next_keepalive,
+        })
+    }
+
+    /// Returns the number of streams being published.
+    pub fn num_streams(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Writes a frame to the given stream.
+    ///
+    /// `data` should be in the same form as returned by Retina's
+    /// depacketizers: for H.264, NAL units each preceded by a 4-byte
+    /// big-endian length; for AAC, a single raw access unit. `timestamp` must
+    /// use the stream's clock rate. Its start is ignored; RTP timestamps use a
+    /// random offset.
+    ///
+    /// This also sends keepalives as necessary, so write at least every few
+    /// seconds to keep the session alive.
+    pub async fn write(
+        &mut self,
+        stream_id: usize,
+        timestamp: Timestamp,
+        data: Bytes,
+    ) -> Result<(), Error> {
+        self.handle_incoming()?;
+        if Instant::now() >= self.next_keepalive {
+            self.send_keepalive().await?;
+        }
+        let num_streams = self.streams.len();
+        let stream = self.streams.get_mut(stream_id).ok_or_else(|| {
+            wrap!(ErrorInt::InvalidArgument(format!(
+                "no stream {stream_id}; publishing {num_streams}"
+            )))
+        })?;
+        if timestamp.clock_rate() != stream.clock_rate {
+            bail!(ErrorInt::InvalidArgument(format!(
+                "timestamp has clock rate {}; stream {} expects {}",
+                timestamp.clock_rate(),
+                stream_id,
+                stream.clock_rate
+            )));
+        }
+        let rtp_timestamp = (timestamp.timestamp() as u32).wrapping_add(stream.timestamp_offset);
+        let timestamp = Timestamp::new(i64::from(rtp_timestamp), stream.clock_rate, 0)
+            .expect("non-negative timestamp with zero start is valid");
+        stream.packetizer.push(timestamp, data)?;
+        loop {
+            let pkt = match stream.packetizer.pull() {
+                Ok(Some(pkt)) => pkt.raw.0,
+                Ok(None) => break,
+                Err(e) => {
+                    // Drop the rest of the frame, so the packetizer is idle.
+                    while let Ok(Some(_)) = stream.packetizer.pull() {}
+                    bail!(ErrorInt::InvalidArgument(format!(
+                        "unable to packetize frame for stream {stream_id}: {e}"
+                    )));
+                }
+            };
+            self.conn
+                .inner
+                .feed(rtsp_types::Message::Data(Data::new(stream.channel_id, pkt)))
+                .await
+                .map_err(|e| wrap!(e))?;
+        }
+        self.conn.inner.flush().await.map_err(|e| wrap!(e))
+    }
+
+    /// Sends a `TEARDOWN` and waits for its response.
+    pub async fn teardown(mut self) -> Result<(), Error> {
+        let mut req = rtsp_types::Request::builder(Method::Teardown, rtsp_types::Version::V1_0)
+            .request_uri(self.url.clone())
+            .header(rtsp_types::headers::SESSION, self.session.id.to_string())
+            .build(Bytes::new());
+        self.conn
+            .send(
+                ResponseMode::Teardown,
+                &self.options,
+                None,
+                &mut self.requested_auth,
+                &mut req,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Handles any messages the server has already sent, without waiting.
+    ///
+    /// These may be keepalive responses or RTCP receiver reports. Reading them
+    /// keeps the server from blocking on a full send buffer.
+    fn handle_incoming(&mut self) -> Result<(), Error> {
+        while let Some(msg) = self.conn.inner.next().now_or_never() {
+            let msg = match msg {
+                Some(m) => m?,
+                None => bail!(ErrorInt::RtspReadError {
+                    conn_ctx: self.conn.inner.ctx().clone(),
+                    msg_ctx: self.conn.inner.eof_ctx(),
+                    source: std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "EOF while publishing",
+                    ),
+                }),
+            };
+            match msg.msg {
+                rtsp_types::Message::Response(r) => match self.keepalive {
+                    Some((cseq, method)) if parse::get_cseq(&r) == Some(cseq) => {
+                        self.keepalive = None;
+                        if handle_keepalive_response(
+                            &mut self.flags,
+                            &mut self.requested_auth,
+                            method,
+                            &r,
+                        ) {
+                            self.next_keepalive = Instant::now() + STALE_KEEPALIVE_RETRY;
+                        }
+                    }
+                    _ => debug!("ignoring unexpected {:?} response", r.status()),
+                },
+                rtsp_types::Message::Data(d) => {
+                    trace!("ignoring data message on channel {}", d.channel_id());
+                }
+                rtsp_types::Message::Request(r) => {
+                    debug!("ignoring {:?} request from server", r.method());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_keepalive(&mut self) -> Result<(), Error> {
+        if let Some((cseq, method)) = self.keepalive {
+            warn!(
+                "no response to {:?} keepalive CSeq {} after {:?}",
+                method,
+                cseq,
+                keepalive_interval(&self.session)
+            );
+        }
+        let method = KeepaliveMethod::choose(self.flags);
+        let mut req = rtsp_types::Request::builder(method.into(), rtsp_types::Version::V1_0)
+            .request_uri(self.url.clone())
+            .header(rtsp_types::headers::SESSION, self.session.id.to_string())
+            .build(Bytes::new());
+        let cseq = self
+            .conn
+            .fill_req(&self.options, &mut self.requested_auth, &mut req)?;
+        trace!("sending {:?} keepalive", method);
+        self.conn
+            .inner
+            .feed(rtsp_types::Message::Request(req))
+            .await
+            .map_err(|e| wrap!(e))?;
+        self.keepalive = Some((cseq, method));
+        self.next_keepalive = Instant::now() + keepalive_interval(&self.session);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{CodecItem, Depacketizer};
+    use crate::runtime::AsyncTcpListener;
+    use crate::testutil::{init_logging, listen};
+
+    const H264_FMTP: &str = "packetization-mode=1;profile-level-id=640033;sprop-parameter-sets=Z2QAM6wVFKCgL/lQ,aO48sA==";
+    const AAC_FMTP: &str = "streamtype=5;profile-level-id=41;mode=AAC-hbr;sizelength=13;indexlength=3;indexdeltalength=3;config=1188";
+
+    /// Receives a request and responds with the given status and headers.
+    async fn respond(
+        server: &mut crate::net::Connection,
+        method: Method,
+        headers: &[(rtsp_types::HeaderName, &str)],
+    ) -> rtsp_types::Request<Bytes> {
+        let req = match server.next().await.unwrap().unwrap().msg {
+            rtsp_types::Message::Request(r) => r,
+            o => panic!("unexpected message {o:?}"),
+        };
+        assert_eq!(req.method(), method);
+        let mut resp =
+            rtsp_types::Response::builder(rtsp_types::Version::V1_0, rtsp_types::StatusCode::Ok)
+                .build(Bytes::new());
+        resp.insert_header(
+            rtsp_types::headers::CSEQ,
+            req.header(&rtsp_types::headers::CSEQ).unwrap().as_str(),
+        );
+        for (name, value) in headers {
+            resp.insert_header(name.clone(), *value);
+        }
+        server
+            .send(rtsp_types::Message::Response(resp))
+            .await
+            .unwrap();
+        req
+    }
+
+    fn depacketizer(media: &str, encoding_name: &str, clock_rate: u32, fmtp: &str) -> Depacketizer {
+        Depacketizer::new(media, encoding_name, clock_rate, None, Some(fmtp)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn record() {
+        init_logging();
+        let video = match depacketizer("video", "h264", 90_000, H264_FMTP).parameters() {
+            Some(ParametersRef::Video(v)) => v.clone(),
+            o => panic!("unexpected parameters {o:?}"),
+        };
+        let audio = match depacketizer("audio", "mpeg4-generic", 48_000, AAC_FMTP).parameters() {
+            Some(ParametersRef::Audio(a)) => a.clone(),
+            o => panic!("unexpected parameters {o:?}"),
+        };
+        let (listener, addr) = listen().await;
+        let url = Url::parse(&format!("rtsp://{addr}/clip")).unwrap();
+        let serve = async {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut server = crate::net::Connection::from_stream(tcp).unwrap();
+            let announce = respond(&mut server, Method::Announce, &[]).await;
+            let sdp = std::str::from_utf8(announce.body()).unwrap();
+            assert!(sdp.contains(&format!("a=fmtp:96 {H264_FMTP}")), "{sdp}");
+            assert!(sdp.contains("a=rtpmap:97 MPEG4-GENERIC/48000/1"), "{sdp}");
+            assert!(sdp.contains(&format!("a=fmtp:97 {AAC_FMTP}")), "{sdp}");
+            for (i, transport) in [
+                "RTP/AVP/TCP;unicast;interleaved=0-1;mode=record",
+                "RTP/AVP/TCP;unicast;interleaved=2-3;mode=record",
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let setup = respond(
+                    &mut server,
+                    Method::Setup,
+                    &[
+                        (rtsp_types::headers::TRANSPORT, transport),
+                        (rtsp_types::headers::SESSION, "abc;timeout=60"),
+                    ],
+                )
+                .await;
+                assert_eq!(
+                    setup.request_uri().unwrap().as_str(),
+                    format!("{url}/trackID={i}")
+                );
+                assert_eq!(
+                    setup
+                        .header(&rtsp_types::headers::TRANSPORT)
+                        .unwrap()
+                        .as_str(),
+                    transport
+                );
+            }
+            respond(
+                &mut server,
+                Method::Record,
+                &[(rtsp_types::headers::SESSION, "abc")],
+            )
+            .await;
+            server
+        };
+        let (publisher, mut server) = tokio::join!(
+            Publisher::record(
+                url.clone(),
+                [ParametersRef::Video(&video), ParametersRef::Audio(&audio)],
+                SessionOptions::default(),
+            ),
+            serve
+        );
+        let mut publisher = publisher.unwrap();
+        assert_eq!(publisher.num_streams(), 2);
+
+        // Write a fragmented IDR frame and an AAC frame, and check that each
+        // is depacketized intact.
+        let mut idr = vec![0, 0, 0x0b, 0xb8, 0x65];
+        idr.extend((1..3000).map(|i| i as u8));
+        let idr = Bytes::from(idr);
+        let aac = Bytes::from_static(b"asdf");
+        publisher
+            .write(
+                0,
+                Timestamp::new(0, NonZeroU32::new(90_000).unwrap(), 0).unwrap(),
+                idr.clone(),
+            )
+            .await
+            .unwrap();
+        publisher
+            .write(
+                1,
+                Timestamp::new(0, NonZeroU32::new(48_000).unwrap(), 0).unwrap(),
+                aac.clone(),
+            )
+            .await
+            .unwrap();
+        assert!(publisher
+            .write(
+                1,
+                Timestamp::new(0, NonZeroU32::new(90_000).unwrap(), 0).unwrap(),
+                aac.clone(),
+            )
+            .await
+            .is_err());
+        let mut depacketizers = [
+            depacketizer("video", "h264", 90_000, H264_FMTP),
+            depacketizer("audio", "mpeg4-generic", 48_000, AAC_FMTP),
+        ];
+        let mut frames = Vec::new();
+        while frames.len() < 2 {
+            let data = match server.next().await.unwrap().unwrap().msg {
+                rtsp_types::Message::Data(d) => d,
+                o => panic!("unexpected message {o:?}"),
+            };
+            let stream_id = usize::from(data.channel_id() / 2);
+            let clock_rate = NonZeroU32::new([90_000, 48_000][stream_id]).unwrap();
+            let (raw, payload_range) = crate::rtp::RawPacket::new(data.into_body()).unwrap();
+            let pkt = crate::rtp::ReceivedPacketBuilder {
+                ctx: crate::PacketContext::dummy(),
+                stream_id,
+                sequence_number: raw.sequence_number(),
+                timestamp: Timestamp::new(i64::from(raw.timestamp()), clock_rate, 0).unwrap(),
+                payload_type: raw.payload_type(),
+                ssrc: raw.ssrc(),
+                mark: raw.mark(),
+                loss: 0,
+            }
+            .build(
+                raw.0[usize::from(payload_range.start)..usize::from(payload_range.end)]
+                    .iter()
+                    .copied(),
+            )
+            .unwrap();
+            let d = &mut depacketizers[stream_id];
+            d.push(pkt).unwrap();
+            if let Some(f) = d
+                .pull(
+                    &crate::ConnectionContext::dummy(),
+                    &crate::StreamContext::dummy(),
+                )
+                .unwrap()
+            {
+                frames.push(f);
+            }
+        }
+        match &frames[..] {
+            [CodecItem::VideoFrame(v), CodecItem::AudioFrame(a)] => {
+                assert!(v.is_random_access_point());
+                assert_eq!(v.data(), &idr[..]);
+                assert_eq!(a.data(), &aac[..]);
+            }
+            o => panic!("unexpected frames {o:#?}"),
+        }
+
+        let (r, _) = tokio::join!(
+            publisher.teardown(),
+            respond(&mut server, Method::Teardown, &[])
+        );
+        r.unwrap();
+    }
+}
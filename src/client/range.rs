@@ -0,0 +1,443 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The RTSP `Range` header, as described in
+//! [RFC 2326 section 12.29](https://datatracker.ietf.org/doc/html/rfc2326#section-12.29).
+
+use std::time::Duration;
+
+use crate::{Error, ErrorInt, NtpTimestamp, UNIX_EPOCH};
+
+/// A time range, as used in the `Range` header of `PLAY` requests and responses.
+///
+/// Specify via [`super::PlayOptions::range`]; the server's response is available
+/// via [`super::Session::play_range`].
+///
+/// The [`std::fmt::Display`] and [`std::str::FromStr`] implementations use the
+/// header's wire format, e.g. `npt=0.000-` or `clock=20090615T114900.440Z-`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Range {
+    /// Normal play time, relative to the beginning of the presentation.
+    ///
+    /// See [RFC 2326 section 3.6](https://datatracker.ietf.org/doc/html/rfc2326#section-3.6).
+    Npt {
+        start: NptTime,
+        end: Option<NptTime>,
+    },
+
+    /// Absolute time in UTC, as used by cameras and NVRs to play back recordings.
+    ///
+    /// See [RFC 2326 section 3.7](https://datatracker.ietf.org/doc/html/rfc2326#section-3.7).
+    Clock {
+        start: NtpTimestamp,
+        end: Option<NtpTimestamp>,
+    },
+
+    /// SMPTE time codes, relative to the beginning of the presentation.
+    ///
+    /// See [RFC 2326 section 3.5](https://datatracker.ietf.org/doc/html/rfc2326#section-3.5).
+    Smpte {
+        kind: SmpteKind,
+        start: SmpteTime,
+        end: Option<SmpteTime>,
+    },
+}
+
+/// A single normal play time position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NptTime {
+    /// The current position, for live streams.
+    Now,
+
+    /// The given offset from the beginning of the presentation.
+    Offset(Duration),
+}
+
+/// The frame rate of a [`Range::Smpte`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SmpteKind {
+    /// `smpte`: 30 frames per second.
+    Smpte30,
+
+    /// `smpte-30-drop`: 29.97 frames per second, in drop-frame format.
+    Smpte30Drop,
+
+    /// `smpte-25`: 25 frames per second.
+    Smpte25,
+}
+
+/// A single SMPTE time code: `hours:minutes:seconds:frames.subframes`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SmpteTime {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub subframes: u8,
+}
+
+impl Range {
+    /// Returns the range starting at the beginning of the presentation, with no end.
+    ///
+    /// This is what Retina requests when no range is specified.
+    pub fn from_beginning() -> Self {
+        Range::Npt {
+            start: NptTime::Offset(Duration::ZERO),
+            end: None,
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        let (unit, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Range {s:?} has no unit"))?;
+
+        // RFC 2326 allows an optional `;time=` parameter, which is meaningless
+        // in responses and which Retina never sends. Ignore it.
+        let value = value
+            .split(';')
+            .next()
+            .expect("split returns at least one item");
+        let (start, end) = value
+            .split_once('-')
+            .ok_or_else(|| format!("Range {s:?} has no '-'"))?;
+        let end = Some(end).filter(|e| !e.is_empty());
+        let range = match unit.trim() {
+            "npt" => Range::Npt {
+                start: parse_npt(start)?,
+                end: end.map(parse_npt).transpose()?,
+            },
+            "clock" => Range::Clock {
+                start: parse_clock(start)?,
+                end: end.map(parse_clock).transpose()?,
+            },
+            "smpte" | "smpte-30-drop" | "smpte-25" => Range::Smpte {
+                kind: match unit.trim() {
+                    "smpte" => SmpteKind::Smpte30,
+                    "smpte-30-drop" => SmpteKind::Smpte30Drop,
+                    _ => SmpteKind::Smpte25,
+                },
+                start: parse_smpte(start)?,
+                end: end.map(parse_smpte).transpose()?,
+            },
+            _ => return Err(format!("Range {s:?} has unknown unit {unit:?}")),
+        };
+        Ok(range)
+    }
+}
+
+impl Default for Range {
+    fn default() -> Self {
+        Range::from_beginning()
+    }
+}
+
+impl std::fmt::Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Range::Npt { start, end } => {
+                write!(f, "npt={start}-")?;
+                if let Some(end) = end {
+                    write!(f, "{end}")?;
+                }
+            }
+            Range::Clock { start, end } => {
+                f.write_str("clock=")?;
+                write_clock(f, *start)?;
+                f.write_str("-")?;
+                if let Some(end) = end {
+                    write_clock(f, *end)?;
+                }
+            }
+            Range::Smpte { kind, start, end } => {
+                write!(f, "{kind}={start}-")?;
+                if let Some(end) = end {
+                    write!(f, "{end}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Range {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Range::parse(s).map_err(|e| wrap!(ErrorInt::InvalidArgument(e)))
+    }
+}
+
+impl std::fmt::Display for NptTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NptTime::Now => f.write_str("now"),
+            NptTime::Offset(d) if d.subsec_nanos() % 1_000_000 == 0 => {
+                write!(f, "{}.{:03}", d.as_secs(), d.subsec_millis())
+            }
+            NptTime::Offset(d) => write!(f, "{}.{:09}", d.as_secs(), d.subsec_nanos()),
+        }
+    }
+}
+
+impl std::fmt::Display for SmpteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            SmpteKind::Smpte30 => "smpte",
+            SmpteKind::Smpte30Drop => "smpte-30-drop",
+            SmpteKind::Smpte25 => "smpte-25",
+        })
+    }
+}
+
+impl std::fmt::Display for SmpteTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{:02}:{:02}", self.hours, self.minutes, self.seconds)?;
+        if self.frames != 0 || self.subframes != 0 {
+            write!(f, ":{:02}", self.frames)?;
+        }
+        if self.subframes != 0 {
+            write!(f, ".{:02}", self.subframes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a string of ASCII digits, rejecting the signs and whitespace `u64::from_str` allows.
+pub(super) fn parse_digits(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Parses a fractional part (the digits after the decimal point) into nanoseconds.
+///
+/// Digits beyond nanosecond precision are ignored.
+pub(super) fn parse_nanos(frac: &str) -> Option<u32> {
+    if !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut nanos = 0;
+    for i in 0..9 {
+        let digit = frac.as_bytes().get(i).map(|&b| b - b'0').unwrap_or(0);
+        nanos = nanos * 10 + u32::from(digit);
+    }
+    Some(nanos)
+}
+
+/// Parses a `npt-time`: `now`, `npt-sec`, or `npt-hhmmss`.
+fn parse_npt(s: &str) -> Result<NptTime, String> {
+    let s = s.trim();
+    if s == "now" {
+        return Ok(NptTime::Now);
+    }
+    let bad = || format!("bad npt time {s:?}");
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    let nanos = parse_nanos(frac).ok_or_else(bad)?;
+    let mut parts = whole.split(':');
+    let secs = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(secs), None, None, None) => parse_digits(secs).ok_or_else(bad)?,
+        (Some(hh), Some(mm), Some(ss), None) => {
+            let hh = parse_digits(hh).ok_or_else(bad)?;
+            let mm = parse_digits(mm).filter(|&m| m < 60).ok_or_else(bad)?;
+            let ss = parse_digits(ss).filter(|&s| s < 60).ok_or_else(bad)?;
+            hh.checked_mul(3600)
+                .and_then(|h| h.checked_add(mm * 60 + ss))
+                .ok_or_else(bad)?
+        }
+        _ => return Err(bad()),
+    };
+    Ok(NptTime::Offset(Duration::new(secs, nanos)))
+}
+
+/// Parses a `utc-time` such as `19961108T143720.25Z`.
+pub(super) fn parse_clock(s: &str) -> Result<NtpTimestamp, String> {
+    let s = s.trim();
+    let bad = || format!("bad clock time {s:?}");
+    let s = s.strip_suffix('Z').ok_or_else(bad)?;
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    if whole.len() != 15 || !whole.is_char_boundary(8) || whole.as_bytes()[8] != b'T' {
+        return Err(bad());
+    }
+    if parse_digits(&whole[..8]).is_none() || parse_digits(&whole[9..]).is_none() {
+        return Err(bad());
+    }
+    let tm = time::strptime(whole, "%Y%m%dT%H%M%S").map_err(|_| bad())?;
+    let ntp_sec = tm
+        .to_timespec()
+        .sec
+        .checked_add((UNIX_EPOCH.0 >> 32) as i64)
+        .and_then(|s| u32::try_from(s).ok())
+        .ok_or_else(bad)?;
+    let nanos = parse_nanos(frac).ok_or_else(bad)?;
+    // Round up so that `write_clock` recovers the same milliseconds.
+    let ntp_frac = ((u64::from(nanos) << 32) + 999_999_999) / 1_000_000_000;
+    Ok(NtpTimestamp(u64::from(ntp_sec) << 32 | ntp_frac))
+}
+
+fn write_clock(f: &mut std::fmt::Formatter<'_>, t: NtpTimestamp) -> std::fmt::Result {
+    let since_epoch = t.0.wrapping_sub(UNIX_EPOCH.0);
+    let tm = time::at_utc(time::Timespec {
+        sec: i64::from((since_epoch >> 32) as u32),
+        nsec: 0,
+    });
+    let ms = ((since_epoch & 0xFFFF_FFFF) * 1_000) >> 32;
+    write!(
+        f,
+        "{}.{:03}Z",
+        tm.strftime("%Y%m%dT%H%M%S").map_err(|_| std::fmt::Error)?,
+        ms
+    )
+}
+
+/// Parses a `smpte-time` such as `10:07:33:05.01`.
+fn parse_smpte(s: &str) -> Result<SmpteTime, String> {
+    let s = s.trim();
+    let bad = || format!("bad smpte time {s:?}");
+    let (whole, subframes) = s.split_once('.').unwrap_or((s, "0"));
+    let field = |f: &str| {
+        Some(f)
+            .filter(|f| f.len() <= 2)
+            .and_then(parse_digits)
+            .map(|v| v as u8)
+            .ok_or_else(bad)
+    };
+    let mut parts = whole.split(':');
+    let (hours, minutes, seconds, frames) = match (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) {
+        (Some(h), Some(m), Some(s), f, None) => {
+            (field(h)?, field(m)?, field(s)?, f.map(field).transpose()?)
+        }
+        _ => return Err(bad()),
+    };
+    Ok(SmpteTime {
+        hours,
+        minutes,
+        seconds,
+        frames: frames.unwrap_or(0),
+        subframes: field(subframes)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(s: &str, expected: Range) {
+        let parsed: Range = s.parse().unwrap();
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.to_string(), s);
+    }
+
+    #[test]
+    fn npt() {
+        round_trip("npt=0.000-", Range::from_beginning());
+        round_trip(
+            "npt=now-",
+            Range::Npt {
+                start: NptTime::Now,
+                end: None,
+            },
+        );
+        round_trip(
+            "npt=10.500-20.000",
+            Range::Npt {
+                start: NptTime::Offset(Duration::from_millis(10_500)),
+                end: Some(NptTime::Offset(Duration::from_secs(20))),
+            },
+        );
+        assert_eq!(
+            Range::parse("npt=1:02:03.25-").unwrap(),
+            Range::Npt {
+                start: NptTime::Offset(Duration::from_millis(3_723_250)),
+                end: None,
+            }
+        );
+        assert_eq!(Range::parse("npt=0-").unwrap().to_string(), "npt=0.000-");
+        Range::parse("npt=1:60:00-").unwrap_err();
+        Range::parse("npt=+1-").unwrap_err();
+        Range::parse("npt=").unwrap_err();
+    }
+
+    #[test]
+    fn clock() {
+        // ONVIF Streaming Specification, section 6.4.3 example.
+        let start = NtpTimestamp((3_454_055_340 << 32) + 1_889_785_611);
+        round_trip(
+            "clock=20090615T114900.440Z-",
+            Range::Clock { start, end: None },
+        );
+        let end = NtpTimestamp(3_454_059_000 << 32);
+        round_trip(
+            "clock=20090615T114900.440Z-20090615T125000.000Z",
+            Range::Clock {
+                start,
+                end: Some(end),
+            },
+        );
+        assert_eq!(
+            Range::parse("clock=20090615T125000Z-").unwrap(),
+            Range::Clock {
+                start: end,
+                end: None
+            }
+        );
+        Range::parse("clock=20090615T125000-").unwrap_err();
+        Range::parse("clock=2009061T125000Z-").unwrap_err();
+    }
+
+    #[test]
+    fn smpte() {
+        round_trip(
+            "smpte=10:07:33-",
+            Range::Smpte {
+                kind: SmpteKind::Smpte30,
+                start: SmpteTime {
+                    hours: 10,
+                    minutes: 7,
+                    seconds: 33,
+                    ..Default::default()
+                },
+                end: None,
+            },
+        );
+        round_trip(
+            "smpte-25=10:07:00-10:07:33:05.01",
+            Range::Smpte {
+                kind: SmpteKind::Smpte25,
+                start: SmpteTime {
+                    hours: 10,
+                    minutes: 7,
+                    ..Default::default()
+                },
+                end: Some(SmpteTime {
+                    hours: 10,
+                    minutes: 7,
+                    seconds: 33,
+                    frames: 5,
+                    subframes: 1,
+                }),
+            },
+        );
+        Range::parse("smpte-30-drop=10:07-").unwrap_err();
+    }
+
+    #[test]
+    fn ignores_time_param() {
+        assert_eq!(
+            Range::parse("npt=0.000-;time=19970123T153600Z").unwrap(),
+            Range::from_beginning()
+        );
+    }
+
+    #[test]
+    fn unknown_unit() {
+        Range::parse("bytes=0-").unwrap_err();
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! RTCP receiver reports, as described in
+//! [RFC 3550 section 6.4.2](https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.2).
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+/// The minimum interval between RTCP reports; see RFC 3550 section 6.2.
+const MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `e - 3/2`, compensating for the randomization's effect on the average
+/// interval; see RFC 3550 section 6.3.1.
+const COMPENSATION: f64 = std::f64::consts::E - 1.5;
+
+/// Length of a single report block within a receiver report.
+pub(crate) const REPORT_BLOCK_LEN: usize = 24;
+
+/// Returns a randomized delay before sending the next receiver report.
+///
+/// RTSP sessions have two participants, so the RFC 3550 section 6.3.1
+/// calculation is dominated by its minimum interval, halved for the initial
+/// report.
+pub(crate) fn interval(initial: bool) -> Duration {
+    let min = if initial {
+        MIN_INTERVAL / 2
+    } else {
+        MIN_INTERVAL
+    };
+    min.mul_f64(rand::thread_rng().gen_range(0.5..1.5) / COMPENSATION)
+}
+
+/// Reception statistics for a single source, as described in
+/// [RFC 3550 appendix A.3 and A.8](https://datatracker.ietf.org/doc/html/rfc3550#appendix-A.3).
+#[derive(Debug)]
+pub(crate) struct ReceptionStats {
+    ssrc: u32,
+    clock_rate: u32,
+
+    /// The start of the timescale used for arrival times.
+    epoch: Instant,
+
+    /// The first sequence number received, extended to 32 bits.
+    base_seq: u32,
+
+    /// The highest sequence number received, extended to 32 bits.
+    max_seq: u32,
+
+    /// Total packets received.
+    received: u32,
+
+    /// `expected` and `received` as of the previous report.
+    expected_prior: u32,
+    received_prior: u32,
+
+    /// The relative transit time of the previous packet, in RTP timestamp units.
+    transit: Option<u32>,
+
+    /// The interarrival jitter in RTP timestamp units, scaled by 16.
+    jitter: u32,
+
+    /// The middle 32 bits of the most recent sender report's NTP timestamp,
+    /// and when that report was received.
+    last_sr: Option<(u32, Instant)>,
+}
+
+impl ReceptionStats {
+    pub(crate) fn new(ssrc: u32, clock_rate: u32, seq: u16, now: Instant) -> Self {
+        Self {
+            ssrc,
+            clock_rate,
+            epoch: now,
+            base_seq: u32::from(seq),
+            max_seq: u32::from(seq).wrapping_sub(1),
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0,
+            last_sr: None,
+        }
+    }
+
+    /// Records an accepted RTP packet.
+    ///
+    /// The caller is responsible for rejecting out-of-order packets, so `seq`
+    /// is assumed to follow the previous sequence number, possibly after loss.
+    pub(crate) fn record_rtp(&mut self, seq: u16, rtp_timestamp: u32, now: Instant) {
+        let delta = seq.wrapping_sub(self.max_seq as u16);
+        self.max_seq = self.max_seq.wrapping_add(u32::from(delta));
+        self.received = self.received.wrapping_add(1);
+
+        let elapsed = now.saturating_duration_since(self.epoch);
+        let arrival = (elapsed.as_nanos() * u128::from(self.clock_rate) / 1_000_000_000) as u32;
+        let transit = arrival.wrapping_sub(rtp_timestamp);
+        if let Some(prior) = self.transit.replace(transit) {
+            let d = (transit.wrapping_sub(prior) as i32).unsigned_abs();
+            self.jitter = self
+                .jitter
+                .wrapping_add(d)
+                .wrapping_sub((self.jitter + 8) >> 4);
+        }
+    }
+
+    /// Records a sender report with the given NTP timestamp.
+    pub(crate) fn record_sr(&mut self, ntp_timestamp: crate::NtpTimestamp, now: Instant) {
+        self.last_sr = Some(((ntp_timestamp.0 >> 16) as u32, now));
+    }
+
+    /// Returns the cumulative number of packets lost, which may be negative
+    /// if duplicates were received.
+    fn cumulative_lost(&self) -> i64 {
+        i64::from(self.expected()) - i64::from(self.received)
+    }
+
+    /// Returns the interarrival jitter, in RTP timestamp units.
+    fn jitter(&self) -> u32 {
+        self.jitter >> 4
+    }
+
+    fn expected(&self) -> u32 {
+        self.max_seq.wrapping_sub(self.base_seq).wrapping_add(1)
+    }
+
+    /// Writes a report block, resetting the interval used for the fraction lost.
+    pub(crate) fn report_block(&mut self, now: Instant) -> [u8; REPORT_BLOCK_LEN] {
+        let expected = self.expected();
+        let expected_interval = expected.wrapping_sub(self.expected_prior);
+        let received_interval = self.received.wrapping_sub(self.received_prior);
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+        let lost_interval = i64::from(expected_interval) - i64::from(received_interval);
+        let fraction = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / i64::from(expected_interval)) as u8
+        };
+        let cumulative = self.cumulative_lost().clamp(-0x80_0000, 0x7f_ffff) as u32 & 0xff_ffff;
+        let (lsr, dlsr) = match self.last_sr {
+            None => (0, 0),
+            Some((lsr, received)) => {
+                let delay = now.saturating_duration_since(received);
+                let dlsr = (delay.as_nanos() * 65_536 / 1_000_000_000).min(u128::from(u32::MAX));
+                (lsr, dlsr as u32)
+            }
+        };
+
+        let mut block = [0; REPORT_BLOCK_LEN];
+        block[0..4].copy_from_slice(&self.ssrc.to_be_bytes());
+        block[4..8].copy_from_slice(&(u32::from(fraction) << 24 | cumulative).to_be_bytes());
+        block[8..12].copy_from_slice(&self.max_seq.to_be_bytes());
+        block[12..16].copy_from_slice(&self.jitter().to_be_bytes());
+        block[16..20].copy_from_slice(&lsr.to_be_bytes());
+        block[20..24].copy_from_slice(&dlsr.to_be_bytes());
+        block
+    }
+}
+
+/// Returns a compound RTCP packet with a receiver report from `reporter_ssrc`
+/// (including `block`, if supplied) and a source description with `cname`.
+pub(crate) fn compound_packet(
+    reporter_ssrc: u32,
+    cname: &str,
+    block: Option<&[u8; REPORT_BLOCK_LEN]>,
+) -> Vec<u8> {
+    let cname = &cname.as_bytes()[..std::cmp::min(cname.len(), 255)];
+    let mut pkt = Vec::with_capacity(8 + REPORT_BLOCK_LEN + 12 + cname.len());
+
+    // Receiver report (PT=201).
+    let rr_words = 1 + block.map(|_| REPORT_BLOCK_LEN / 4).unwrap_or(0);
+    pkt.push(0x80 | u8::from(block.is_some()));
+    pkt.push(201);
+    pkt.extend_from_slice(&(rr_words as u16).to_be_bytes());
+    pkt.extend_from_slice(&reporter_ssrc.to_be_bytes());
+    if let Some(block) = block {
+        pkt.extend_from_slice(&block[..]);
+    }
+
+    // Source description (PT=202) with a single chunk holding the CNAME item,
+    // terminated by at least one null octet and padded to a 32-bit boundary.
+    let chunk_len = (4 + 2 + cname.len() + 1 + 3) & !3;
+    pkt.push(0x81);
+    pkt.push(202);
+    pkt.extend_from_slice(&((chunk_len / 4) as u16).to_be_bytes());
+    let chunk_start = pkt.len();
+    pkt.extend_from_slice(&reporter_ssrc.to_be_bytes());
+    pkt.push(1); // CNAME
+    pkt.push(cname.len() as u8);
+    pkt.extend_from_slice(cname);
+    pkt.resize(chunk_start + chunk_len, 0);
+    pkt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loss_and_jitter() {
+        let start = Instant::now();
+        let mut stats = ReceptionStats::new(0x1234_5678, 90_000, 0xfffe, start);
+
+        // Packets every 10 ms with RTP timestamps to match, except that 0x0001
+        // and 0x0002 are lost.
+        for (i, seq) in [0xfffe_u16, 0xffff, 0x0000, 0x0003].into_iter().enumerate() {
+            let ts = u32::from(seq.wrapping_sub(0xfffe)) * 900;
+            stats.record_rtp(seq, ts, start + Duration::from_millis(10) * i as u32);
+        }
+        assert_eq!(stats.cumulative_lost(), 2);
+
+        // The last packet arrived 20 ms (1,800 units) early.
+        assert_eq!(stats.jitter(), 1_800 / 16);
+
+        stats.record_sr(crate::NtpTimestamp(0x0123_4567_89ab_cdef), start);
+        let block = stats.report_block(start + Duration::from_millis(500));
+        assert_eq!(&block[0..4], b"\x12\x34\x56\x78");
+        assert_eq!(block[4], 85); // fraction lost: 2/6 * 256
+        assert_eq!(&block[5..8], b"\x00\x00\x02"); // cumulative lost
+        assert_eq!(&block[8..12], b"\x00\x01\x00\x03");
+        assert_eq!(&block[16..20], b"\x45\x67\x89\xab");
+        assert_eq!(&block[20..24], &32_768u32.to_be_bytes());
+
+        // The fraction lost covers only the interval since the previous report.
+        stats.record_rtp(0x0004, 6 * 900, start + Duration::from_millis(40));
+        let block = stats.report_block(start + Duration::from_millis(500));
+        assert_eq!(block[4], 0);
+        assert_eq!(&block[5..8], b"\x00\x00\x02");
+    }
+
+    #[test]
+    fn compound() {
+        let pkt = compound_packet(0xcafe_babe, "retina@192.168.5.2", None);
+        let (rr, rest) = crate::rtcp::PacketRef::parse(&pkt).unwrap();
+        assert_eq!(rr.payload_type(), 201);
+        assert_eq!(rr.count(), 0);
+        let (sdes, rest) = crate::rtcp::PacketRef::parse(rest).unwrap();
+        assert_eq!(sdes.payload_type(), 202);
+        assert_eq!(sdes.count(), 1);
+        assert!(rest.is_empty());
+        assert_eq!(&sdes.raw()[8..28], b"\x01\x12retina@192.168.5.2");
+        assert_eq!(&sdes.raw()[28..], b"\x00\x00\x00\x00");
+
+        let mut stats = ReceptionStats::new(0x1234_5678, 8_000, 0, Instant::now());
+        let block = stats.report_block(Instant::now());
+        let pkt = compound_packet(0xcafe_babe, "a", Some(&block));
+        let (rr, rest) = crate::rtcp::PacketRef::parse(&pkt).unwrap();
+        assert_eq!(rr.count(), 1);
+        assert_eq!(rr.raw().len(), 8 + REPORT_BLOCK_LEN);
+        assert_eq!(rest.len(), 12);
+        crate::rtcp::ReceivedCompoundPacket::validate(&pkt).unwrap();
+    }
+}
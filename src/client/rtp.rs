@@ -3,21 +3,39 @@
 
 //! RTP and RTCP handling; see [RFC 3550](https://datatracker.ietf.org/doc/html/rfc3550).
 
+use std::collections::VecDeque;
+use std::ops::Range;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use std::pin::Pin;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
 use bytes::Bytes;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use futures::Future;
 use log::debug;
 
 use crate::client::PacketItem;
 use crate::rtcp::ReceivedCompoundPacket;
 use crate::rtp::{RawPacket, ReceivedPacket};
+use crate::runtime::Instant;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::runtime::Timer;
 use crate::{ConnectionContext, Error, ErrorInt, PacketContext, StreamContext, StreamContextInner};
 
-use super::{SessionOptions, Timeline};
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use super::receiver_report::{self, ReceptionStats};
+use super::{SessionOptions, SsrcChangePolicy, Timeline};
 
 /// RTP/RTCP demarshaller which ensures packets have the correct SSRC and
 /// monotonically increasing SEQ. Unstable; exposed for benchmark.
 ///
 /// When using UDP, skips and logs out-of-order packets. When using TCP,
-/// fails on them.
+/// fails on them. UDP streams may be configured with a reorder buffer which
+/// holds back packets that arrive early, so that packets which are only
+/// slightly out of order can be released in sequence. Then loss is reported
+/// only once a gap times out or the buffer fills.
 ///
 /// This reports packet loss (via [ReceivedPacket::loss]) but doesn't prohibit it
 /// of more than `i16::MAX` which would be indistinguishable from non-monotonic sequence numbers.
@@ -25,12 +43,24 @@ use super::{SessionOptions, Timeline};
 ///
 /// At least [one camera](https://github.com/scottlamb/moonfire-nvr/wiki/Cameras:-Reolink#reolink-rlc-410-hardware-version-ipc_3816m)
 /// sometimes sends data from old RTSP sessions over new ones. This seems like a
-/// serious bug, and currently `InorderRtpParser` will error in this case,
-/// although it'd be possible to discard the incorrect SSRC instead.
+/// serious bug, and by default `InorderRtpParser` will error in this case.
+/// Under [`SsrcChangePolicy::AfterPackets`], packets with an unexpected SSRC
+/// are instead discarded until that many consecutive in-sequence packets have
+/// arrived from it. Then the parser switches to the new SSRC, reporting the
+/// discarded packets as loss.
+///
+/// If the stream uses SRTP, packets are authenticated and decrypted before
+/// parsing. Packets which fail authentication or are replayed are errors on
+/// TCP; on UDP, where anyone can send packets to the socket, they're logged
+/// and dropped.
+///
+/// Accepted packets and sender reports update the reception statistics used
+/// in RTCP receiver reports.
 ///
 /// [RFC 3550 section 8.2](https://tools.ietf.org/html/rfc3550#section-8.2) says that SSRC
-/// can change mid-session with a RTCP BYE message. This currently isn't handled. I'm
-/// not sure it will ever come up with IP cameras.
+/// can change mid-session with a RTCP BYE message. Some IP cameras do this when
+/// reconfigured. Such changes are accepted according to [`SsrcChangePolicy`];
+/// the BYE itself is returned as a [`PacketItem::Goodbye`].
 #[doc(hidden)] // pub only for the benchmarks; not a stable API.
 #[derive(Debug)]
 pub struct InorderParser {
@@ -42,6 +72,125 @@ pub struct InorderParser {
 
     /// Total packets seen in this stream.
     seen_packets: u64,
+
+    srtp: Option<super::srtp::Context>,
+
+    /// Reception statistics, starting from the first accepted packet.
+    #[cfg(any(feature = "tokio", feature = "async-std"))]
+    stats: Option<ReceptionStats>,
+
+    reorder: Option<ReorderBuffer>,
+
+    ssrc_change: SsrcChangePolicy,
+
+    /// True iff the current `ssrc` has sent an RTCP BYE.
+    goodbye: bool,
+
+    /// A new SSRC seen in consecutive packets, under [`SsrcChangePolicy::AfterPackets`].
+    candidate: Option<SsrcCandidate>,
+}
+
+#[derive(Debug)]
+struct SsrcCandidate {
+    ssrc: u32,
+    next_seq: u16,
+    packets: u16,
+}
+
+/// The outcome of [`InorderParser::ssrc_change`].
+enum SsrcChange {
+    /// Switch to the new SSRC, after discarding this many of its packets.
+    Accept {
+        discarded: u16,
+    },
+    Discard,
+    Reject,
+}
+
+/// Limits on how long a UDP stream's reorder buffer holds back packets.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ReorderConfig {
+    /// The maximum number of packets to hold; at least 1.
+    pub(crate) max_packets: usize,
+
+    /// The maximum time to hold any packet.
+    pub(crate) max_delay: Duration,
+}
+
+/// Packets which arrived ahead of a gap in sequence numbers.
+struct ReorderBuffer {
+    config: ReorderConfig,
+
+    /// Held packets, in sequence number order.
+    held: VecDeque<HeldPacket>,
+
+    /// A timer for the earliest deadline among `held`, created on first use.
+    #[cfg(any(feature = "tokio", feature = "async-std"))]
+    timer: Option<Pin<Box<crate::runtime::Sleep>>>,
+}
+
+struct HeldPacket {
+    raw: RawPacket,
+    payload_range: Range<u16>,
+    pkt_ctx: PacketContext,
+    arrival: Instant,
+}
+
+impl std::fmt::Debug for ReorderBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReorderBuffer")
+            .field("config", &self.config)
+            .field(
+                "held",
+                &self
+                    .held
+                    .iter()
+                    .map(|h| h.raw.sequence_number())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl ReorderBuffer {
+    /// Returns the time at which the longest-held packet must be released.
+    fn deadline(&self) -> Option<Instant> {
+        self.held
+            .iter()
+            .map(|h| h.arrival)
+            .min()
+            .map(|a| a + self.config.max_delay)
+    }
+
+    /// Holds a packet `ahead` sequence numbers past the next expected one.
+    /// Drops duplicates.
+    fn insert(&mut self, next_seq: u16, ahead: u16, held: HeldPacket) {
+        let i = self
+            .held
+            .partition_point(|h| h.raw.sequence_number().wrapping_sub(next_seq) < ahead);
+        if matches!(self.held.get(i), Some(h) if h.raw.sequence_number() == held.raw.sequence_number())
+        {
+            debug!(
+                "dropping duplicate seq={:04x} in reorder buffer",
+                held.raw.sequence_number()
+            );
+            return;
+        }
+        self.held.insert(i, held);
+    }
+
+    /// Removes the first held packet if it's next in sequence, the buffer is
+    /// over capacity, or any held packet has reached its deadline.
+    fn pop(&mut self, next_seq: Option<u16>, now: Instant) -> Option<HeldPacket> {
+        let first = self.held.front()?;
+        if Some(first.raw.sequence_number()) == next_seq
+            || self.held.len() > self.config.max_packets
+            || matches!(self.deadline(), Some(d) if d <= now)
+        {
+            return self.held.pop_front();
+        }
+        None
+    }
 }
 
 impl InorderParser {
@@ -51,6 +200,58 @@ impl InorderParser {
             next_seq,
             initial_ssrc: ssrc.is_some(),
             seen_packets: 0,
+            srtp: None,
+            #[cfg(any(feature = "tokio", feature = "async-std"))]
+            stats: None,
+            reorder: None,
+            ssrc_change: SsrcChangePolicy::default(),
+            goodbye: false,
+            candidate: None,
+        }
+    }
+
+    /// Uses a reorder buffer with the given limits, if any.
+    pub(crate) fn with_reorder(self, config: Option<ReorderConfig>) -> Self {
+        Self {
+            reorder: config.map(|config| ReorderBuffer {
+                config,
+                held: VecDeque::new(),
+                #[cfg(any(feature = "tokio", feature = "async-std"))]
+                timer: None,
+            }),
+            ..self
+        }
+    }
+
+    /// Handles SSRC changes according to the given policy.
+    pub(crate) fn with_ssrc_change(self, ssrc_change: SsrcChangePolicy) -> Self {
+        Self {
+            ssrc_change,
+            ..self
+        }
+    }
+
+    /// Uses the given SRTP cryptographic context, if any.
+    pub(crate) fn with_srtp(self, srtp: Option<super::srtp::Context>) -> Self {
+        Self { srtp, ..self }
+    }
+
+    /// Accounts for a `PAUSE` and subsequent `PLAY`, given the `ssrc` and
+    /// `seq` from the latter's `RTP-Info` header, if any.
+    ///
+    /// Servers may advance the sequence number while paused; if the new
+    /// starting point is known, this avoids reporting the gap as loss.
+    pub fn resume(&mut self, ssrc: Option<u32>, next_seq: Option<u16>) {
+        if ssrc.is_some() {
+            self.ssrc = ssrc;
+        }
+        if next_seq.is_some() {
+            self.next_seq = next_seq;
+        }
+
+        // Packets held from before the pause would be out of place.
+        if let Some(r) = &mut self.reorder {
+            r.held.clear();
         }
     }
 
@@ -66,9 +267,26 @@ impl InorderParser {
         stream_id: usize,
         data: Bytes,
     ) -> Result<Option<PacketItem>, Error> {
+        let data = match &mut self.srtp {
+            Some(srtp) => match srtp.unprotect_rtp(&data) {
+                Ok(data) => data,
+                Err(description) if matches!(stream_ctx.0, StreamContextInner::Udp(_)) => {
+                    log::info!("Dropping stream {} SRTP packet: {}", stream_id, description);
+                    return Ok(None);
+                }
+                Err(description) => bail!(ErrorInt::SrtpAuthenticationError {
+                    conn_ctx: conn_ctx.clone(),
+                    stream_ctx: stream_ctx.to_owned(),
+                    pkt_ctx: *pkt_ctx,
+                    stream_id,
+                    description,
+                }),
+            },
+            None => data,
+        };
         let (raw, payload_range) = RawPacket::new(data).map_err(|e| {
             wrap!(ErrorInt::PacketError {
-                conn_ctx: *conn_ctx,
+                conn_ctx: conn_ctx.clone(),
                 stream_ctx: stream_ctx.to_owned(),
                 pkt_ctx: *pkt_ctx,
                 stream_id,
@@ -94,29 +312,58 @@ impl InorderParser {
 
         let sequence_number = raw.sequence_number();
         let ssrc = raw.ssrc();
-        let loss = sequence_number.wrapping_sub(self.next_seq.unwrap_or(sequence_number));
+        let mut restarted = false;
         if matches!(self.ssrc, Some(s) if s != ssrc) {
-            if matches!(stream_ctx.0, StreamContextInner::Udp(_)) {
-                super::note_stale_live555_data(tool, session_options);
+            match self.ssrc_change(ssrc, sequence_number) {
+                SsrcChange::Accept { discarded } => {
+                    log::info!(
+                        "Stream {} changing from ssrc={:08x?} to ssrc={:08x} at seq={:04x} \
+                         after {} packets",
+                        stream_id,
+                        self.ssrc,
+                        ssrc,
+                        sequence_number,
+                        self.seen_packets,
+                    );
+                    self.restart(ssrc, timeline);
+
+                    // Report the discarded packets as loss, so depacketizers
+                    // don't expect continuity with a fragment they never saw.
+                    self.next_seq = Some(sequence_number.wrapping_sub(discarded));
+                    restarted = true;
+                }
+                SsrcChange::Discard => {
+                    debug!(
+                        "Discarding ssrc={:08x} seq={:04x} when expecting ssrc={:08x?}",
+                        ssrc, sequence_number, self.ssrc,
+                    );
+                    return Ok(None);
+                }
+                SsrcChange::Reject => {
+                    if matches!(stream_ctx.0, StreamContextInner::Udp(_)) {
+                        super::note_stale_live555_data(tool, session_options);
+                    }
+                    bail!(ErrorInt::RtpPacketError {
+                        conn_ctx: conn_ctx.clone(),
+                        pkt_ctx: *pkt_ctx,
+                        stream_ctx: stream_ctx.to_owned(),
+                        stream_id,
+                        ssrc,
+                        sequence_number,
+                        description: format!(
+                            "Wrong ssrc after {} packets; expecting ssrc={:08x?} seq={:04x?} \
+                             (initial ssrc: {:?})",
+                            self.seen_packets, self.ssrc, self.next_seq, self.initial_ssrc,
+                        ),
+                    });
+                }
             }
-            bail!(ErrorInt::RtpPacketError {
-                conn_ctx: *conn_ctx,
-                pkt_ctx: *pkt_ctx,
-                stream_ctx: stream_ctx.to_owned(),
-                stream_id,
-                ssrc,
-                sequence_number,
-                description: format!(
-                    "Wrong ssrc after {} packets; expecting ssrc={:08x?} seq={:04x?} \
-                     (initial ssrc: {:?})",
-                    self.seen_packets, self.ssrc, self.next_seq, self.initial_ssrc,
-                ),
-            });
         }
+        let loss = sequence_number.wrapping_sub(self.next_seq.unwrap_or(sequence_number));
         if loss > 0x80_00 {
             if matches!(stream_ctx.0, StreamContextInner::Tcp { .. }) {
                 bail!(ErrorInt::RtpPacketError {
-                    conn_ctx: *conn_ctx,
+                    conn_ctx: conn_ctx.clone(),
                     pkt_ctx: *pkt_ctx,
                     stream_ctx: stream_ctx.to_owned(),
                     stream_id,
@@ -137,11 +384,142 @@ impl InorderParser {
                 return Ok(None);
             }
         }
+        let now = Instant::now();
+        let mut held = HeldPacket {
+            raw,
+            payload_range,
+            pkt_ctx: *pkt_ctx,
+            arrival: now,
+        };
+        if let (Some(r), Some(next_seq)) = (&mut self.reorder, self.next_seq) {
+            if loss > 0 && !restarted {
+                r.insert(next_seq, loss, held);
+                match r.pop(Some(next_seq), now) {
+                    Some(h) => held = h,
+                    None => return Ok(None),
+                }
+            }
+        }
+        self.accept(conn_ctx, stream_ctx, timeline, stream_id, held)
+            .map(Some)
+    }
+
+    /// Decides whether to switch to `ssrc`, seen with `sequence_number` on a
+    /// packet which doesn't match the current SSRC.
+    fn ssrc_change(&mut self, ssrc: u32, sequence_number: u16) -> SsrcChange {
+        let n = match self.ssrc_change {
+            SsrcChangePolicy::Error => return SsrcChange::Reject,
+            SsrcChangePolicy::AfterGoodbye if self.goodbye => None,
+            SsrcChangePolicy::AfterGoodbye => return SsrcChange::Reject,
+            SsrcChangePolicy::AfterPackets(n) => Some(n),
+        };
+        let packets = match &mut self.candidate {
+            Some(c) if c.ssrc == ssrc && c.next_seq == sequence_number => {
+                c.packets = c.packets.saturating_add(1);
+                c.next_seq = sequence_number.wrapping_add(1);
+                c.packets
+            }
+            c => {
+                *c = Some(SsrcCandidate {
+                    ssrc,
+                    next_seq: sequence_number.wrapping_add(1),
+                    packets: 1,
+                });
+                1
+            }
+        };
+        if self.goodbye || matches!(n, Some(n) if packets >= n.get()) {
+            SsrcChange::Accept {
+                discarded: packets - 1,
+            }
+        } else {
+            SsrcChange::Discard
+        }
+    }
+
+    /// Starts over with a new source, as if no packets had been received.
+    fn restart(&mut self, ssrc: u32, timeline: &mut Timeline) {
+        self.ssrc = Some(ssrc);
+        self.next_seq = None;
+        self.initial_ssrc = false;
+        self.goodbye = false;
+        self.candidate = None;
+        #[cfg(any(feature = "tokio", feature = "async-std"))]
+        {
+            self.stats = None;
+        }
+        if let Some(r) = &mut self.reorder {
+            r.held.clear();
+        }
+        timeline.restart();
+    }
+
+    /// Releases a held packet from the reorder buffer, if one is ready.
+    ///
+    /// If none is ready, arranges for `cx` to be woken when one times out.
+    #[cfg(any(feature = "tokio", feature = "async-std"))]
+    pub(crate) fn poll_reordered(
+        &mut self,
+        cx: &mut Context<'_>,
+        conn_ctx: &ConnectionContext,
+        stream_ctx: &StreamContext,
+        timeline: &mut Timeline,
+        stream_id: usize,
+    ) -> Result<Option<PacketItem>, Error> {
+        let r = match &mut self.reorder {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+        loop {
+            if let Some(held) = r.pop(self.next_seq, Instant::now()) {
+                return self
+                    .accept(conn_ctx, stream_ctx, timeline, stream_id, held)
+                    .map(Some);
+            }
+            let deadline = match r.deadline() {
+                Some(d) => d,
+                None => return Ok(None),
+            };
+            let timer = r
+                .timer
+                .get_or_insert_with(|| Box::pin(crate::runtime::sleep_until(deadline)));
+            if timer.deadline() != deadline {
+                timer.as_mut().reset(deadline);
+            }
+            if timer.as_mut().poll(cx) == Poll::Pending {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Accepts the given packet as the next in sequence, reporting any gap before it as loss.
+    fn accept(
+        &mut self,
+        conn_ctx: &ConnectionContext,
+        stream_ctx: &StreamContext,
+        timeline: &mut Timeline,
+        stream_id: usize,
+        held: HeldPacket,
+    ) -> Result<PacketItem, Error> {
+        // Without an async runtime, there are no receiver reports to keep statistics for.
+        #[cfg_attr(
+            not(any(feature = "tokio", feature = "async-std")),
+            allow(unused_variables)
+        )]
+        let HeldPacket {
+            raw,
+            payload_range,
+            pkt_ctx,
+            arrival,
+        } = held;
+        let sequence_number = raw.sequence_number();
+        let ssrc = raw.ssrc();
+        let loss = sequence_number.wrapping_sub(self.next_seq.unwrap_or(sequence_number));
         let timestamp = match timeline.advance_to(raw.timestamp()) {
             Ok(ts) => ts,
             Err(description) => bail!(ErrorInt::RtpPacketError {
-                conn_ctx: *conn_ctx,
-                pkt_ctx: *pkt_ctx,
+                conn_ctx: conn_ctx.clone(),
+                pkt_ctx,
                 stream_ctx: stream_ctx.to_owned(),
                 stream_id,
                 ssrc,
@@ -149,21 +527,110 @@ impl InorderParser {
                 description,
             }),
         };
+        #[cfg(any(feature = "tokio", feature = "async-std"))]
+        {
+            let arrival = arrival.into_std();
+            match &mut self.stats {
+                Some(stats) => stats.record_rtp(sequence_number, raw.timestamp(), arrival),
+                None => {
+                    let mut stats = ReceptionStats::new(
+                        ssrc,
+                        timeline.clock_rate().get(),
+                        sequence_number,
+                        arrival,
+                    );
+                    stats.record_rtp(sequence_number, raw.timestamp(), arrival);
+                    self.stats = Some(stats);
+                }
+            }
+        }
         self.ssrc = Some(ssrc);
         self.next_seq = Some(sequence_number.wrapping_add(1));
+        self.candidate = None;
         self.seen_packets += 1;
-        Ok(Some(PacketItem::Rtp(ReceivedPacket {
-            ctx: *pkt_ctx,
+        Ok(PacketItem::Rtp(ReceivedPacket {
+            ctx: pkt_ctx,
             stream_id,
             timestamp,
             raw,
             payload_range,
             loss,
-        })))
+        }))
     }
 
     #[allow(clippy::too_many_arguments)]
     pub fn rtcp(
+        &mut self,
+        session_options: &SessionOptions,
+        stream_ctx: &StreamContext,
+        tool: Option<&super::Tool>,
+        conn_ctx: &ConnectionContext,
+        pkt_ctx: &PacketContext,
+        timeline: &mut Timeline,
+        stream_id: usize,
+        data: Bytes,
+    ) -> Result<Option<PacketItem>, Error> {
+        let data = match &mut self.srtp {
+            Some(srtp) => match srtp.unprotect_rtcp(&data) {
+                Ok(data) => data,
+                Err(description) if matches!(stream_ctx.0, StreamContextInner::Udp(_)) => {
+                    log::info!(
+                        "Dropping stream {} SRTCP packet: {}",
+                        stream_id,
+                        description
+                    );
+                    return Ok(None);
+                }
+                Err(description) => bail!(ErrorInt::SrtpAuthenticationError {
+                    conn_ctx: conn_ctx.clone(),
+                    stream_ctx: stream_ctx.to_owned(),
+                    pkt_ctx: *pkt_ctx,
+                    stream_id,
+                    description,
+                }),
+            },
+            None => data,
+        };
+        self.rtcp_plain(
+            session_options,
+            stream_ctx,
+            tool,
+            pkt_ctx,
+            timeline,
+            stream_id,
+            data,
+        )
+        .map_err(|description| {
+            wrap!(ErrorInt::PacketError {
+                conn_ctx: conn_ctx.clone(),
+                stream_ctx: stream_ctx.to_owned(),
+                pkt_ctx: *pkt_ctx,
+                stream_id,
+                description,
+            })
+        })
+    }
+
+    /// Returns a compound RTCP packet with a receiver report from
+    /// `reporter_ssrc` and a source description with `cname`, protected with
+    /// SRTCP if applicable.
+    ///
+    /// The receiver report has a report block iff an RTP packet has been accepted.
+    #[cfg(any(feature = "tokio", feature = "async-std"))]
+    pub(crate) fn receiver_report(&mut self, reporter_ssrc: u32, cname: &str) -> Bytes {
+        let block = self
+            .stats
+            .as_mut()
+            .map(|s| s.report_block(std::time::Instant::now()));
+        let pkt = receiver_report::compound_packet(reporter_ssrc, cname, block.as_ref());
+        match &mut self.srtp {
+            Some(srtp) => srtp.protect_rtcp(&pkt),
+            None => Bytes::from(pkt),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn rtcp_plain(
         &mut self,
         session_options: &SessionOptions,
         stream_ctx: &StreamContext,
@@ -176,39 +643,67 @@ impl InorderParser {
         let first_pkt = crate::rtcp::ReceivedCompoundPacket::validate(&data[..])?;
         let mut rtp_timestamp = None;
         if let Ok(Some(sr)) = first_pkt.as_sender_report() {
-            rtp_timestamp = Some(timeline.place(sr.rtp_timestamp()).map_err(
-                |mut description| {
-                    description.push_str(" in RTCP SR");
-                    description
-                },
-            )?);
-
             let ssrc = sr.ssrc();
             if matches!(self.ssrc, Some(s) if s != ssrc) {
-                if matches!(stream_ctx.0, StreamContextInner::Tcp { .. }) {
-                    super::note_stale_live555_data(tool, session_options);
+                if self.ssrc_change == SsrcChangePolicy::Error {
+                    if matches!(stream_ctx.0, StreamContextInner::Tcp { .. }) {
+                        super::note_stale_live555_data(tool, session_options);
+                    }
+                    return Err(format!(
+                        "Expected ssrc={:08x?}, got RTCP SR ssrc={:08x}",
+                        self.ssrc, ssrc
+                    ));
+                }
+
+                // The SR may be from a new source which hasn't been accepted
+                // yet; its RTP timestamp can't be placed on the timeline.
+                debug!(
+                    "Ignoring RTCP SR ssrc={:08x} when expecting ssrc={:08x?}",
+                    ssrc, self.ssrc
+                );
+            } else {
+                rtp_timestamp = Some(timeline.place(sr.rtp_timestamp()).map_err(
+                    |mut description| {
+                        description.push_str(" in RTCP SR");
+                        description
+                    },
+                )?);
+                self.ssrc = Some(ssrc);
+                #[cfg(any(feature = "tokio", feature = "async-std"))]
+                if let Some(stats) = &mut self.stats {
+                    stats.record_sr(sr.ntp_timestamp(), std::time::Instant::now());
                 }
-                return Err(format!(
-                    "Expected ssrc={:08x?}, got RTCP SR ssrc={:08x}",
-                    self.ssrc, ssrc
-                ));
             }
-            self.ssrc = Some(ssrc);
         }
-        Ok(Some(PacketItem::Rtcp(ReceivedCompoundPacket {
+        let pkt = ReceivedCompoundPacket {
             ctx: *pkt_ctx,
             stream_id,
             rtp_timestamp,
             raw: data,
-        })))
+        };
+        let goodbye = match pkt.goodbye() {
+            Some(bye) => {
+                if matches!(self.ssrc, Some(s) if bye.ssrcs().any(|b| b == s)) {
+                    self.goodbye = true;
+                }
+                true
+            }
+            None => false,
+        };
+        Ok(Some(if goodbye {
+            PacketItem::Goodbye(pkt)
+        } else {
+            PacketItem::Rtcp(pkt)
+        }))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::net::{IpAddr, Ipv4Addr};
+    use std::num::{NonZeroU16, NonZeroU32};
 
-    use crate::client::UdpStreamContext;
+    use crate::UdpStreamContext;
 
     use super::*;
 
@@ -356,4 +851,195 @@ mod tests {
             o => panic!("unexpected packet 2 result: {o:#?}"),
         }
     }
+
+    #[cfg(any(feature = "tokio", feature = "async-std"))]
+    #[tokio::test(start_paused = true)]
+    async fn reorder() {
+        let mut timeline = Timeline::new(None, 90_000, None).unwrap();
+        let mut parser =
+            InorderParser::new(Some(0xd25614e), None).with_reorder(Some(ReorderConfig {
+                max_packets: 3,
+                max_delay: Duration::from_millis(100),
+            }));
+        let stream_ctx = StreamContext(StreamContextInner::Udp(UdpStreamContext {
+            local_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            peer_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            local_rtp_port: 0,
+            peer_rtp_port: 0,
+        }));
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let rtp = |parser: &mut InorderParser, timeline: &mut Timeline, seq: u16| {
+            let (pkt, _payload_range) = crate::rtp::RawPacketBuilder {
+                sequence_number: seq,
+                timestamp: u32::from(seq),
+                payload_type: 96,
+                ssrc: 0xd25614e,
+                mark: true,
+            }
+            .build(*b"foo")
+            .unwrap();
+            match parser.rtp(
+                &SessionOptions::default(),
+                &stream_ctx,
+                None,
+                &ConnectionContext::dummy(),
+                &PacketContext::dummy(),
+                timeline,
+                0,
+                pkt.0,
+            ) {
+                Ok(Some(PacketItem::Rtp(p))) => Some((p.sequence_number(), p.loss())),
+                Ok(None) => None,
+                o => panic!("unexpected result for seq={seq}: {o:#?}"),
+            }
+        };
+        let mut reordered = |parser: &mut InorderParser, timeline: &mut Timeline| match parser
+            .poll_reordered(
+                &mut cx,
+                &ConnectionContext::dummy(),
+                &stream_ctx,
+                timeline,
+                0,
+            ) {
+            Ok(Some(PacketItem::Rtp(p))) => Some((p.sequence_number(), p.loss())),
+            Ok(None) => None,
+            o => panic!("unexpected result: {o:#?}"),
+        };
+
+        // Slightly out-of-order packets are released in order without loss.
+        assert_eq!(rtp(&mut parser, &mut timeline, 1), Some((1, 0)));
+        assert_eq!(rtp(&mut parser, &mut timeline, 3), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 4), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 3), None); // duplicate
+        assert_eq!(reordered(&mut parser, &mut timeline), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 2), Some((2, 0)));
+        assert_eq!(reordered(&mut parser, &mut timeline), Some((3, 0)));
+        assert_eq!(reordered(&mut parser, &mut timeline), Some((4, 0)));
+        assert_eq!(reordered(&mut parser, &mut timeline), None);
+
+        // A gap is reported as loss once it times out...
+        assert_eq!(rtp(&mut parser, &mut timeline, 6), None);
+        tokio::time::advance(Duration::from_millis(99)).await;
+        assert_eq!(reordered(&mut parser, &mut timeline), None);
+        tokio::time::advance(Duration::from_millis(1)).await;
+        assert_eq!(reordered(&mut parser, &mut timeline), Some((6, 1)));
+
+        // ...or when the buffer fills. A straggler is then dropped.
+        assert_eq!(rtp(&mut parser, &mut timeline, 8), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 9), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 10), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 11), Some((8, 1)));
+        assert_eq!(reordered(&mut parser, &mut timeline), Some((9, 0)));
+        assert_eq!(rtp(&mut parser, &mut timeline, 7), None);
+        assert_eq!(reordered(&mut parser, &mut timeline), Some((10, 0)));
+        assert_eq!(reordered(&mut parser, &mut timeline), Some((11, 0)));
+    }
+
+    #[test]
+    fn ssrc_change() {
+        let stream_ctx = StreamContext::dummy();
+        let rtp = |parser: &mut InorderParser, timeline: &mut Timeline, ssrc: u32, seq: u16| {
+            let (pkt, _payload_range) = crate::rtp::RawPacketBuilder {
+                sequence_number: seq,
+                timestamp: u32::from(seq) * 10,
+                payload_type: 96,
+                ssrc,
+                mark: true,
+            }
+            .build(*b"foo")
+            .unwrap();
+            parser
+                .rtp(
+                    &SessionOptions::default(),
+                    &stream_ctx,
+                    None,
+                    &ConnectionContext::dummy(),
+                    &PacketContext::dummy(),
+                    timeline,
+                    0,
+                    pkt.0,
+                )
+                .map(|item| match item {
+                    Some(PacketItem::Rtp(p)) => Some((p.ssrc(), p.loss(), p.timestamp().elapsed())),
+                    None => None,
+                    o => panic!("unexpected result for seq={seq}: {o:#?}"),
+                })
+        };
+        let bye = |parser: &mut InorderParser, timeline: &mut Timeline| match parser.rtcp(
+            &SessionOptions::default(),
+            &stream_ctx,
+            None,
+            &ConnectionContext::dummy(),
+            &PacketContext::dummy(),
+            timeline,
+            0,
+            Bytes::from_static(
+                b"\x80\xc9\x00\x01\x00\x00\x00\x01\
+                      \x81\xcb\x00\x01\x00\x00\x00\xaa",
+            ),
+        ) {
+            Ok(Some(PacketItem::Goodbye(p))) => {
+                assert_eq!(p.goodbye().unwrap().ssrcs().collect::<Vec<_>>(), [0xaa]);
+            }
+            o => panic!("unexpected result: {o:#?}"),
+        };
+
+        // By default, a new SSRC is an error, even after a BYE.
+        let mut timeline = Timeline::new(None, 90_000, None).unwrap();
+        let mut parser = InorderParser::new(None, None);
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xaa, 1).unwrap(),
+            Some((0xaa, 0, 0))
+        );
+        bye(&mut parser, &mut timeline);
+        rtp(&mut parser, &mut timeline, 0xbb, 100).unwrap_err();
+
+        // After a BYE, a new SSRC starts over, continuing the timeline.
+        let mut timeline = Timeline::new(None, 90_000, NonZeroU32::new(10)).unwrap();
+        let mut parser =
+            InorderParser::new(None, None).with_ssrc_change(SsrcChangePolicy::AfterGoodbye);
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xaa, 1).unwrap(),
+            Some((0xaa, 0, 0))
+        );
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xaa, 2).unwrap(),
+            Some((0xaa, 0, 10))
+        );
+        rtp(&mut parser, &mut timeline, 0xbb, 100).unwrap_err();
+        bye(&mut parser, &mut timeline);
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xbb, 100).unwrap(),
+            Some((0xbb, 0, 11))
+        );
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xbb, 102).unwrap(),
+            Some((0xbb, 1, 31))
+        );
+
+        // Or after enough consecutive packets, discarding them until then.
+        let mut timeline = Timeline::new(None, 90_000, None).unwrap();
+        let mut parser = InorderParser::new(None, None)
+            .with_ssrc_change(SsrcChangePolicy::AfterPackets(NonZeroU16::new(3).unwrap()));
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xaa, 1).unwrap(),
+            Some((0xaa, 0, 0))
+        );
+        assert_eq!(rtp(&mut parser, &mut timeline, 0xbb, 100).unwrap(), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 0xbb, 101).unwrap(), None);
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xaa, 2).unwrap(),
+            Some((0xaa, 0, 10))
+        );
+        assert_eq!(rtp(&mut parser, &mut timeline, 0xbb, 102).unwrap(), None);
+        assert_eq!(rtp(&mut parser, &mut timeline, 0xbb, 103).unwrap(), None);
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xbb, 104).unwrap(),
+            Some((0xbb, 2, 11))
+        );
+        assert_eq!(
+            rtp(&mut parser, &mut timeline, 0xbb, 105).unwrap(),
+            Some((0xbb, 0, 21))
+        );
+    }
 }
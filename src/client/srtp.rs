@@ -0,0 +1,884 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Secure RTP (SRTP) and RTCP (SRTCP), as described in
+//! [RFC 3711](https://datatracker.ietf.org/doc/html/rfc3711) and, for
+//! `AEAD_AES_128_GCM`, [RFC 7714](https://datatracker.ietf.org/doc/html/rfc7714).
+//!
+//! Keys come from SDP `a=crypto` attributes, as described in
+//! [RFC 4568](https://datatracker.ietf.org/doc/html/rfc4568), or from MIKEY
+//! messages in `a=key-mgmt` attributes; see [`super::mikey`]. Master key
+//! identifiers (MKIs) and non-zero key derivation rates aren't supported.
+
+use std::collections::HashMap;
+
+use aes::cipher::{InnerIvInit, KeyInit, StreamCipher};
+use aes_gcm::aead::AeadInPlace;
+use bytes::{Bytes, BytesMut};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Length of the SRTCP `E` flag and index which follow the encrypted portion.
+const SRTCP_INDEX_LEN: usize = 4;
+
+/// Length of the HMAC-SHA1 tag on SRTCP packets, regardless of crypto suite.
+const SRTCP_HMAC_TAG_LEN: usize = 10;
+
+const GCM_TAG_LEN: usize = 16;
+
+// Key derivation labels; see RFC 3711 section 4.3.2.
+const LABEL_RTP_ENCRYPTION: u8 = 0;
+const LABEL_RTP_AUTH: u8 = 1;
+const LABEL_RTP_SALT: u8 = 2;
+const LABEL_RTCP_ENCRYPTION: u8 = 3;
+const LABEL_RTCP_AUTH: u8 = 4;
+const LABEL_RTCP_SALT: u8 = 5;
+
+/// SRTP keys offered for a single stream.
+#[derive(Clone, Debug)]
+pub(crate) enum Keys {
+    /// A master key from an SDES `a=crypto` attribute.
+    Sdes(MasterKey),
+
+    /// A MIKEY message from an `a=key-mgmt` attribute. Its keys may be
+    /// encrypted with a pre-shared key, so they're unwrapped at `SETUP` time.
+    Mikey(super::mikey::Message),
+}
+
+impl Keys {
+    /// Derives the session keys for a new cryptographic context, using the
+    /// given MIKEY pre-shared key if necessary.
+    pub(crate) fn context(&self, psk: Option<&[u8]>) -> Result<Context, String> {
+        match self {
+            Keys::Sdes(key) => Ok(key.context()),
+            Keys::Mikey(msg) => Ok(msg.master_key(psk)?.context()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Suite {
+    AesCm128HmacSha1_80,
+    AesCm128HmacSha1_32,
+    AeadAes128Gcm,
+}
+
+impl Suite {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "AES_CM_128_HMAC_SHA1_80" => Some(Suite::AesCm128HmacSha1_80),
+            "AES_CM_128_HMAC_SHA1_32" => Some(Suite::AesCm128HmacSha1_32),
+            "AEAD_AES_128_GCM" => Some(Suite::AeadAes128Gcm),
+            _ => None,
+        }
+    }
+
+    pub(super) fn salt_len(self) -> usize {
+        match self {
+            Suite::AeadAes128Gcm => 12,
+            _ => 14,
+        }
+    }
+}
+
+/// A master key and salt, as supplied in an `a=crypto` attribute or a MIKEY message.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct MasterKey {
+    suite: Suite,
+    key: [u8; 16],
+
+    /// The master salt, padded with zeros to 14 bytes.
+    salt: [u8; 14],
+}
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't log the key material.
+        f.debug_struct("MasterKey")
+            .field("suite", &self.suite)
+            .finish()
+    }
+}
+
+impl MasterKey {
+    /// Creates a master key from its parts, as supplied via MIKEY.
+    pub(super) fn new(suite: Suite, key: &[u8], salt: &[u8]) -> Result<Self, String> {
+        let key: [u8; 16] = key
+            .try_into()
+            .map_err(|_| format!("{suite:?} master key has {} bytes; expected 16", key.len()))?;
+        if salt.len() != suite.salt_len() {
+            return Err(format!(
+                "{:?} master salt has {} bytes; expected {}",
+                suite,
+                salt.len(),
+                suite.salt_len()
+            ));
+        }
+        let mut padded_salt = [0; 14];
+        padded_salt[..salt.len()].copy_from_slice(salt);
+        Ok(MasterKey {
+            suite,
+            key,
+            salt: padded_salt,
+        })
+    }
+
+    /// Parses the value of an `a=crypto` attribute, as described in RFC 4568
+    /// section 9.1.
+    ///
+    /// Returns `Ok(None)` for unknown crypto suites, so the caller can try
+    /// another offered attribute.
+    pub(crate) fn parse_crypto(value: &str) -> Result<Option<Self>, String> {
+        // crypto-attribute = tag 1*WSP crypto-suite 1*WSP key-params *(1*WSP session-param)
+        let mut parts = value.split_ascii_whitespace();
+        let (suite, key_params) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(_tag), Some(suite), Some(key_params)) => (suite, key_params),
+            _ => return Err(format!("bad crypto attribute {value:?}")),
+        };
+        let suite = match Suite::parse(suite) {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+        for param in parts {
+            match param.split_once('=') {
+                Some(("KDR", "0")) => {}
+                _ if param.starts_with("KDR=")
+                    || param.starts_with("UNENCRYPTED_")
+                    || param.starts_with("UNAUTHENTICATED_") =>
+                {
+                    return Err(format!("unsupported crypto session parameter {param:?}"));
+                }
+                _ => {}
+            }
+        }
+        if key_params.contains(';') {
+            return Err("multiple crypto keys aren't supported".to_owned());
+        }
+        let key_info = key_params
+            .strip_prefix("inline:")
+            .ok_or_else(|| format!("unsupported crypto key method in {key_params:?}"))?;
+
+        // key-info = key-salt ["|" lifetime] ["|" mki]. The lifetime is
+        // ignored; the server is expected to supply a fresh key via a new
+        // DESCRIBE before it's exhausted.
+        let mut key_info = key_info.split('|');
+        let key_salt = key_info.next().expect("split returns at least one item");
+        if key_info.any(|p| p.contains(':')) {
+            return Err("crypto master key identifiers (MKIs) aren't supported".to_owned());
+        }
+        let key_salt = base64::decode(key_salt).map_err(|_| "bad crypto key base64".to_owned())?;
+        if key_salt.len() != 16 + suite.salt_len() {
+            return Err(format!(
+                "{:?} crypto key has {} bytes; expected {}",
+                suite,
+                key_salt.len(),
+                16 + suite.salt_len()
+            ));
+        }
+        let mut key = [0; 16];
+        key.copy_from_slice(&key_salt[..16]);
+        let mut salt = [0; 14];
+        salt[..suite.salt_len()].copy_from_slice(&key_salt[16..]);
+        Ok(Some(MasterKey { suite, key, salt }))
+    }
+
+    /// Derives the session keys for a new cryptographic context.
+    pub(crate) fn context(&self) -> Context {
+        Context {
+            suite: self.suite,
+            rtp: self.session_keys(LABEL_RTP_ENCRYPTION, LABEL_RTP_AUTH, LABEL_RTP_SALT),
+            rtcp: self.session_keys(LABEL_RTCP_ENCRYPTION, LABEL_RTCP_AUTH, LABEL_RTCP_SALT),
+            sources: HashMap::new(),
+            next_srtcp_index: 0,
+        }
+    }
+
+    fn session_keys(&self, encryption: u8, auth: u8, salt: u8) -> SessionKeys {
+        let mut key = [0; 16];
+        self.derive(encryption, &mut key);
+        let mut session_salt = [0; 14];
+        self.derive(salt, &mut session_salt[..self.suite.salt_len()]);
+        let cipher = match self.suite {
+            Suite::AeadAes128Gcm => Cipher::Gcm(aes_gcm::Aes128Gcm::new(&key.into())),
+            Suite::AesCm128HmacSha1_80 | Suite::AesCm128HmacSha1_32 => {
+                let mut auth_key = [0; 20];
+                self.derive(auth, &mut auth_key);
+                Cipher::AesCm {
+                    aes: aes::Aes128::new(&key.into()),
+                    hmac: <Hmac<Sha1> as KeyInit>::new_from_slice(&auth_key)
+                        .expect("HMAC accepts any key length"),
+                }
+            }
+        };
+        SessionKeys {
+            cipher,
+            salt: session_salt,
+        }
+    }
+
+    /// Fills `out` via the AES-CM key derivation function, as described in
+    /// RFC 3711 section 4.3.3, with a key derivation rate of 0.
+    fn derive(&self, label: u8, out: &mut [u8]) {
+        let mut iv = [0; 16];
+        iv[..14].copy_from_slice(&self.salt);
+        iv[7] ^= label;
+        out.fill(0);
+        Aes128Ctr::from_core(ctr::CtrCore::inner_iv_init(
+            aes::Aes128::new(&self.key.into()),
+            &iv.into(),
+        ))
+        .apply_keystream(out);
+    }
+}
+
+enum Cipher {
+    AesCm { aes: aes::Aes128, hmac: Hmac<Sha1> },
+    Gcm(aes_gcm::Aes128Gcm),
+}
+
+struct SessionKeys {
+    cipher: Cipher,
+
+    /// The session salt, padded with zeros to 14 bytes.
+    salt: [u8; 14],
+}
+
+impl SessionKeys {
+    /// Applies the AES-CM keystream for the given SSRC and index to `buf`,
+    /// as described in RFC 3711 section 4.1.1.
+    fn apply_aes_cm(aes: &aes::Aes128, salt: &[u8; 14], ssrc: u32, index: u64, buf: &mut [u8]) {
+        let mut iv = [0; 16];
+        iv[..14].copy_from_slice(salt);
+        for (b, s) in iv[4..8].iter_mut().zip(ssrc.to_be_bytes()) {
+            *b ^= s;
+        }
+        for (b, i) in iv[8..14].iter_mut().zip(&index.to_be_bytes()[2..]) {
+            *b ^= i;
+        }
+        Aes128Ctr::from_core(ctr::CtrCore::inner_iv_init(aes.clone(), &iv.into()))
+            .apply_keystream(buf);
+    }
+
+    /// Returns the 12-byte AEAD nonce given the packet-specific fields.
+    fn gcm_nonce(&self, fields: [u8; 12]) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        for ((n, s), f) in nonce.iter_mut().zip(&self.salt[..12]).zip(fields) {
+            *n = s ^ f;
+        }
+        nonce
+    }
+}
+
+/// The cryptographic context for a single stream's SRTP and SRTCP packets.
+pub(crate) struct Context {
+    suite: Suite,
+    rtp: SessionKeys,
+    rtcp: SessionKeys,
+
+    /// Receive state for each SSRC, which RFC 3711 section 3.2.3 keeps
+    /// separately. A source's entry is created on its first authenticated
+    /// packet, so stray packets from another SSRC don't disturb the current
+    /// one's rollover counter.
+    sources: HashMap<u32, Source>,
+
+    /// The SRTCP index of the next RTCP packet to send.
+    next_srtcp_index: u32,
+}
+
+impl std::fmt::Debug for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("suite", &self.suite)
+            .field("sources", &self.sources)
+            .field("next_srtcp_index", &self.next_srtcp_index)
+            .finish()
+    }
+}
+
+/// The size of the SRTP and SRTCP replay windows, the minimum allowed by RFC
+/// 3711 section 3.3.2.
+const REPLAY_WINDOW_LEN: u64 = 64;
+
+/// The receive state of a single source.
+#[derive(Debug, Default)]
+struct Source {
+    /// The rollover counter: the number of times the RTP sequence number has wrapped.
+    roc: u32,
+
+    /// The highest RTP sequence number authenticated so far, `s_l` in RFC 3711.
+    highest_seq: Option<u16>,
+
+    /// The replay list of SRTP packet indices.
+    rtp_replay: ReplayList,
+
+    /// The replay list of SRTCP indices.
+    rtcp_replay: ReplayList,
+}
+
+/// A sliding window replay list, as described in RFC 3711 section 3.3.2.
+#[derive(Debug, Default)]
+struct ReplayList {
+    /// The highest index authenticated so far.
+    highest: Option<u64>,
+
+    /// Bit `i` is set iff the index `i` below the highest has been authenticated.
+    window: u64,
+}
+
+impl ReplayList {
+    /// Checks an index which has not yet been authenticated, returning an
+    /// error if it has been seen before or is too old to tell.
+    fn check(&self, protocol: &str, index: u64) -> Result<(), String> {
+        let highest = match self.highest {
+            None => return Ok(()),
+            Some(h) => h,
+        };
+        if index > highest {
+            return Ok(());
+        }
+        let age = highest - index;
+        if age >= REPLAY_WINDOW_LEN {
+            return Err(format!(
+                "{protocol} packet index {index:#x} is too far behind highest {highest:#x}"
+            ));
+        }
+        if self.window & (1 << age) != 0 {
+            return Err(format!("replayed {protocol} packet index {index:#x}"));
+        }
+        Ok(())
+    }
+
+    /// Marks an index as seen after authenticating its packet.
+    fn update(&mut self, index: u64) {
+        let highest = match self.highest {
+            None => {
+                self.highest = Some(index);
+                self.window = 1;
+                return;
+            }
+            Some(h) => h,
+        };
+        if index > highest {
+            let shift = index - highest;
+            self.window = if shift >= REPLAY_WINDOW_LEN {
+                0
+            } else {
+                self.window << shift
+            } | 1;
+            self.highest = Some(index);
+        } else {
+            self.window |= 1 << (highest - index);
+        }
+    }
+}
+
+/// Returns the 48-bit SRTP packet index, as described in RFC 3711 section 3.3.1.
+fn packet_index(roc: u32, seq: u16) -> u64 {
+    u64::from(roc) << 16 | u64::from(seq)
+}
+
+impl Source {
+    /// Estimates the rollover counter for the given sequence number, as
+    /// described in RFC 3711 appendix A.
+    fn estimate_roc(&self, seq: u16) -> u32 {
+        let s_l = match self.highest_seq {
+            None => return self.roc,
+            Some(s) => s,
+        };
+        if s_l < 0x8000 {
+            if seq > s_l && seq - s_l > 0x8000 {
+                return self.roc.wrapping_sub(1);
+            }
+        } else if s_l - 0x8000 > seq {
+            return self.roc.wrapping_add(1);
+        }
+        self.roc
+    }
+
+    /// Updates the rollover counter, highest sequence number, and replay list
+    /// after authenticating a packet, as described in RFC 3711 section 3.3.1.
+    fn update_rtp(&mut self, roc: u32, seq: u16) {
+        let index = packet_index(roc, seq);
+        let is_highest = match self.highest_seq {
+            None => true,
+            Some(s_l) => index > packet_index(self.roc, s_l),
+        };
+        if is_highest {
+            self.roc = roc;
+            self.highest_seq = Some(seq);
+        }
+        self.rtp_replay.update(index);
+    }
+}
+
+impl Context {
+    /// Authenticates and decrypts an SRTP packet, returning the plain RTP packet.
+    pub(crate) fn unprotect_rtp(&mut self, data: &[u8]) -> Result<Bytes, String> {
+        let header_len = rtp_header_len(data)?;
+        let tag_len = match self.suite {
+            Suite::AesCm128HmacSha1_80 => 10,
+            Suite::AesCm128HmacSha1_32 => 4,
+            Suite::AeadAes128Gcm => GCM_TAG_LEN,
+        };
+        if data.len() < header_len + tag_len {
+            return Err(format!(
+                "SRTP packet of {} bytes is too short for {}-byte header and {}-byte tag",
+                data.len(),
+                header_len,
+                tag_len
+            ));
+        }
+        let seq = u16::from_be_bytes([data[2], data[3]]);
+        let ssrc = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+        let roc = match self.sources.get(&ssrc) {
+            Some(source) => {
+                let roc = source.estimate_roc(seq);
+                source.rtp_replay.check("SRTP", packet_index(roc, seq))?;
+                roc
+            }
+            None => 0,
+        };
+        let (authenticated, tag) = data.split_at(data.len() - tag_len);
+        let mut buf = BytesMut::from(authenticated);
+        match &self.rtp.cipher {
+            Cipher::AesCm { aes, hmac } => {
+                let mut hmac = hmac.clone();
+                hmac.update(authenticated);
+                hmac.update(&roc.to_be_bytes());
+                hmac.verify_truncated_left(tag)
+                    .map_err(|_| "SRTP authentication tag mismatch".to_owned())?;
+                let index = packet_index(roc, seq);
+                SessionKeys::apply_aes_cm(aes, &self.rtp.salt, ssrc, index, &mut buf[header_len..]);
+            }
+            Cipher::Gcm(gcm) => {
+                // RFC 7714 section 8.1.
+                let mut fields = [0; 12];
+                fields[2..6].copy_from_slice(&ssrc.to_be_bytes());
+                fields[6..10].copy_from_slice(&roc.to_be_bytes());
+                fields[10..12].copy_from_slice(&seq.to_be_bytes());
+                let nonce = self.rtp.gcm_nonce(fields);
+                let (header, payload) = buf.split_at_mut(header_len);
+                gcm.decrypt_in_place_detached(&nonce.into(), header, payload, tag.into())
+                    .map_err(|_| "SRTP authentication tag mismatch".to_owned())?;
+            }
+        }
+
+        self.sources.entry(ssrc).or_default().update_rtp(roc, seq);
+        Ok(buf.freeze())
+    }
+
+    /// Authenticates and decrypts an SRTCP packet, returning the plain RTCP
+    /// compound packet.
+    pub(crate) fn unprotect_rtcp(&mut self, data: &[u8]) -> Result<Bytes, String> {
+        let tag_len = match self.suite {
+            Suite::AesCm128HmacSha1_80 | Suite::AesCm128HmacSha1_32 => SRTCP_HMAC_TAG_LEN,
+            Suite::AeadAes128Gcm => GCM_TAG_LEN,
+        };
+        if data.len() < 8 + tag_len + SRTCP_INDEX_LEN {
+            return Err(format!("SRTCP packet of {} bytes is too short", data.len()));
+        }
+        let ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let e_index_pos = match &self.rtcp.cipher {
+            Cipher::AesCm { .. } => data.len() - tag_len - SRTCP_INDEX_LEN,
+            Cipher::Gcm(_) => data.len() - SRTCP_INDEX_LEN,
+        };
+        let index = u64::from(
+            u32::from_be_bytes(
+                data[e_index_pos..e_index_pos + SRTCP_INDEX_LEN]
+                    .try_into()
+                    .expect("slice has SRTCP_INDEX_LEN bytes"),
+            ) & 0x7fff_ffff,
+        );
+        if let Some(source) = self.sources.get(&ssrc) {
+            source.rtcp_replay.check("SRTCP", index)?;
+        }
+        let plain = match &self.rtcp.cipher {
+            Cipher::AesCm { aes, hmac } => {
+                // header || encrypted portion || E || index || tag
+                let (authenticated, tag) = data.split_at(data.len() - tag_len);
+                let mut hmac = hmac.clone();
+                hmac.update(authenticated);
+                hmac.verify_truncated_left(tag)
+                    .map_err(|_| "SRTCP authentication tag mismatch".to_owned())?;
+                let (body, e_index) = authenticated.split_at(authenticated.len() - SRTCP_INDEX_LEN);
+                let mut buf = BytesMut::from(body);
+                if e_index[0] & 0x80 != 0 {
+                    SessionKeys::apply_aes_cm(aes, &self.rtcp.salt, ssrc, index, &mut buf[8..]);
+                }
+                buf.freeze()
+            }
+            Cipher::Gcm(gcm) => {
+                // RFC 7714 section 9: header || ciphertext || tag || E || index
+                let (body, e_index) = data.split_at(data.len() - SRTCP_INDEX_LEN);
+                let (body, tag) = body.split_at(body.len() - tag_len);
+                let encrypted = e_index[0] & 0x80 != 0;
+                let mut fields = [0; 12];
+                fields[2..6].copy_from_slice(&ssrc.to_be_bytes());
+                fields[8..12].copy_from_slice(e_index);
+                fields[8] &= 0x7f;
+                let nonce = self.rtcp.gcm_nonce(fields);
+                let mut buf = BytesMut::from(body);
+                let r = if encrypted {
+                    let mut aad = [0; 8 + SRTCP_INDEX_LEN];
+                    aad[..8].copy_from_slice(&body[..8]);
+                    aad[8..].copy_from_slice(e_index);
+                    gcm.decrypt_in_place_detached(&nonce.into(), &aad, &mut buf[8..], tag.into())
+                } else {
+                    let mut aad = body.to_vec();
+                    aad.extend_from_slice(e_index);
+                    gcm.decrypt_in_place_detached(&nonce.into(), &aad, &mut [], tag.into())
+                };
+                r.map_err(|_| "SRTCP authentication tag mismatch".to_owned())?;
+                buf.freeze()
+            }
+        };
+        self.sources
+            .entry(ssrc)
+            .or_default()
+            .rtcp_replay
+            .update(index);
+        Ok(plain)
+    }
+
+    /// Encrypts and authenticates an outgoing RTCP compound packet.
+    ///
+    /// `plain` must contain at least the first RTCP header and sender SSRC.
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+    pub(crate) fn protect_rtcp(&mut self, plain: &[u8]) -> Bytes {
+        assert!(plain.len() >= 8);
+        let index = self.next_srtcp_index;
+        self.next_srtcp_index = (index + 1) & 0x7fff_ffff;
+        let e_index = (0x8000_0000 | index).to_be_bytes();
+        let ssrc = u32::from_be_bytes([plain[4], plain[5], plain[6], plain[7]]);
+        let mut buf = BytesMut::with_capacity(plain.len() + SRTCP_INDEX_LEN + GCM_TAG_LEN);
+        buf.extend_from_slice(plain);
+        match &self.rtcp.cipher {
+            Cipher::AesCm { aes, hmac } => {
+                SessionKeys::apply_aes_cm(
+                    aes,
+                    &self.rtcp.salt,
+                    ssrc,
+                    u64::from(index),
+                    &mut buf[8..],
+                );
+                buf.extend_from_slice(&e_index);
+                let mut hmac = hmac.clone();
+                hmac.update(&buf);
+                buf.extend_from_slice(&hmac.finalize().into_bytes()[..SRTCP_HMAC_TAG_LEN]);
+            }
+            Cipher::Gcm(gcm) => {
+                let mut fields = [0; 12];
+                fields[2..6].copy_from_slice(&ssrc.to_be_bytes());
+                fields[8..12].copy_from_slice(&index.to_be_bytes());
+                let nonce = self.rtcp.gcm_nonce(fields);
+                let mut aad = [0; 8 + SRTCP_INDEX_LEN];
+                aad[..8].copy_from_slice(&plain[..8]);
+                aad[8..].copy_from_slice(&e_index);
+                let tag = gcm
+                    .encrypt_in_place_detached(&nonce.into(), &aad, &mut buf[8..])
+                    .expect("RTCP packets are far smaller than the GCM length limit");
+                buf.extend_from_slice(&tag);
+                buf.extend_from_slice(&e_index);
+            }
+        }
+        buf.freeze()
+    }
+}
+
+/// Returns the length of the RTP header, including CSRCs and any extension.
+fn rtp_header_len(data: &[u8]) -> Result<usize, String> {
+    if data.len() < 12 {
+        return Err(format!("SRTP packet of {} bytes is too short", data.len()));
+    }
+    if data[0] >> 6 != 2 {
+        return Err(format!("SRTP packet has bad version {}", data[0] >> 6));
+    }
+    let csrc_count = usize::from(data[0] & 0b1111);
+    let mut len = 12 + 4 * csrc_count;
+    if data[0] & 0b0001_0000 != 0 {
+        let ext = data
+            .get(len + 2..len + 4)
+            .ok_or_else(|| "SRTP packet too short for header extension".to_owned())?;
+        len += 4 + 4 * usize::from(u16::from_be_bytes([ext[0], ext[1]]));
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(suite: Suite) -> MasterKey {
+        let salt_len = suite.salt_len();
+        let mut salt = [0; 14];
+        salt[..salt_len].copy_from_slice(
+            &b"\x0e\xc6\x75\xad\x49\x8a\xfe\xeb\xb6\x96\x0b\x3a\xab\xe6"[..salt_len],
+        );
+        MasterKey {
+            suite,
+            key: *b"\xe1\xf9\x7a\x0d\x3e\x01\x8b\xe0\xd6\x4f\xa3\x2c\x06\xde\x41\x39",
+            salt,
+        }
+    }
+
+    /// Protects an RTP packet, mirroring `Context::unprotect_rtp`.
+    fn protect_rtp(ctx: &Context, roc: u32, plain: &[u8]) -> Vec<u8> {
+        let header_len = rtp_header_len(plain).unwrap();
+        let seq = u16::from_be_bytes([plain[2], plain[3]]);
+        let ssrc = u32::from_be_bytes([plain[8], plain[9], plain[10], plain[11]]);
+        let mut buf = plain.to_vec();
+        match &ctx.rtp.cipher {
+            Cipher::AesCm { aes, hmac } => {
+                let index = packet_index(roc, seq);
+                SessionKeys::apply_aes_cm(aes, &ctx.rtp.salt, ssrc, index, &mut buf[header_len..]);
+                let mut hmac = hmac.clone();
+                hmac.update(&buf);
+                hmac.update(&roc.to_be_bytes());
+                let tag = hmac.finalize().into_bytes();
+                let tag_len = match ctx.suite {
+                    Suite::AesCm128HmacSha1_80 => 10,
+                    _ => 4,
+                };
+                buf.extend_from_slice(&tag[..tag_len]);
+            }
+            Cipher::Gcm(gcm) => {
+                let mut fields = [0; 12];
+                fields[2..6].copy_from_slice(&ssrc.to_be_bytes());
+                fields[6..10].copy_from_slice(&roc.to_be_bytes());
+                fields[10..12].copy_from_slice(&seq.to_be_bytes());
+                let nonce = ctx.rtp.gcm_nonce(fields);
+                let (header, payload) = buf.split_at_mut(header_len);
+                let tag = gcm
+                    .encrypt_in_place_detached(&nonce.into(), header, payload)
+                    .unwrap();
+                buf.extend_from_slice(&tag);
+            }
+        }
+        buf
+    }
+
+    fn rtp(seq: u16) -> Vec<u8> {
+        let (pkt, _) = crate::rtp::RawPacketBuilder {
+            sequence_number: seq,
+            timestamp: 0x1234,
+            payload_type: 96,
+            ssrc: 0xcafe_babe,
+            mark: true,
+        }
+        .build(*b"hello world")
+        .unwrap();
+        pkt.0.to_vec()
+    }
+
+    /// Tests key derivation against the vectors in RFC 3711 appendix B.3.
+    #[test]
+    fn key_derivation() {
+        let key = key(Suite::AesCm128HmacSha1_80);
+        let mut cipher_key = [0; 16];
+        key.derive(LABEL_RTP_ENCRYPTION, &mut cipher_key);
+        assert_eq!(
+            cipher_key,
+            *b"\xc6\x1e\x7a\x93\x74\x4f\x39\xee\x10\x73\x4a\xfe\x3f\xf7\xa0\x87"
+        );
+        let mut cipher_salt = [0; 14];
+        key.derive(LABEL_RTP_SALT, &mut cipher_salt);
+        assert_eq!(
+            cipher_salt,
+            *b"\x30\xcb\xbc\x08\x86\x3d\x8c\x85\xd4\x9d\xb3\x4a\x9a\xe1"
+        );
+        let mut auth_key = [0; 20];
+        key.derive(LABEL_RTP_AUTH, &mut auth_key);
+        assert_eq!(
+            auth_key,
+            *b"\xce\xbe\x32\x1f\x6f\xf7\x71\x6b\x6f\xd4\xab\x49\xaf\x25\x6a\x15\x6d\x38\xba\xa4"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for suite in [
+            Suite::AesCm128HmacSha1_80,
+            Suite::AesCm128HmacSha1_32,
+            Suite::AeadAes128Gcm,
+        ] {
+            let mut ctx = key(suite).context();
+            let plain = rtp(1);
+            let protected = protect_rtp(&ctx, 0, &plain);
+            assert_ne!(&protected[12..23], &plain[12..]);
+            assert_eq!(&ctx.unprotect_rtp(&protected).unwrap()[..], &plain[..]);
+
+            // Any modification should fail authentication.
+            let mut tampered = protected.clone();
+            tampered[14] ^= 1;
+            ctx.unprotect_rtp(&tampered).unwrap_err();
+        }
+    }
+
+    /// Tests that the rollover counter advances when the sequence number wraps.
+    #[test]
+    fn rollover() {
+        let mut ctx = key(Suite::AesCm128HmacSha1_80).context();
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 0, &rtp(0xfffe)))
+            .unwrap();
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 1, &rtp(0x0001)))
+            .unwrap();
+        assert_eq!(ctx.sources[&0xcafe_babe].roc, 1);
+
+        // A late packet from before the wrap still uses the old counter.
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 0, &rtp(0xffff)))
+            .unwrap();
+        assert_eq!(ctx.sources[&0xcafe_babe].roc, 1);
+        assert_eq!(ctx.sources[&0xcafe_babe].highest_seq, Some(0x0001));
+    }
+
+    /// Tests rejecting replayed packets and those too old for the replay window.
+    #[test]
+    fn replay() {
+        let mut ctx = key(Suite::AesCm128HmacSha1_80).context();
+        let first = protect_rtp(&ctx, 0, &rtp(100));
+        ctx.unprotect_rtp(&first).unwrap();
+        ctx.unprotect_rtp(&first).unwrap_err();
+
+        // A reordered packet within the window is accepted once.
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 0, &rtp(200))).unwrap();
+        let late = protect_rtp(&ctx, 0, &rtp(150));
+        ctx.unprotect_rtp(&late).unwrap();
+        ctx.unprotect_rtp(&late).unwrap_err();
+
+        // One from before the window is rejected even if never seen.
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 0, &rtp(101)))
+            .unwrap_err();
+    }
+
+    /// Tests that a new source starts with its own rollover counter.
+    #[test]
+    fn new_source() {
+        let mut ctx = key(Suite::AesCm128HmacSha1_80).context();
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 0, &rtp(0xfffe)))
+            .unwrap();
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 1, &rtp(0x0001)))
+            .unwrap();
+        let mut other = rtp(0xc000);
+        other[8..12].copy_from_slice(&0xdead_beef_u32.to_be_bytes());
+        ctx.unprotect_rtp(&protect_rtp(&ctx, 0, &other)).unwrap();
+        assert_eq!(ctx.sources[&0xdead_beef].roc, 0);
+        assert_eq!(ctx.sources[&0xcafe_babe].roc, 1);
+    }
+
+    #[test]
+    fn rtcp() {
+        // An RTCP receiver report with no report blocks.
+        let plain = b"\x80\xc9\x00\x01\xca\xfe\xba\xbe";
+        let mut ctx = key(Suite::AesCm128HmacSha1_80).context();
+
+        // Authenticated but unencrypted (E=0), index 1.
+        let mut protected = plain.to_vec();
+        protected.extend_from_slice(b"\x00\x00\x00\x01");
+        let tag = match &ctx.rtcp.cipher {
+            Cipher::AesCm { hmac, .. } => {
+                let mut hmac = hmac.clone();
+                hmac.update(&protected);
+                hmac.finalize().into_bytes()
+            }
+            Cipher::Gcm(_) => unreachable!(),
+        };
+        protected.extend_from_slice(&tag[..SRTCP_HMAC_TAG_LEN]);
+        assert_eq!(&ctx.unprotect_rtcp(&protected).unwrap()[..], &plain[..]);
+        protected[1] ^= 1;
+        ctx.unprotect_rtcp(&protected).unwrap_err();
+
+        // GCM, encrypted (E=1), index 2.
+        let mut ctx = key(Suite::AeadAes128Gcm).context();
+        let e_index = *b"\x80\x00\x00\x02";
+        let mut protected = plain.to_vec();
+        let tag = match &ctx.rtcp.cipher {
+            Cipher::Gcm(gcm) => {
+                let mut fields = [0; 12];
+                fields[2..6].copy_from_slice(&plain[4..8]);
+                fields[11] = 2;
+                let nonce = ctx.rtcp.gcm_nonce(fields);
+                let mut aad = plain.to_vec();
+                aad.extend_from_slice(&e_index);
+                gcm.encrypt_in_place_detached(&nonce.into(), &aad, &mut protected[8..])
+                    .unwrap()
+            }
+            Cipher::AesCm { .. } => unreachable!(),
+        };
+        protected.extend_from_slice(&tag);
+        protected.extend_from_slice(&e_index);
+        assert_eq!(&ctx.unprotect_rtcp(&protected).unwrap()[..], &plain[..]);
+    }
+
+    #[test]
+    fn protect_rtcp() {
+        let plain = b"\x80\xc9\x00\x01\xca\xfe\xba\xbe";
+        for suite in [Suite::AesCm128HmacSha1_80, Suite::AeadAes128Gcm] {
+            let mut ctx = key(suite).context();
+            let first = ctx.protect_rtcp(plain);
+            let second = ctx.protect_rtcp(plain);
+            assert_ne!(first, second);
+            assert_eq!(&ctx.unprotect_rtcp(&first).unwrap()[..], &plain[..]);
+            assert_eq!(&ctx.unprotect_rtcp(&second).unwrap()[..], &plain[..]);
+        }
+    }
+
+    /// Tests that SRTCP rejects replayed and too-old packets, as described in
+    /// RFC 3711 section 3.3.2.
+    #[test]
+    fn rtcp_replay() {
+        let plain = b"\x80\xc9\x00\x01\xca\xfe\xba\xbe";
+        for suite in [Suite::AesCm128HmacSha1_80, Suite::AeadAes128Gcm] {
+            let mut sender = key(suite).context();
+            let packets: Vec<_> = (0..100).map(|_| sender.protect_rtcp(plain)).collect();
+            let mut ctx = key(suite).context();
+            ctx.unprotect_rtcp(&packets[50]).unwrap();
+            ctx.unprotect_rtcp(&packets[50]).unwrap_err();
+
+            // Out of order but within the window.
+            ctx.unprotect_rtcp(&packets[49]).unwrap();
+            ctx.unprotect_rtcp(&packets[49]).unwrap_err();
+            ctx.unprotect_rtcp(&packets[99]).unwrap();
+
+            // Behind the window.
+            ctx.unprotect_rtcp(&packets[20]).unwrap_err();
+
+            // A forged packet doesn't advance the window.
+            let mut forged = packets[98].to_vec();
+            forged[1] ^= 1;
+            ctx.unprotect_rtcp(&forged).unwrap_err();
+            ctx.unprotect_rtcp(&packets[98]).unwrap();
+        }
+    }
+
+    #[test]
+    fn parse_crypto() {
+        let k = MasterKey::parse_crypto(
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(k.suite, Suite::AesCm128HmacSha1_80);
+        assert_eq!(&k.key[..4], b"=-n@");
+
+        // Unknown suites are skipped; unsupported features are errors.
+        assert!(MasterKey::parse_crypto(
+            "1 F8_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR"
+        )
+        .unwrap()
+        .is_none());
+        MasterKey::parse_crypto(
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20|1:4",
+        )
+        .unwrap_err();
+        MasterKey::parse_crypto(
+            "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR UNENCRYPTED_SRTP",
+        )
+        .unwrap_err();
+        MasterKey::parse_crypto(
+            "1 AEAD_AES_128_GCM inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR",
+        )
+        .unwrap_err();
+    }
+}
@@ -5,7 +5,8 @@ use bytes::Bytes;
 use rtsp_types::{Method, Request};
 use url::Url;
 
-use super::{ResponseMode, RtspConnection, SessionOptions, Tool};
+use super::{Origin, ResponseMode, RtspConnection, SessionOptions, Tool};
+use crate::runtime::Timer;
 use crate::{error::ErrorInt, Error};
 
 const EXISTING_CONN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
@@ -18,35 +19,41 @@ const FRESH_CONN_MAX_TIMEOUT: std::time::Duration = std::time::Duration::from_se
 pub(super) async fn background_teardown(
     seqnum: Option<u64>,
     base_url: Url,
+    conn_url: Url,
+    version: rtsp_types::Version,
     tool: Option<Tool>,
     session_id: Box<str>,
     just_try_once: bool,
     options: SessionOptions,
     requested_auth: Option<http_auth::PasswordClient>,
     conn: Option<RtspConnection>,
+    creds_origin: Option<Origin>,
     mut tx: tokio::sync::watch::Sender<Option<Result<(), Error>>>,
-    expires: tokio::time::Instant,
+    expires: crate::runtime::Instant,
 ) {
     log::debug!(
         "TEARDOWN {} starting for URL {}",
         &*session_id,
         base_url.as_str(),
     );
-    if tokio::time::timeout_at(
+    if crate::runtime::timeout_at(
         expires,
         teardown_loop_forever(
             base_url,
+            &conn_url,
+            version,
             tool,
             &session_id,
             just_try_once,
             &options,
             requested_auth,
             conn,
+            creds_origin,
             &mut tx,
         ),
     )
     .await
-    .is_err()
+    .is_none()
     {
         log::debug!("TEARDOWN {} aborted on session expiration", &*session_id);
     }
@@ -75,20 +82,23 @@ pub(super) async fn background_teardown(
 #[allow(clippy::too_many_arguments)]
 pub(super) async fn teardown_loop_forever(
     url: Url,
+    conn_url: &Url,
+    version: rtsp_types::Version,
     tool: Option<Tool>,
     session_id: &str,
     just_try_once: bool,
     options: &SessionOptions,
     mut requested_auth: Option<http_auth::PasswordClient>,
     mut conn: Option<RtspConnection>,
+    creds_origin: Option<Origin>,
     tx: &mut tokio::sync::watch::Sender<Option<Result<(), Error>>>,
 ) {
-    let mut req = rtsp_types::Request::builder(Method::Teardown, rtsp_types::Version::V1_0)
+    let mut req = rtsp_types::Request::builder(Method::Teardown, version)
         .request_uri(url.clone())
         .header(rtsp_types::headers::SESSION, session_id.to_string())
         .build(Bytes::new());
 
-    let attempt_deadline = tokio::time::sleep(EXISTING_CONN_TIMEOUT);
+    let attempt_deadline = crate::runtime::sleep(EXISTING_CONN_TIMEOUT);
     tokio::pin!(attempt_deadline);
     if let Some(conn) = conn.take() {
         // Attempt first on existing connection. Besides being the most
@@ -146,9 +156,10 @@ pub(super) async fn teardown_loop_forever(
     for attempt_num in 1.. {
         attempt_deadline
             .as_mut()
-            .reset(tokio::time::Instant::now() + timeout);
+            .reset(crate::runtime::Instant::now() + timeout);
         let attempt = async {
-            let conn = RtspConnection::connect(&url).await?;
+            let mut conn = RtspConnection::connect(conn_url, options).await?;
+            conn.creds_origin = creds_origin.clone();
             attempt(&mut req, tool.as_ref(), options, &mut requested_auth, conn).await
         };
         tokio::select! {
@@ -23,6 +23,19 @@ pub struct Timeline {
 
     /// The same in seconds, for logging.
     max_forward_jump_secs: u32,
+
+    /// If true, the next [`Timeline::advance_to`] call follows a `PAUSE` and
+    /// `PLAY` for which the server didn't supply an `rtptime`. It may jump
+    /// forward by any amount.
+    resume_pending: bool,
+
+    /// Added to each supplied RTP timestamp, so that the timeline continues
+    /// across a change of RTP source. See [`Timeline::restart`].
+    rtp_offset: u32,
+
+    /// If true, the next [`Timeline::advance_to`] call follows a change of RTP
+    /// source and sets `rtp_offset`.
+    restart_pending: bool,
 }
 
 impl Timeline {
@@ -51,14 +64,76 @@ impl Timeline {
             max_forward_jump_secs: enforce_with_max_forward_jump_secs
                 .map(NonZeroU32::get)
                 .unwrap_or(0),
+            resume_pending: false,
+            rtp_offset: 0,
+            restart_pending: false,
         })
     }
 
+    #[cfg(any(feature = "tokio", feature = "async-std"))]
+    pub(crate) fn clock_rate(&self) -> NonZeroU32 {
+        self.clock_rate
+    }
+
+    /// Accounts for a `PAUSE` and subsequent `PLAY`.
+    ///
+    /// RTP timestamps keep advancing while a live stream is paused, so the
+    /// timeline may need to jump forward by more than the usual limit. If the
+    /// `PLAY` response's `RTP-Info` header supplied an `rtptime`, moves there
+    /// immediately. Otherwise, the next [`Timeline::advance_to`] call may
+    /// jump forward by any amount.
+    pub fn resume(&mut self, rtp_timestamp: Option<u32>) {
+        match rtp_timestamp {
+            Some(rtp_timestamp) => {
+                self.resume_pending = false;
+                self.jump_forward_to(rtp_timestamp.wrapping_add(self.rtp_offset));
+            }
+            None => self.resume_pending = true,
+        }
+    }
+
+    /// Accounts for a change of RTP source, such as a new SSRC after an RTCP `BYE`.
+    ///
+    /// The new source's RTP timestamps are unrelated to the old one's, so the
+    /// next [`Timeline::advance_to`] call continues one tick past the current
+    /// timestamp, and later ones are relative to it. Subsequent [Timestamp]s'
+    /// [`Timestamp::timestamp`] values are offset from the new source's RTP
+    /// timestamps accordingly.
+    pub fn restart(&mut self) {
+        self.resume_pending = false;
+        self.restart_pending = true;
+    }
+
+    /// Moves to `rtp_timestamp`, interpreting any difference as forward
+    /// motion (even beyond `i32::MAX`) without applying time jump policy.
+    fn jump_forward_to(&mut self, rtp_timestamp: u32) {
+        if self.start.is_none() {
+            // No packets yet; the NPT epoch will be set on the next one.
+            return;
+        }
+        let delta = rtp_timestamp.wrapping_sub(self.timestamp as u32);
+        self.timestamp += i64::from(delta);
+    }
+
     /// Advances to the given (wrapping) RTP timestamp.
     ///
     /// If enforcement was enabled, this produces a monotonically increasing
     /// [Timestamp], erroring on excessive or backward time jumps.
     pub fn advance_to(&mut self, rtp_timestamp: u32) -> Result<Timestamp, String> {
+        if self.restart_pending {
+            self.restart_pending = false;
+            if self.start.is_some() {
+                self.rtp_offset = (self.timestamp as u32)
+                    .wrapping_add(1)
+                    .wrapping_sub(rtp_timestamp);
+            }
+        }
+        let rtp_timestamp = rtp_timestamp.wrapping_add(self.rtp_offset);
+        if self.resume_pending {
+            self.resume_pending = false;
+            self.jump_forward_to(rtp_timestamp);
+            return Ok(self.ts_and_delta(rtp_timestamp)?.0);
+        }
         let (timestamp, delta) = self.ts_and_delta(rtp_timestamp)?;
         if matches!(self.max_forward_jump, Some(j) if !(0..j.get()).contains(&delta)) {
             return Err(format!(
@@ -81,7 +156,9 @@ impl Timeline {
     /// This is useful for RTP timestamps in RTCP packets. They commonly refer
     /// to time slightly before the most timestamp of the matching RTP stream.
     pub fn place(&mut self, rtp_timestamp: u32) -> Result<Timestamp, String> {
-        Ok(self.ts_and_delta(rtp_timestamp)?.0)
+        Ok(self
+            .ts_and_delta(rtp_timestamp.wrapping_add(self.rtp_offset))?
+            .0)
     }
 
     fn ts_and_delta(&mut self, rtp_timestamp: u32) -> Result<(Timestamp, i32), String> {
@@ -171,6 +248,52 @@ mod tests {
         assert_eq!(t.advance_to(218250000).unwrap().elapsed(), 0);
     }
 
+    #[test]
+    fn resume() {
+        // Resuming with a known rtptime allows a jump beyond the limit (even
+        // beyond i32::MAX), then enforces the limit from there.
+        let mut t = Timeline::new(Some(100), 90_000, NonZeroU32::new(10)).unwrap();
+        assert_eq!(t.advance_to(200).unwrap().elapsed(), 100);
+        t.resume(Some(200u32.wrapping_add(0x9000_0000)));
+        assert_eq!(
+            t.advance_to(201u32.wrapping_add(0x9000_0000))
+                .unwrap()
+                .elapsed(),
+            0x9000_0000 + 101
+        );
+        t.advance_to(201).unwrap_err();
+
+        // Resuming without a rtptime allows one arbitrary forward jump.
+        let mut t = Timeline::new(Some(100), 90_000, NonZeroU32::new(10)).unwrap();
+        t.resume(None);
+        assert_eq!(
+            t.advance_to(100 + (20 * 90_000)).unwrap().elapsed(),
+            20 * 90_000
+        );
+        t.advance_to(100 + (40 * 90_000)).unwrap_err();
+
+        // Resuming before any packets have been received just sets the epoch.
+        let mut t = Timeline::new(None, 90_000, NonZeroU32::new(10)).unwrap();
+        t.resume(Some(5000));
+        assert_eq!(t.advance_to(218250000).unwrap().elapsed(), 0);
+    }
+
+    #[test]
+    fn restart() {
+        // After a restart, the new source's first timestamp continues one tick
+        // past the old source's last, even if it's far behind, and enforcement
+        // applies relative to it.
+        let mut t = Timeline::new(Some(100), 90_000, NonZeroU32::new(10)).unwrap();
+        assert_eq!(t.advance_to(1000).unwrap().elapsed(), 900);
+        t.restart();
+        assert_eq!(t.advance_to(5).unwrap().elapsed(), 901);
+        assert_eq!(t.advance_to(3005).unwrap().elapsed(), 3901);
+        assert_eq!(t.place(4).unwrap().elapsed(), 900);
+        t.advance_to(4).unwrap_err();
+        t.resume(Some(9005));
+        assert_eq!(t.advance_to(9006).unwrap().elapsed(), 9902);
+    }
+
     #[test]
     fn cast() {
         let a = 0x1FFFF_FFFFi64;
@@ -0,0 +1,94 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! TLS configuration for `rtsps` URLs.
+
+use std::sync::Arc;
+
+/// Options for connecting to `rtsps` URLs.
+///
+/// Specify via [`super::SessionOptions::tls`]. By default, Retina trusts the
+/// Mozilla root certificates bundled with the `webpki-roots` crate and
+/// doesn't present a client certificate.
+#[derive(Clone, Default)]
+pub struct TlsOptions {
+    root_certificates: Option<Vec<Vec<u8>>>,
+    client_auth: Option<(Vec<Vec<u8>>, Vec<u8>)>,
+}
+
+impl TlsOptions {
+    /// Trusts only the given DER-encoded root certificates.
+    ///
+    /// This is useful for cameras with certificates signed by a private
+    /// certificate authority.
+    pub fn root_certificates(mut self, certs: Vec<Vec<u8>>) -> Self {
+        self.root_certificates = Some(certs);
+        self
+    }
+
+    /// Presents the given DER-encoded certificate chain and private key if the
+    /// server requests client authentication.
+    ///
+    /// The chain starts with the end-entity certificate. The key may be in
+    /// PKCS#8, PKCS#1, or SEC1 format.
+    pub fn client_auth(mut self, cert_chain: Vec<Vec<u8>>, key: Vec<u8>) -> Self {
+        self.client_auth = Some((cert_chain, key));
+        self
+    }
+
+    pub(crate) fn client_config(&self) -> Result<Arc<rustls::ClientConfig>, String> {
+        let mut roots = rustls::RootCertStore::empty();
+        match &self.root_certificates {
+            Some(certs) => {
+                for cert in certs {
+                    roots
+                        .add(&rustls::Certificate(cert.clone()))
+                        .map_err(|e| format!("bad root certificate: {e}"))?;
+                }
+            }
+            None => roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+                rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject,
+                    ta.spki,
+                    ta.name_constraints,
+                )
+            })),
+        }
+        let builder = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let config = match &self.client_auth {
+            Some((cert_chain, key)) => builder
+                .with_client_auth_cert(
+                    cert_chain
+                        .iter()
+                        .cloned()
+                        .map(rustls::Certificate)
+                        .collect(),
+                    rustls::PrivateKey(key.clone()),
+                )
+                .map_err(|e| format!("bad client certificate: {e}"))?,
+            None => builder.with_no_client_auth(),
+        };
+        Ok(Arc::new(config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_roots() {
+        TlsOptions::default().client_config().unwrap();
+    }
+
+    #[test]
+    fn bad_root() {
+        TlsOptions::default()
+            .root_certificates(vec![b"not a certificate".to_vec()])
+            .client_config()
+            .map(|_| ())
+            .unwrap_err();
+    }
+}
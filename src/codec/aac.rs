@@ -22,7 +22,11 @@ use std::{
     num::{NonZeroU16, NonZeroU32},
 };
 
-use crate::{error::ErrorInt, rtp::ReceivedPacket, ConnectionContext, Error, StreamContext};
+use crate::{
+    error::ErrorInt,
+    rtp::{ReceivedPacket, ReceivedPacketBuilder},
+    ConnectionContext, Error, StreamContext, Timestamp,
+};
 
 use super::{AudioParameters, CodecItem};
 
@@ -33,6 +37,9 @@ use super::{AudioParameters, CodecItem};
 struct AudioSpecificConfig {
     parameters: AudioParameters,
 
+    // Only needed by the packetizers, which require an async runtime.
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+    audio_object_type: u8,
     frame_length: NonZeroU16,
     channels: &'static ChannelConfig,
 }
@@ -168,10 +175,27 @@ impl AudioSpecificConfig {
                 extra_data: raw.to_owned(),
                 sample_entry: Some(make_sample_entry(channels, sampling_frequency, raw)?),
             },
+            audio_object_type,
             frame_length,
             channels,
         })
     }
+
+    /// Returns the `audioProfileLevelIndication` (ISO/IEC 14496-3 Table 1.14)
+    /// of the lowest AAC Profile level which can decode this stream, or `0xFE`
+    /// ("no audio profile specified") if it's outside the AAC Profile.
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+    fn profile_level_id(&self) -> u8 {
+        if self.audio_object_type != 2 {
+            return 0xFE;
+        }
+        match (self.channels.channels, self.parameters.clock_rate) {
+            (c, r) if c <= 2 && r <= 48_000 => 0x29, // L2
+            (c, r) if c <= 6 && r <= 48_000 => 0x2A, // L4
+            (c, r) if c <= 6 && r <= 96_000 => 0x2B, // L5
+            _ => 0xFE,
+        }
+    }
 }
 
 /// Overwrites a buffer with a varint length, returning the length of the length.
@@ -348,6 +372,25 @@ fn make_sample_entry(
     Ok(buf)
 }
 
+/// Splits the `format-specific-params` of a SDP `fmtp` media attribute into
+/// lowercased keys and their values.
+fn format_specific_param_pairs(
+    format_specific_params: &str,
+) -> impl Iterator<Item = Result<(String, &str), String>> {
+    format_specific_params.split(';').filter_map(|p| {
+        let p = p.trim();
+        if p.is_empty() {
+            // Reolink cameras leave a trailing ';'.
+            return None;
+        }
+        Some(
+            p.split_once('=')
+                .map(|(key, value)| (key.to_ascii_lowercase(), value))
+                .ok_or_else(|| format!("bad format-specific-param {p}")),
+        )
+    })
+}
+
 /// Parses metadata from the `format-specific-params` of a SDP `fmtp` media attribute.
 /// The metadata is defined in [RFC 3640 section
 /// 4.1](https://datatracker.ietf.org/doc/html/rfc3640#section-4.1).
@@ -360,16 +403,9 @@ fn parse_format_specific_params(
     let mut size_length = None;
     let mut index_length = None;
     let mut index_delta_length = None;
-    for p in format_specific_params.split(';') {
-        let p = p.trim();
-        if p.is_empty() {
-            // Reolink cameras leave a trailing ';'.
-            continue;
-        }
-        let (key, value) = p
-            .split_once('=')
-            .ok_or_else(|| format!("bad format-specific-param {p}"))?;
-        match &key.to_ascii_lowercase()[..] {
+    for p in format_specific_param_pairs(format_specific_params) {
+        let (key, value) = p?;
+        match &key[..] {
             "config" => {
                 config = Some(
                     hex::decode(value)
@@ -462,6 +498,9 @@ struct Aggregate {
 struct Fragment {
     rtp_timestamp: u16,
 
+    /// The absolute time from the packet which started this fragment.
+    ntp_timestamp: Option<crate::NtpTimestamp>,
+
     /// Number of RTP packets lost before between the previous output AudioFrame
     /// and now.
     loss: u16,
@@ -610,6 +649,7 @@ impl Depacketizer {
                             frame_length: NonZeroU32::from(self.config.frame_length),
                             stream_id: pkt.stream_id(),
                             timestamp: pkt.timestamp(),
+                            ntp_timestamp: frag.ntp_timestamp,
                             data: std::mem::take(&mut frag.buf).freeze(),
                         });
                     }
@@ -666,7 +706,7 @@ impl Depacketizer {
                     // TODO: https://datatracker.ietf.org/doc/html/rfc3640#section-3.3.6
                     // says "receivers MUST support de-interleaving".
                     return Err(error(
-                        *conn_ctx,
+                        conn_ctx.clone(),
                         stream_ctx,
                         agg,
                         "interleaving not yet supported".to_owned(),
@@ -676,7 +716,7 @@ impl Depacketizer {
                     // start of fragment
                     if agg.frame_count != 1 {
                         return Err(error(
-                            *conn_ctx,
+                            conn_ctx.clone(),
                             stream_ctx,
                             agg,
                             "fragmented AUs must not share packets".to_owned(),
@@ -695,7 +735,7 @@ impl Depacketizer {
                             return Ok(None);
                         }
                         return Err(error(
-                            *conn_ctx,
+                            conn_ctx.clone(),
                             stream_ctx,
                             agg,
                             "mark can't be set on beginning of fragment".to_owned(),
@@ -705,6 +745,7 @@ impl Depacketizer {
                     buf.extend_from_slice(&payload[agg.data_off..]);
                     self.state = DepacketizerState::Fragmented(Fragment {
                         rtp_timestamp: agg.pkt.timestamp().timestamp as u16,
+                        ntp_timestamp: agg.pkt.ntp_timestamp(),
                         loss: agg.loss,
                         loss_since_mark: agg.loss_since_mark,
                         size: size as u16,
@@ -714,7 +755,7 @@ impl Depacketizer {
                 }
                 if !mark {
                     return Err(error(
-                        *conn_ctx,
+                        conn_ctx.clone(),
                         stream_ctx,
                         agg,
                         "mark must be set on non-fragmented au".to_owned(),
@@ -734,13 +775,21 @@ impl Depacketizer {
                         Some(t) => t,
                         None => {
                             return Err(error(
-                                *conn_ctx,
+                                conn_ctx.clone(),
                                 stream_ctx,
                                 agg,
                                 format!("aggregate timestamp {agg_timestamp} + {delta} overflows"),
                             ))
                         }
                     },
+
+                    // The packet's absolute time applies to its first frame;
+                    // offset it for the others.
+                    ntp_timestamp: agg.pkt.ntp_timestamp().map(|t| {
+                        crate::NtpTimestamp(t.0.wrapping_add(
+                            (u64::from(delta) << 32) / u64::from(agg_timestamp.clock_rate().get()),
+                        ))
+                    }),
                     data: Bytes::copy_from_slice(&payload[agg.data_off..agg.data_off + size]),
                 };
                 agg.loss = 0;
@@ -755,6 +804,162 @@ impl Depacketizer {
     }
 }
 
+/// Returns the `format-specific-params` of a SDP `fmtp` attribute which
+/// describes the given `AudioSpecificConfig`, as found in
+/// [`super::AudioParameters::extra_data`]. The stream is assumed to be sent by
+/// [`Packetizer`], so `mode=AAC-hbr`.
+#[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+pub(crate) fn format_specific_params(audio_specific_config: &[u8]) -> Result<String, String> {
+    let config = AudioSpecificConfig::parse(audio_specific_config)?;
+    Ok(format!(
+        "streamtype=5;profile-level-id={};mode=AAC-hbr;sizelength=13;indexlength=3;\
+         indexdeltalength=3;config={}",
+        config.profile_level_id(),
+        hex::encode(audio_specific_config)
+    ))
+}
+
+/// Returns the number of channels described by the given `AudioSpecificConfig`.
+#[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+pub(crate) fn channels(audio_specific_config: &[u8]) -> Result<NonZeroU16, String> {
+    let config = AudioSpecificConfig::parse(audio_specific_config)?;
+    NonZeroU16::new(config.channels.channels).ok_or_else(|| "zero channels".to_owned())
+}
+
+/// A simple packetizer, used by [`crate::client::Publisher`] and
+/// [`crate::client::BackchannelSink`].
+///
+/// Produces `mode=AAC-hbr` packets with a single access unit each, fragmenting
+/// access units which don't fit within `max_payload_size`. Expects raw access
+/// units, without ADTS headers.
+#[derive(Debug)]
+#[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+pub(crate) struct Packetizer {
+    max_payload_size: u16,
+    next_sequence_number: u16,
+    stream_id: usize,
+    ssrc: u32,
+    payload_type: u8,
+
+    /// The access unit being sent, its size, and the part not yet sent.
+    state: Option<(Timestamp, u16, Bytes)>,
+}
+
+#[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+impl Packetizer {
+    pub(crate) fn new(
+        max_payload_size: u16,
+        stream_id: usize,
+        initial_sequence_number: u16,
+        payload_type: u8,
+        ssrc: u32,
+    ) -> Result<Self, String> {
+        if max_payload_size < 5 {
+            // minimum size to make progress: 4 bytes of AU headers plus data.
+            return Err("max_payload_size must be > 4".into());
+        }
+        Ok(Self {
+            max_payload_size,
+            next_sequence_number: initial_sequence_number,
+            stream_id,
+            ssrc,
+            payload_type,
+            state: None,
+        })
+    }
+
+    /// Checks that a stream described by the given `format-specific-params`
+    /// expects the packets this produces: `mode=AAC-hbr` with AU headers of a
+    /// 13-bit size and 3-bit index, and no other AU header fields.
+    pub(crate) fn check_format_specific_params(format_specific_params: &str) -> Result<(), String> {
+        let mut mode = None;
+        let mut size_length = None;
+        let mut index_length = None;
+        for p in format_specific_param_pairs(format_specific_params) {
+            let (key, value) = p?;
+            match &key[..] {
+                "mode" => mode = Some(value),
+                "sizelength" => size_length = Some(value),
+                "indexlength" => index_length = Some(value),
+                "constantsize"
+                | "ctsdeltalength"
+                | "dtsdeltalength"
+                | "randomaccessindication"
+                | "streamstateindication"
+                | "auxiliarydatasizelength"
+                    if value != "0" =>
+                {
+                    return Err(format!(
+                        "unsupported AAC format-specific-param {key}={value}"
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if mode != Some("AAC-hbr") {
+            return Err(format!("Expected mode AAC-hbr, got {mode:#?}"));
+        }
+        if size_length != Some("13") || index_length != Some("3") {
+            return Err(format!(
+                "Unexpected sizeLength={size_length:?} indexLength={index_length:?}"
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn push(&mut self, timestamp: Timestamp, data: Bytes) -> Result<(), Error> {
+        assert!(self.state.is_none());
+        let size = u16::try_from(data.len())
+            .ok()
+            .filter(|&s| s > 0 && s < 1 << 13)
+            .ok_or_else(|| {
+                wrap!(ErrorInt::InvalidArgument(format!(
+                    "AAC frame of {} bytes; expected [1, 8191]",
+                    data.len()
+                )))
+            })?;
+        self.state = Some((timestamp, size, data));
+        Ok(())
+    }
+
+    pub(crate) fn pull(&mut self) -> Result<Option<ReceivedPacket>, String> {
+        let (timestamp, size, mut data) = match self.state.take() {
+            None => return Ok(None),
+            Some(s) => s,
+        };
+        let chunk = data.split_to(std::cmp::min(
+            data.len(),
+            usize::from(self.max_payload_size) - 4,
+        ));
+        let mark = data.is_empty();
+        if !mark {
+            self.state = Some((timestamp, size, data));
+        }
+        let sequence_number = self.next_sequence_number;
+        self.next_sequence_number = self.next_sequence_number.wrapping_add(1);
+
+        // A single AU header: 13-bit size, 3-bit index. Each fragment repeats
+        // the header with the full size.
+        let [size_hi, size_lo] = (size << 3).to_be_bytes();
+        let payload = [0x00, 0x10, size_hi, size_lo]
+            .into_iter()
+            .chain(chunk.iter().copied());
+        Ok(Some(
+            ReceivedPacketBuilder {
+                ctx: crate::PacketContext::dummy(),
+                stream_id: self.stream_id,
+                timestamp,
+                ssrc: self.ssrc,
+                sequence_number,
+                loss: 0,
+                mark,
+                payload_type: self.payload_type,
+            }
+            .build(payload)?,
+        ))
+    }
+}
+
 fn error(
     conn_ctx: ConnectionContext,
     stream_ctx: &StreamContext,
@@ -1384,4 +1589,47 @@ mod tests {
             e
         );
     }
+
+    #[test]
+    fn packetize_roundtrip() {
+        let params = "streamtype=5;profile-level-id=41;mode=AAC-hbr;sizelength=13;indexlength=3;indexdeltalength=3;config=1188";
+        assert_eq!(format_specific_params(&[0x11, 0x88]).unwrap(), params);
+        Packetizer::check_format_specific_params(params).unwrap();
+        Packetizer::check_format_specific_params(
+            "streamtype=5;profile-level-id=1;mode=AAC-lbr;sizelength=6;indexlength=2;config=1188",
+        )
+        .unwrap_err();
+        assert_eq!(channels(&[0x11, 0x88]).unwrap().get(), 1);
+        let mut d = Depacketizer::new(48_000, None, Some(params)).unwrap();
+        let mut p = Packetizer::new(1000, 0, 0xffff, 96, 0x1234_5678).unwrap();
+        let timestamp = crate::Timestamp {
+            timestamp: 42,
+            clock_rate: NonZeroU32::new(48_000).unwrap(),
+            start: 0,
+        };
+
+        // The second frame is fragmented into three packets.
+        for (len, packets) in [(4, 1), (2500, 3)] {
+            let frame: Bytes = (0..len).map(|i| i as u8).collect();
+            p.push(timestamp, frame.clone()).unwrap();
+            let mut frames = Vec::new();
+            let mut n = 0;
+            while let Some(pkt) = p.pull().unwrap() {
+                n += 1;
+                d.push(pkt).unwrap();
+                frames.extend(
+                    d.pull(&ConnectionContext::dummy(), &StreamContext::dummy())
+                        .unwrap(),
+                );
+            }
+            assert_eq!(n, packets);
+            let a = match frames.pop() {
+                Some(CodecItem::AudioFrame(a)) if frames.is_empty() => a,
+                o => panic!("unexpected item {o:?}"),
+            };
+            assert_eq!(a.timestamp, timestamp);
+            assert_eq!(a.data, frame);
+        }
+        assert!(p.push(timestamp, Bytes::new()).is_err());
+    }
 }
@@ -64,6 +64,7 @@ impl Depacketizer {
             loss: pkt.loss(),
             stream_id: pkt.stream_id(),
             timestamp: pkt.timestamp(),
+            ntp_timestamp: pkt.ntp_timestamp(),
             frame_length: NonZeroU32::new(240).unwrap(),
             data: pkt.into_payload_bytes(),
         });
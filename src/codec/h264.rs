@@ -62,6 +62,7 @@ struct AccessUnit {
     start_ctx: crate::PacketContext,
     end_ctx: crate::PacketContext,
     timestamp: crate::Timestamp,
+    ntp_timestamp: Option<crate::NtpTimestamp>,
     stream_id: usize,
 
     /// True iff currently processing a FU-A.
@@ -517,6 +518,7 @@ impl Depacketizer {
             start_ctx: au.start_ctx,
             end_ctx: au.end_ctx,
             timestamp: au.timestamp,
+            ntp_timestamp: au.ntp_timestamp,
             stream_id: au.stream_id,
             is_random_access_point,
             is_disposable,
@@ -549,6 +551,7 @@ impl AccessUnit {
             start_ctx: *pkt.ctx(),
             end_ctx: *pkt.ctx(),
             timestamp: pkt.timestamp(),
+            ntp_timestamp: pkt.ntp_timestamp(),
             stream_id: pkt.stream_id(),
             in_fu_a: false,
 
@@ -755,6 +758,48 @@ impl InternalParameters {
     }
 }
 
+/// Returns the `format-specific-params` of a SDP `fmtp` attribute which
+/// describes the given AVCDecoderConfiguration, as found in
+/// [`super::VideoParameters::extra_data`]. The stream is assumed to be sent by
+/// [`Packetizer`], so `packetization-mode=1`.
+#[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+pub(crate) fn format_specific_params(avc_decoder_config: &[u8]) -> Result<String, String> {
+    // ISO/IEC 14496-15 section 5.2.4.1.
+    if avc_decoder_config.len() < 7 || avc_decoder_config[0] != 1 {
+        return Err("bad AVCDecoderConfiguration".into());
+    }
+    let mut params = format!(
+        "packetization-mode=1;profile-level-id={:02X}{:02X}{:02X};sprop-parameter-sets=",
+        avc_decoder_config[1], avc_decoder_config[2], avc_decoder_config[3]
+    );
+    let mut data = &avc_decoder_config[5..];
+    let mut first = true;
+
+    // The SPSs (with a 5-bit count) then the PPSs (with an 8-bit count).
+    for count_mask in [0x1f, 0xff] {
+        let (&count, rest) = data
+            .split_first()
+            .ok_or_else(|| "truncated AVCDecoderConfiguration".to_string())?;
+        data = rest;
+        for _ in 0..(count & count_mask) {
+            if data.len() < 2 {
+                return Err("truncated AVCDecoderConfiguration".into());
+            }
+            let len = usize::from(u16::from_be_bytes([data[0], data[1]]));
+            let nal = data
+                .get(2..2 + len)
+                .ok_or_else(|| "truncated AVCDecoderConfiguration".to_string())?;
+            if !first {
+                params.push(',');
+            }
+            first = false;
+            params.push_str(&base64::encode(nal));
+            data = &data[2 + len..];
+        }
+    }
+    Ok(params)
+}
+
 /// Returns true iff the bytes of `nal` equal the bytes of `[hdr, ..data]`.
 fn nal_matches(nal: &[u8], hdr: NalHeader, pieces: &[Bytes]) -> bool {
     if nal.is_empty() || nal[0] != u8::from(hdr) {
@@ -786,7 +831,7 @@ fn to_bytes(hdr: NalHeader, len: u32, pieces: &[Bytes]) -> Bytes {
     out.into()
 }
 
-/// A simple packetizer, currently only for testing/benchmarking. Unstable.
+/// A simple packetizer, used by [`crate::server`] and for testing/benchmarking. Unstable.
 ///
 /// Only uses plain NALs and FU-As, never STAP-A.
 /// Expects data to be NALs separated by 4-byte prefixes.
@@ -1056,6 +1101,20 @@ mod tests {
     }
      */
 
+    #[test]
+    fn format_specific_params_roundtrip() {
+        let params = "packetization-mode=1;profile-level-id=640033;sprop-parameter-sets=Z2QAM6wVFKCgL/lQ,aO48sA==";
+        let d = super::Depacketizer::new(90_000, Some(params)).unwrap();
+        let video = match d.parameters() {
+            Some(crate::codec::ParametersRef::Video(v)) => v.clone(),
+            o => panic!("unexpected parameters {o:?}"),
+        };
+        assert_eq!(
+            super::format_specific_params(video.extra_data()).unwrap(),
+            params
+        );
+    }
+
     #[test]
     fn depacketize() {
         init_logging();
@@ -25,6 +25,11 @@ pub mod h264;
 pub(crate) mod onvif;
 pub(crate) mod simple_audio;
 
+/// The maximum RTP payload size for packetizers, chosen to fit within a
+/// typical Ethernet MTU.
+#[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+pub(crate) const MAX_PAYLOAD_SIZE: u16 = 1400;
+
 /// An item yielded from [`crate::client::Demuxed`]'s [`futures::stream::Stream`] impl.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -33,6 +38,13 @@ pub enum CodecItem {
     AudioFrame(AudioFrame),
     MessageFrame(MessageFrame),
     Rtcp(crate::rtcp::ReceivedCompoundPacket),
+
+    /// An RTCP compound packet which contains a `BYE`; see
+    /// [`crate::client::PacketItem::Goodbye`].
+    Goodbye(crate::rtcp::ReceivedCompoundPacket),
+
+    /// A `REDIRECT` request from the server; see [`crate::client::PacketItem::Redirect`].
+    Redirect(crate::client::Redirect),
 }
 
 /// Reference to parameters which describe a stream.
@@ -190,6 +202,7 @@ pub struct AudioFrame {
     ctx: crate::PacketContext,
     stream_id: usize,
     timestamp: crate::Timestamp,
+    ntp_timestamp: Option<crate::NtpTimestamp>,
     frame_length: NonZeroU32,
     loss: u16,
     data: Bytes,
@@ -216,6 +229,15 @@ impl AudioFrame {
         self.frame_length
     }
 
+    /// Returns the absolute time of this frame, if known.
+    ///
+    /// Currently this is taken from the ONVIF replay RTP header extension; see
+    /// [`crate::rtp::ReceivedPacket::ntp_timestamp`].
+    #[inline]
+    pub fn ntp_timestamp(&self) -> Option<crate::NtpTimestamp> {
+        self.ntp_timestamp
+    }
+
     /// Returns the number of lost RTP packets before this audio frame. See
     /// [crate::rtp::ReceivedPacket::loss].
     ///
@@ -239,6 +261,7 @@ impl std::fmt::Debug for AudioFrame {
             .field("ctx", &self.ctx)
             .field("loss", &self.loss)
             .field("timestamp", &self.timestamp)
+            .field("ntp_timestamp", &self.ntp_timestamp)
             .field("frame_length", &self.frame_length)
             .field("data", &crate::hex::LimitedHex::new(&self.data, 64))
             .finish()
@@ -253,6 +276,7 @@ pub struct MessageParameters(onvif::CompressionType);
 pub struct MessageFrame {
     ctx: crate::PacketContext,
     timestamp: crate::Timestamp,
+    ntp_timestamp: Option<crate::NtpTimestamp>,
     stream_id: usize,
     loss: u16,
     data: Bytes,
@@ -265,6 +289,7 @@ impl std::fmt::Debug for MessageFrame {
             .field("stream_id", &self.stream_id)
             .field("loss", &self.loss)
             .field("timestamp", &self.timestamp)
+            .field("ntp_timestamp", &self.ntp_timestamp)
             .field("data", &crate::hex::LimitedHex::new(&self.data, 64))
             .finish()
     }
@@ -286,6 +311,15 @@ impl MessageFrame {
         self.timestamp
     }
 
+    /// Returns the absolute time of this frame, if known.
+    ///
+    /// Currently this is taken from the ONVIF replay RTP header extension; see
+    /// [`crate::rtp::ReceivedPacket::ntp_timestamp`].
+    #[inline]
+    pub fn ntp_timestamp(&self) -> Option<crate::NtpTimestamp> {
+        self.ntp_timestamp
+    }
+
     /// Returns the number of lost RTP packets before this frame. See
     /// [crate::rtp::ReceivedPacket::loss].
     ///
@@ -318,6 +352,7 @@ pub struct VideoFrame {
     has_new_parameters: bool,
     loss: u16,
     timestamp: crate::Timestamp,
+    ntp_timestamp: Option<crate::NtpTimestamp>,
     stream_id: usize,
     is_random_access_point: bool,
     is_disposable: bool,
@@ -354,6 +389,15 @@ impl VideoFrame {
         self.timestamp
     }
 
+    /// Returns the absolute time of this frame, if known.
+    ///
+    /// Currently this is taken from the ONVIF replay RTP header extension; see
+    /// [`crate::rtp::ReceivedPacket::ntp_timestamp`].
+    #[inline]
+    pub fn ntp_timestamp(&self) -> Option<crate::NtpTimestamp> {
+        self.ntp_timestamp
+    }
+
     #[inline]
     pub fn start_ctx(&self) -> &crate::PacketContext {
         &self.start_ctx
@@ -406,6 +450,7 @@ impl std::fmt::Debug for VideoFrame {
         //use pretty_hex::PrettyHex;
         f.debug_struct("VideoFrame")
             .field("timestamp", &self.timestamp)
+            .field("ntp_timestamp", &self.ntp_timestamp)
             .field("start_ctx", &self.start_ctx)
             .field("end_ctx", &self.end_ctx)
             .field("loss", &self.loss)
@@ -38,6 +38,7 @@ enum State {
 struct InProgress {
     ctx: crate::PacketContext,
     timestamp: crate::Timestamp,
+    ntp_timestamp: Option<crate::NtpTimestamp>,
     data: BytesMut,
     loss: u16,
 }
@@ -86,6 +87,7 @@ impl Depacketizer {
                         loss: pkt.loss(),
                         ctx: *pkt.ctx(),
                         timestamp: pkt.timestamp(),
+                        ntp_timestamp: pkt.ntp_timestamp(),
                         data: pkt.into_payload_bytes(),
                     });
                     return Ok(());
@@ -94,6 +96,7 @@ impl Depacketizer {
                     loss: pkt.loss(),
                     ctx: *pkt.ctx(),
                     timestamp: pkt.timestamp(),
+                    ntp_timestamp: pkt.ntp_timestamp(),
                     data: BytesMut::with_capacity(self.high_water_size),
                 }
             }
@@ -106,6 +109,7 @@ impl Depacketizer {
                 stream_id: pkt.stream_id(),
                 ctx: in_progress.ctx,
                 timestamp: in_progress.timestamp,
+                ntp_timestamp: in_progress.ntp_timestamp,
                 data: in_progress.data.freeze(),
                 loss: in_progress.loss,
             });
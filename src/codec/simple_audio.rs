@@ -61,6 +61,7 @@ impl Depacketizer {
             ctx: *pkt.ctx(),
             stream_id: pkt.stream_id(),
             timestamp: pkt.timestamp(),
+            ntp_timestamp: pkt.ntp_timestamp(),
             frame_length,
             data: pkt.into_payload_bytes(),
         });
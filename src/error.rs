@@ -94,9 +94,26 @@ pub(crate) enum ErrorInt {
         description: String,
     },
 
+    /// An SRTP or SRTCP packet failed authentication or couldn't be decrypted.
+    #[error(
+        "SRTP authentication failed: {description}\n\n\
+             conn: {conn_ctx}\nstream: {stream_ctx}\npkt: {pkt_ctx}"
+    )]
+    SrtpAuthenticationError {
+        conn_ctx: ConnectionContext,
+        stream_ctx: StreamContext,
+        pkt_ctx: PacketContext,
+        stream_id: usize,
+        description: String,
+    },
+
     #[error("Unable to connect to RTSP server: {0}")]
     ConnectError(#[source] std::io::Error),
 
+    #[error("Unable to listen for RTSP connections: {0}")]
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+    ListenError(#[source] std::io::Error),
+
     #[error("Error reading from RTSP peer: {source}\n\nconn: {conn_ctx}\nmsg: {msg_ctx}")]
     RtspReadError {
         conn_ctx: ConnectionContext,
@@ -108,6 +125,7 @@ pub(crate) enum ErrorInt {
         "Error receiving UDP packet: {source}\n\n\
              conn: {conn_ctx}\nstream: {stream_ctx}\nat: {when}"
     )]
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
     UdpRecvError {
         conn_ctx: ConnectionContext,
         stream_ctx: StreamContext,
@@ -128,5 +146,6 @@ pub(crate) enum ErrorInt {
     Internal(#[source] Box<dyn std::error::Error + Send + Sync>),
 
     #[error("Timeout")]
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
     Timeout,
 }
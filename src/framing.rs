@@ -0,0 +1,158 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! RTSP message framing, shared by the async and blocking connections.
+
+use bytes::{Buf, Bytes, BytesMut};
+use rtsp_types::{Data, Message};
+use std::convert::TryFrom;
+use std::time::Instant;
+
+use crate::{ConnectionContext, ReceivedMessage, RtspMessageContext, WallTime};
+
+/// Encodes and decodes RTSP messages.
+pub(crate) struct Codec {
+    pub(crate) ctx: ConnectionContext,
+
+    /// Number of bytes read and processed (drained from the input buffer).
+    pub(crate) read_pos: u64,
+}
+
+/// An intermediate error type that exists because `tokio_util`'s `Framed` expects the
+/// codec's error type to implement `From<std::io::Error>`, and [`crate::Error`]
+/// takes additional context.
+#[derive(Debug)]
+pub(crate) enum CodecError {
+    IoError(std::io::Error),
+    ParseError { description: String, pos: u64 },
+}
+
+impl std::convert::From<std::io::Error> for CodecError {
+    fn from(e: std::io::Error) -> Self {
+        CodecError::IoError(e)
+    }
+}
+
+impl Codec {
+    pub(crate) fn new(ctx: ConnectionContext) -> Self {
+        Self { ctx, read_pos: 0 }
+    }
+
+    fn parse_msg(&self, src: &mut BytesMut) -> Result<Option<(usize, Message<Bytes>)>, CodecError> {
+        // Skip whitespace as `rtsp-types` does. It's important to also do it here, or we might
+        // skip the our own data message encoding (next if) then hit
+        // unreachable! after rtsp-types returns Message::Data.
+        while src.starts_with(b"\r\n") {
+            src.advance(2);
+        }
+
+        if !src.is_empty() && src[0] == b'$' {
+            // Fast path for interleaved data, avoiding MessageRef -> Message<&[u8]> ->
+            // Message<Bytes> conversion. This speeds things up quite a bit in practice,
+            // avoiding a bunch of memmove calls.
+            if src.len() < 4 {
+                return Ok(None);
+            }
+            let channel_id = src[1];
+            let len = 4 + usize::from(u16::from_be_bytes([src[2], src[3]]));
+            if src.len() < len {
+                src.reserve(len - src.len());
+                return Ok(None);
+            }
+            let mut msg = src.split_to(len);
+            msg.advance(4);
+            return Ok(Some((
+                len,
+                Message::Data(Data::new(channel_id, msg.freeze())),
+            )));
+        }
+
+        let (msg, len): (Message<&[u8]>, _) = match Message::parse(src) {
+            Ok((m, l)) => (m, l),
+            Err(rtsp_types::ParseError::Error) => {
+                return Err(CodecError::ParseError {
+                    description: format!(
+                        "Invalid RTSP message; buffered:\n{:#?}",
+                        crate::hex::LimitedHex::new(&src[..], 128),
+                    ),
+                    pos: self.read_pos,
+                });
+            }
+            Err(rtsp_types::ParseError::Incomplete) => return Ok(None),
+        };
+
+        // Map msg's body to a Bytes representation and advance `src`. Awkward:
+        // 1.  lifetime concerns require mapping twice: first so the message
+        //     doesn't depend on the BytesMut, which needs to be split/advanced;
+        //     then to get the proper Bytes body in place post-split.
+        // 2.  rtsp_types messages must be AsRef<[u8]>, so we can't use the
+        //     range as an intermediate body.
+        // 3.  within a match because the rtsp_types::Message enum itself
+        //     doesn't have body/replace_body/map_body methods.
+        let msg = match msg {
+            Message::Request(msg) => {
+                let body_range = crate::as_range(src, msg.body());
+                let msg = msg.replace_body(rtsp_types::Empty);
+                if let Some(r) = body_range {
+                    let mut raw_msg = src.split_to(len);
+                    raw_msg.advance(r.start);
+                    raw_msg.truncate(r.len());
+                    Message::Request(msg.replace_body(raw_msg.freeze()))
+                } else {
+                    src.advance(len);
+                    Message::Request(msg.replace_body(Bytes::new()))
+                }
+            }
+            Message::Response(msg) => {
+                let body_range = crate::as_range(src, msg.body());
+                let msg = msg.replace_body(rtsp_types::Empty);
+                if let Some(r) = body_range {
+                    let mut raw_msg = src.split_to(len);
+                    raw_msg.advance(r.start);
+                    raw_msg.truncate(r.len());
+                    Message::Response(msg.replace_body(raw_msg.freeze()))
+                } else {
+                    src.advance(len);
+                    Message::Response(msg.replace_body(Bytes::new()))
+                }
+            }
+            Message::Data(_) => unreachable!(),
+        };
+        Ok(Some((len, msg)))
+    }
+
+    /// Decodes a message from the start of `src`, if one is complete.
+    pub(crate) fn decode(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> Result<Option<ReceivedMessage>, CodecError> {
+        let (len, msg) = match self.parse_msg(src) {
+            Err(e) => return Err(e),
+            Ok(None) => return Ok(None),
+            Ok(Some((len, msg))) => (len, msg),
+        };
+        let msg = ReceivedMessage {
+            msg,
+            ctx: RtspMessageContext {
+                pos: self.read_pos,
+                received_wall: WallTime::now(),
+                received: Instant::now(),
+            },
+        };
+        self.read_pos += u64::try_from(len).expect("usize fits in u64");
+        Ok(Some(msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crlf_data() {
+        let mut codec = Codec::new(ConnectionContext::dummy());
+        let mut buf = BytesMut::from(&b"\r\n$\x00\x00\x04asdfrest"[..]);
+        codec.decode(&mut buf).unwrap();
+        assert_eq!(&buf[..], b"rest");
+    }
+}
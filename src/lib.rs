@@ -3,23 +3,30 @@
 
 //! High-level RTSP library.
 //!
-//! Currently this is useful for clients; it will be extended to support
-//! servers and proxies.
+//! Currently this is mostly useful for clients. [`server`] has basic support
+//! for serving H.264 video, including restreaming a camera via
+//! [`server::Proxy`].
 
 #![forbid(clippy::print_stderr, clippy::print_stdout)]
 // I prefer to use from_str_radix(..., 10) to explicitly note the base.
 #![allow(clippy::from_str_radix_10)]
 
 use bytes::Bytes;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
 use log::trace;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
 use rand::Rng;
 use rtsp_types::Message;
 use std::fmt::{Debug, Display};
-use std::net::{IpAddr, SocketAddr, UdpSocket};
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use std::net::UdpSocket;
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroU32;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
 use std::ops::Range;
 
 mod error;
+mod framing;
 
 mod hex;
 pub mod rtcp;
@@ -45,8 +52,29 @@ macro_rules! wrap {
 
 pub mod client;
 pub mod codec;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub mod server;
+
+#[cfg(all(
+    any(test, feature = "testing"),
+    any(feature = "tokio", feature = "async-std")
+))]
+pub mod testing;
 //mod error;
-mod tokio;
+mod blocking;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+mod net;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+mod runtime;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+mod sdp;
+
+/// Without an async runtime, only the clock is needed, by the RTP parsing
+/// shared with the blocking client.
+#[cfg(not(any(feature = "tokio", feature = "async-std")))]
+mod runtime {
+    pub(crate) use std::time::Instant;
+}
 
 use error::ErrorInt;
 
@@ -255,11 +283,14 @@ impl Display for WallTime {
 /// RTSP connection context.
 ///
 /// This gives enough information to pick out the flow in a packet capture.
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone)]
 pub struct ConnectionContext {
     local_addr: std::net::SocketAddr,
     peer_addr: std::net::SocketAddr,
     established_wall: WallTime,
+
+    /// The peer's DER-encoded end-entity certificate, for `rtsps` connections.
+    peer_certificate: Option<std::sync::Arc<[u8]>>,
 }
 
 impl ConnectionContext {
@@ -270,8 +301,31 @@ impl ConnectionContext {
             local_addr: addr,
             peer_addr: addr,
             established_wall: WallTime::now(),
+            peer_certificate: None,
         }
     }
+
+    /// Returns the peer's DER-encoded end-entity certificate, if connected via TLS.
+    pub fn peer_certificate(&self) -> Option<&[u8]> {
+        self.peer_certificate.as_deref()
+    }
+}
+
+impl Debug for ConnectionContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionContext")
+            .field("local_addr", &self.local_addr)
+            .field("peer_addr", &self.peer_addr)
+            .field("established_wall", &self.established_wall)
+            .field(
+                "peer_certificate",
+                &self
+                    .peer_certificate
+                    .as_ref()
+                    .map(|c| crate::hex::LimitedHex::new(c, 16)),
+            )
+            .finish()
+    }
 }
 
 impl Display for ConnectionContext {
@@ -359,6 +413,8 @@ impl Display for StreamContext {
 #[derive(Copy, Clone, Debug)]
 enum StreamContextInner {
     Tcp(TcpStreamContext),
+    // The blocking client only uses interleaved TCP.
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
     Udp(UdpStreamContext),
     Dummy,
 }
@@ -418,8 +474,13 @@ impl PacketContext {
 
 #[derive(Copy, Clone, Debug)]
 enum PacketContextInner {
-    Tcp { msg_ctx: RtspMessageContext },
-    Udp { received_wall: WallTime },
+    Tcp {
+        msg_ctx: RtspMessageContext,
+    },
+    #[cfg_attr(not(any(feature = "tokio", feature = "async-std")), allow(dead_code))]
+    Udp {
+        received_wall: WallTime,
+    },
     Dummy,
 }
 
@@ -457,12 +518,14 @@ pub(crate) fn as_range(buf: &[u8], subset: &[u8]) -> Option<std::ops::Range<usiz
 /// A pair of local UDP sockets used for RTP and RTCP transmission.
 ///
 /// The RTP port is always even, and the RTCP port is always the following (odd) integer.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
 struct UdpPair {
     rtp_port: u16,
     rtp_socket: UdpSocket,
     rtcp_socket: UdpSocket,
 }
 
+#[cfg(any(feature = "tokio", feature = "async-std"))]
 impl UdpPair {
     fn for_ip(ip_addr: IpAddr) -> Result<Self, std::io::Error> {
         const MAX_TRIES: usize = 10;
@@ -513,9 +576,78 @@ impl UdpPair {
             ),
         ))
     }
+
+    /// Binds a pair of sockets to receive from the given multicast group.
+    ///
+    /// The sockets allow address reuse, so that several receivers on the same
+    /// host can join the same group. `interface_v4` and `interface_v6` choose
+    /// the interface on which to join for IPv4 and IPv6 groups, respectively;
+    /// the unspecified address and index 0 let the system decide. `ttl`, if
+    /// specified, applies to packets sent on these sockets.
+    fn for_multicast(
+        group: IpAddr,
+        rtp_port: u16,
+        ttl: Option<u32>,
+        interface_v4: std::net::Ipv4Addr,
+        interface_v6: u32,
+    ) -> Result<Self, std::io::Error> {
+        let rtcp_port = rtp_port.checked_add(1).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("multicast RTP port {rtp_port} has no following RTCP port"),
+            )
+        })?;
+        let bind = |port| -> Result<UdpSocket, std::io::Error> {
+            // A socket bound to the unspecified address would also receive
+            // packets sent to other groups joined on this port, by this or
+            // any other socket on the host. Unix systems allow binding to the
+            // group address itself, so that only its packets are delivered.
+            // Windows doesn't.
+            #[cfg(unix)]
+            let ip = group;
+            #[cfg(not(unix))]
+            let ip = match group {
+                IpAddr::V4(_) => IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+                IpAddr::V6(_) => IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+            };
+            let addr = match ip {
+                IpAddr::V4(ip) => SocketAddr::V4(std::net::SocketAddrV4::new(ip, port)),
+                IpAddr::V6(ip) => {
+                    SocketAddr::V6(std::net::SocketAddrV6::new(ip, port, 0, interface_v6))
+                }
+            };
+            let socket = socket2::Socket::new(
+                socket2::Domain::for_address(addr),
+                socket2::Type::DGRAM,
+                Some(socket2::Protocol::UDP),
+            )?;
+            socket.set_reuse_address(true)?;
+            socket.bind(&addr.into())?;
+            match group {
+                IpAddr::V4(g) => {
+                    socket.join_multicast_v4(&g, &interface_v4)?;
+                    if let Some(ttl) = ttl {
+                        socket.set_multicast_ttl_v4(ttl)?;
+                    }
+                }
+                IpAddr::V6(g) => {
+                    socket.join_multicast_v6(&g, interface_v6)?;
+                    if let Some(ttl) = ttl {
+                        socket.set_multicast_hops_v6(ttl)?;
+                    }
+                }
+            }
+            Ok(socket.into())
+        };
+        Ok(Self {
+            rtp_port,
+            rtp_socket: bind(rtp_port)?,
+            rtcp_socket: bind(rtcp_port)?,
+        })
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, any(feature = "tokio", feature = "async-std")))]
 mod test {
     use std::net::Ipv4Addr;
 
@@ -526,4 +658,40 @@ mod test {
         // Just test that it succeeds.
         UdpPair::for_ip(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
     }
+
+    /// Tests that a multicast pair doesn't receive packets sent to another
+    /// group joined on the same port.
+    #[cfg(unix)]
+    #[test]
+    fn multicast_other_group() {
+        let group = |last| Ipv4Addr::new(239, 255, 83, last);
+        let port = UdpPair::for_ip(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .unwrap()
+            .rtp_port;
+        let mine = UdpPair::for_multicast(IpAddr::V4(group(1)), port, None, Ipv4Addr::LOCALHOST, 0)
+            .unwrap();
+        let other =
+            UdpPair::for_multicast(IpAddr::V4(group(2)), port, None, Ipv4Addr::LOCALHOST, 0)
+                .unwrap();
+        let sender = socket2::Socket::new(
+            socket2::Domain::IPV4,
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )
+        .unwrap();
+        sender.set_multicast_if_v4(&Ipv4Addr::LOCALHOST).unwrap();
+        sender.set_multicast_loop_v4(true).unwrap();
+        let sender = UdpSocket::from(sender);
+        sender.send_to(b"other", (group(2), port)).unwrap();
+        sender.send_to(b"mine", (group(1), port)).unwrap();
+
+        let timeout = Some(std::time::Duration::from_secs(5));
+        let mut buf = [0u8; 16];
+        other.rtp_socket.set_read_timeout(timeout).unwrap();
+        let len = other.rtp_socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"other");
+        mine.rtp_socket.set_read_timeout(timeout).unwrap();
+        let len = mine.rtp_socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"mine");
+    }
 }
@@ -0,0 +1,418 @@
+// Copyright (C) 2021 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Async [`Connection`], using sockets from the [`crate::runtime`] backend.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{ready, Sink, SinkExt, Stream, StreamExt};
+use rtsp_types::Message;
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_util::codec::Framed;
+use url::Host;
+
+use crate::client::capture::{Capture, ConnectionCapture, ReplayConnection};
+use crate::framing::{Codec, CodecError};
+use crate::runtime::{AsyncTcpStream, AsyncUdpSocket, TcpStream, UdpSocket};
+use crate::{Error, ErrorInt, RtspMessageContext};
+
+use super::{ConnectionContext, ReceivedMessage, WallTime};
+
+mod tunnel;
+
+/// A RTSP connection which implements `Stream`, `Sink`, and `Unpin`.
+pub(crate) struct Connection {
+    inner: Inner,
+
+    /// Where to record traffic, if capturing.
+    capture: Option<ConnectionCapture>,
+}
+
+enum Inner {
+    Framed(Framed<Io, Codec>),
+
+    /// A recorded connection; see [`crate::client::Replay`].
+    Replay(ReplayConnection),
+}
+
+/// A byte stream supplied by the caller rather than dialed by Retina; see
+/// [`crate::client::Session::describe_with_stream`].
+pub(crate) trait CallerStream: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Sync + Unpin> CallerStream for T {}
+
+/// The underlying byte stream of a [`Connection`].
+enum Io {
+    Tcp(TcpStream),
+    Caller(Box<dyn CallerStream>),
+    HttpTunnel(tunnel::HttpTunnel),
+    #[cfg(feature = "tls")]
+    Tls(Box<tokio_rustls::client::TlsStream<TcpStream>>),
+}
+
+impl Connection {
+    pub(crate) async fn connect(host: Host<&str>, port: u16) -> Result<Self, std::io::Error> {
+        Self::from_stream(<TcpStream as AsyncTcpStream>::connect(host, port).await?)
+    }
+
+    /// Connects via an RTSP over HTTP tunnel; see the `tunnel` module.
+    ///
+    /// `path` is the path and query of the URL, used as the HTTP request target.
+    pub(crate) async fn connect_http_tunnel(
+        host: Host<&str>,
+        port: u16,
+        path: &str,
+    ) -> Result<Self, std::io::Error> {
+        let get = <TcpStream as AsyncTcpStream>::connect(host.clone(), port).await?;
+        let post = <TcpStream as AsyncTcpStream>::connect(host, port).await?;
+        let tunnel = tunnel::HttpTunnel::establish(get, post, path).await?;
+        let local_addr = AsyncTcpStream::local_addr(tunnel.get())?;
+        let peer_addr = AsyncTcpStream::peer_addr(tunnel.get())?;
+        Ok(Self::new(
+            Io::HttpTunnel(tunnel),
+            local_addr,
+            peer_addr,
+            None,
+        ))
+    }
+
+    /// Connects via TLS, as for `rtsps` URLs.
+    #[cfg(feature = "tls")]
+    pub(crate) async fn connect_tls(
+        host: Host<&str>,
+        port: u16,
+        config: std::sync::Arc<rustls::ClientConfig>,
+    ) -> Result<Self, std::io::Error> {
+        let server_name = match host {
+            Host::Domain(h) => rustls::ServerName::try_from(h)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+            Host::Ipv4(h) => rustls::ServerName::IpAddress(h.into()),
+            Host::Ipv6(h) => rustls::ServerName::IpAddress(h.into()),
+        };
+        let stream = <TcpStream as AsyncTcpStream>::connect(host, port).await?;
+        let local_addr = AsyncTcpStream::local_addr(&stream)?;
+        let peer_addr = AsyncTcpStream::peer_addr(&stream)?;
+        let stream = tokio_rustls::TlsConnector::from(config)
+            .connect(server_name, stream)
+            .await?;
+        let peer_certificate = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|c| c.first())
+            .map(|c| c.0.as_slice().into());
+        Ok(Self::new(
+            Io::Tls(Box::new(stream)),
+            local_addr,
+            peer_addr,
+            peer_certificate,
+        ))
+    }
+
+    pub(crate) fn from_stream(stream: TcpStream) -> Result<Self, std::io::Error> {
+        let local_addr = AsyncTcpStream::local_addr(&stream)?;
+        let peer_addr = AsyncTcpStream::peer_addr(&stream)?;
+        Ok(Self::new(Io::Tcp(stream), local_addr, peer_addr, None))
+    }
+
+    /// Uses a caller-supplied byte stream with the given addresses.
+    pub(crate) fn from_caller_stream(
+        stream: Box<dyn CallerStream>,
+        local_addr: std::net::SocketAddr,
+        peer_addr: std::net::SocketAddr,
+    ) -> Self {
+        Self::new(Io::Caller(stream), local_addr, peer_addr, None)
+    }
+
+    fn new(
+        io: Io,
+        local_addr: std::net::SocketAddr,
+        peer_addr: std::net::SocketAddr,
+        peer_certificate: Option<std::sync::Arc<[u8]>>,
+    ) -> Self {
+        let established_wall = WallTime::now();
+        Self {
+            inner: Inner::Framed(Framed::new(
+                io,
+                Codec::new(ConnectionContext {
+                    local_addr,
+                    peer_addr,
+                    established_wall,
+                    peer_certificate,
+                }),
+            )),
+            capture: None,
+        }
+    }
+
+    /// Stands in for a connection by replaying a recorded one.
+    pub(crate) fn replay(conn: ReplayConnection) -> Self {
+        Self {
+            inner: Inner::Replay(conn),
+            capture: None,
+        }
+    }
+
+    pub(crate) fn is_replay(&self) -> bool {
+        matches!(self.inner, Inner::Replay(_))
+    }
+
+    /// Records this connection's traffic from now on.
+    pub(crate) fn set_capture(&mut self, capture: &Capture) {
+        self.capture = Some(capture.connection(self.ctx()));
+    }
+
+    /// Records a UDP packet received for a stream set up on this connection.
+    pub(crate) fn capture_udp(
+        &self,
+        stream_i: usize,
+        rtcp: bool,
+        received_wall: WallTime,
+        data: &[u8],
+    ) {
+        if let Some(c) = self.capture.as_ref() {
+            c.udp(stream_i, rtcp, received_wall, data);
+        }
+    }
+
+    /// Takes the next replayed UDP packet for the given stream, if it's due.
+    ///
+    /// Returns whether it's RTCP, its received time, and its data.
+    pub(crate) fn take_replayed_udp(&mut self, stream_i: usize) -> Option<(bool, WallTime, Bytes)> {
+        match &mut self.inner {
+            Inner::Replay(r) => r.take_udp(stream_i),
+            Inner::Framed(_) => None,
+        }
+    }
+
+    pub(crate) fn ctx(&self) -> &ConnectionContext {
+        match &self.inner {
+            Inner::Framed(f) => &f.codec().ctx,
+            Inner::Replay(r) => r.ctx(),
+        }
+    }
+
+    pub(crate) fn eof_ctx(&self) -> RtspMessageContext {
+        match &self.inner {
+            Inner::Framed(f) => RtspMessageContext {
+                pos: f.codec().read_pos
+                    + u64::try_from(f.read_buffer().remaining()).expect("usize fits in u64"),
+                received_wall: WallTime::now(),
+                received: Instant::now(),
+            },
+            Inner::Replay(r) => r.eof_ctx(),
+        }
+    }
+
+    fn wrap_write_err(&self, e: CodecError) -> ErrorInt {
+        match e {
+            CodecError::IoError(source) => ErrorInt::WriteError {
+                conn_ctx: self.ctx().clone(),
+                source,
+            },
+            CodecError::ParseError { .. } => unreachable!(),
+        }
+    }
+}
+
+impl Stream for Connection {
+    type Item = Result<ReceivedMessage, Error>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let r = match &mut self.inner {
+            Inner::Framed(f) => ready!(f.poll_next_unpin(cx)).map(|r| {
+                r.map_err(|e| {
+                    wrap!(match e {
+                        CodecError::IoError(error) => ErrorInt::RtspReadError {
+                            conn_ctx: self.ctx().clone(),
+                            msg_ctx: self.eof_ctx(),
+                            source: error,
+                        },
+                        CodecError::ParseError { description, pos } => {
+                            ErrorInt::RtspFramingError {
+                                conn_ctx: self.ctx().clone(),
+                                msg_ctx: RtspMessageContext {
+                                    pos,
+                                    received_wall: WallTime::now(),
+                                    received: Instant::now(),
+                                },
+                                description,
+                            }
+                        }
+                    })
+                })
+            }),
+            Inner::Replay(r) => ready!(r.poll_next(cx)),
+        };
+        if let (Some(Ok(msg)), Some(c)) = (&r, self.capture.as_ref()) {
+            c.received(msg);
+        }
+        Poll::Ready(r)
+    }
+}
+
+impl Sink<Message<Bytes>> for Connection {
+    type Error = ErrorInt;
+
+    fn poll_ready(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Inner::Framed(f) => f.poll_ready_unpin(cx).map_err(|e| self.wrap_write_err(e)),
+            Inner::Replay(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn start_send(
+        mut self: std::pin::Pin<&mut Self>,
+        item: Message<Bytes>,
+    ) -> Result<(), Self::Error> {
+        if let Some(c) = self.capture.as_ref() {
+            c.sent(&item);
+        }
+        match &mut self.inner {
+            Inner::Framed(f) => f.start_send_unpin(item).map_err(|e| self.wrap_write_err(e)),
+            Inner::Replay(r) => {
+                r.sent(&item);
+                Ok(())
+            }
+        }
+    }
+
+    fn poll_flush(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Inner::Framed(f) => f.poll_flush_unpin(cx).map_err(|e| self.wrap_write_err(e)),
+            Inner::Replay(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        match &mut self.inner {
+            Inner::Framed(f) => f.poll_close_unpin(cx).map_err(|e| self.wrap_write_err(e)),
+            Inner::Replay(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl AsyncRead for Io {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            Io::Caller(s) => Pin::new(s).poll_read(cx, buf),
+            Io::HttpTunnel(t) => Pin::new(t).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            Io::Tls(t) => Pin::new(t).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Io {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            Io::Caller(s) => Pin::new(s).poll_write(cx, buf),
+            Io::HttpTunnel(t) => Pin::new(t).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            Io::Tls(t) => Pin::new(t).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_flush(cx),
+            Io::Caller(s) => Pin::new(s).poll_flush(cx),
+            Io::HttpTunnel(t) => Pin::new(t).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            Io::Tls(t) => Pin::new(t).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        match self.get_mut() {
+            Io::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            Io::Caller(s) => Pin::new(s).poll_shutdown(cx),
+            Io::HttpTunnel(t) => Pin::new(t).poll_shutdown(cx),
+            #[cfg(feature = "tls")]
+            Io::Tls(t) => Pin::new(t).poll_shutdown(cx),
+        }
+    }
+}
+
+impl tokio_util::codec::Decoder for Codec {
+    type Item = ReceivedMessage;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Codec::decode(self, src)
+    }
+}
+
+impl tokio_util::codec::Encoder<rtsp_types::Message<Bytes>> for Codec {
+    type Error = CodecError;
+
+    fn encode(
+        &mut self,
+        item: rtsp_types::Message<Bytes>,
+        mut dst: &mut BytesMut,
+    ) -> Result<(), Self::Error> {
+        item.write(&mut (&mut dst).writer())
+            .expect("BufMut Writer is infallible");
+        Ok(())
+    }
+}
+
+/// Async version of [`crate::UdpPair`].
+pub(crate) struct UdpPair {
+    pub(crate) rtp_socket: UdpSocket,
+    pub(crate) rtcp_socket: UdpSocket,
+}
+
+impl UdpPair {
+    /// Converts a `std` pair, which should already be connected if unicast.
+    pub(crate) fn from_std(inner: crate::UdpPair) -> Result<Self, std::io::Error> {
+        Ok(Self {
+            rtp_socket: UdpSocket::from_std(inner.rtp_socket)?,
+            rtcp_socket: UdpSocket::from_std(inner.rtcp_socket)?,
+        })
+    }
+
+    pub(crate) fn for_multicast(
+        group: std::net::IpAddr,
+        rtp_port: u16,
+        ttl: Option<u32>,
+        interface_v4: std::net::Ipv4Addr,
+        interface_v6: u32,
+    ) -> Result<Self, std::io::Error> {
+        Self::from_std(crate::UdpPair::for_multicast(
+            group,
+            rtp_port,
+            ttl,
+            interface_v4,
+            interface_v6,
+        )?)
+    }
+}
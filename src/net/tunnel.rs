@@ -0,0 +1,296 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! RTSP over HTTP tunneling, as described in Apple's "Tunneling RTSP and RTP
+//! over HTTP" QuickTime technote.
+//!
+//! The tunnel is a pair of HTTP connections associated via a `x-sessioncookie`
+//! header. The server sends RTSP messages (including interleaved data) as the
+//! endless body of the response to a `GET`. The client sends RTSP messages as
+//! the endless, base64-encoded body of a `POST`, which gets no response.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Buf, BytesMut};
+use futures::ready;
+use rand::Rng;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::runtime::TcpStream;
+
+/// The maximum length of the `GET` response headers.
+const MAX_RESPONSE_HEADER_LEN: usize = 4096;
+
+/// The amount of buffered plaintext at which writes start encoding and sending
+/// it even without a flush.
+const MAX_PLAIN_LEN: usize = 16 * 1024;
+
+/// Both legs of an established tunnel, implementing `AsyncRead` and `AsyncWrite`.
+pub(crate) struct HttpTunnel {
+    get: TcpStream,
+
+    /// Bytes read from `get` after the HTTP response headers, not yet consumed.
+    get_leftover: BytesMut,
+
+    post: TcpStream,
+
+    /// Plaintext which has been written but not yet encoded.
+    plain: Vec<u8>,
+
+    /// Encoded text which has not yet been written to `post`, starting at `encoded_pos`.
+    encoded: String,
+    encoded_pos: usize,
+}
+
+impl HttpTunnel {
+    /// Opens both legs of the tunnel, given two connections to the server.
+    ///
+    /// `path` is the path and query of the URL, used as the HTTP request target.
+    pub(crate) async fn establish(
+        mut get: TcpStream,
+        mut post: TcpStream,
+        path: &str,
+    ) -> Result<Self, io::Error> {
+        let cookie: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(22)
+            .map(char::from)
+            .collect();
+        get.write_all(
+            format!(
+                "GET {path} HTTP/1.0\r\n\
+                 x-sessioncookie: {cookie}\r\n\
+                 Accept: application/x-rtsp-tunnelled\r\n\
+                 Pragma: no-cache\r\n\
+                 Cache-Control: no-cache\r\n\
+                 \r\n"
+            )
+            .as_bytes(),
+        )
+        .await?;
+        let get_leftover = read_get_response(&mut get).await?;
+
+        // The Content-Length is arbitrary but large; the body is never complete.
+        post.write_all(
+            format!(
+                "POST {path} HTTP/1.0\r\n\
+                 x-sessioncookie: {cookie}\r\n\
+                 Content-Type: application/x-rtsp-tunnelled\r\n\
+                 Pragma: no-cache\r\n\
+                 Cache-Control: no-cache\r\n\
+                 Content-Length: 32767\r\n\
+                 Expires: Sun, 9 Jan 1972 00:00:00 GMT\r\n\
+                 \r\n"
+            )
+            .as_bytes(),
+        )
+        .await?;
+        Ok(HttpTunnel {
+            get,
+            get_leftover,
+            post,
+            plain: Vec::new(),
+            encoded: String::new(),
+            encoded_pos: 0,
+        })
+    }
+
+    /// Returns the connection on which RTSP messages are received.
+    pub(crate) fn get(&self) -> &TcpStream {
+        &self.get
+    }
+
+    /// Encodes buffered plaintext and writes it to `post`.
+    ///
+    /// Unless `all` is true, this encodes only whole 3-byte groups, so that
+    /// padding doesn't appear mid-message. Flushes encode everything; the
+    /// server decodes each padded chunk separately.
+    fn poll_drain(&mut self, cx: &mut Context<'_>, all: bool) -> Poll<Result<(), io::Error>> {
+        let len = if all {
+            self.plain.len()
+        } else {
+            self.plain.len() - self.plain.len() % 3
+        };
+        if len > 0 {
+            base64::encode_config_buf(&self.plain[..len], base64::STANDARD, &mut self.encoded);
+            self.plain.drain(..len);
+        }
+        while self.encoded_pos < self.encoded.len() {
+            let n = ready!(Pin::new(&mut self.post)
+                .poll_write(cx, &self.encoded.as_bytes()[self.encoded_pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.encoded_pos += n;
+        }
+        self.encoded.clear();
+        self.encoded_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Reads the `GET` response headers, returning any bytes which follow them.
+async fn read_get_response(get: &mut TcpStream) -> Result<BytesMut, io::Error> {
+    let mut buf = BytesMut::with_capacity(MAX_RESPONSE_HEADER_LEN);
+    let header_len = loop {
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+        if buf.len() >= MAX_RESPONSE_HEADER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "HTTP tunnel GET response headers too long",
+            ));
+        }
+        if get.read_buf(&mut buf).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "EOF while awaiting HTTP tunnel GET response",
+            ));
+        }
+    };
+    let status_line = buf[..header_len]
+        .split(|&b| b == b'\r')
+        .next()
+        .expect("split returns at least one item");
+    let status_line = String::from_utf8_lossy(status_line);
+    let mut parts = status_line.splitn(3, ' ');
+    let ok = matches!(
+        (parts.next(), parts.next()),
+        (Some(v), Some("200")) if v.starts_with("HTTP/1.")
+    );
+    if !ok {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("HTTP tunnel GET failed: {status_line:?}"),
+        ));
+    }
+    buf.advance(header_len);
+    Ok(buf)
+}
+
+impl AsyncRead for HttpTunnel {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        if !self.get_leftover.is_empty() {
+            let n = std::cmp::min(buf.remaining(), self.get_leftover.len());
+            buf.put_slice(&self.get_leftover[..n]);
+            self.get_leftover.advance(n);
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.get).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for HttpTunnel {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        if self.plain.len() >= MAX_PLAIN_LEN {
+            ready!(self.poll_drain(cx, false))?;
+        }
+        self.plain.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        ready!(self.poll_drain(cx, true))?;
+        Pin::new(&mut self.post).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        ready!(self.poll_drain(cx, true))?;
+        Pin::new(&mut self.post).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::AsyncTcpListener;
+    use crate::testutil::{connect, listen};
+
+    async fn read_headers(stream: &mut TcpStream) -> String {
+        let mut buf = Vec::new();
+        while !buf.ends_with(b"\r\n\r\n") {
+            buf.push(stream.read_u8().await.unwrap());
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn cookie(headers: &str) -> &str {
+        headers
+            .lines()
+            .find_map(|l| l.strip_prefix("x-sessioncookie: "))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let (listener, addr) = listen().await;
+        let client = async {
+            let get = connect(addr).await;
+            let post = connect(addr).await;
+            let mut tunnel = HttpTunnel::establish(get, post, "/stream?foo=bar")
+                .await
+                .unwrap();
+            tunnel.write_all(b"OPTIONS * RTSP/1.0\r\n").await.unwrap();
+            tunnel.write_all(b"CSeq: 1\r\n\r\n").await.unwrap();
+            tunnel.flush().await.unwrap();
+            let mut buf = [0u8; 5];
+            tunnel.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"RTSP/");
+        };
+        let server = async {
+            let (mut get, _) = listener.accept().await.unwrap();
+            let (mut post, _) = listener.accept().await.unwrap();
+            let get_headers = read_headers(&mut get).await;
+            assert!(get_headers.starts_with("GET /stream?foo=bar HTTP/1.0\r\n"));
+            get.write_all(
+                b"HTTP/1.0 200 OK\r\nContent-Type: application/x-rtsp-tunnelled\r\n\r\nRTSP/",
+            )
+            .await
+            .unwrap();
+            let post_headers = read_headers(&mut post).await;
+            assert!(post_headers.starts_with("POST /stream?foo=bar HTTP/1.0\r\n"));
+            assert_eq!(cookie(&get_headers), cookie(&post_headers));
+            let expected = base64::encode("OPTIONS * RTSP/1.0\r\nCSeq: 1\r\n\r\n");
+            let mut body = vec![0u8; expected.len()];
+            post.read_exact(&mut body).await.unwrap();
+            assert_eq!(std::str::from_utf8(&body).unwrap(), expected);
+        };
+        tokio::join!(client, server);
+    }
+
+    #[tokio::test]
+    async fn get_failure() {
+        let (listener, addr) = listen().await;
+        let client = async {
+            let get = connect(addr).await;
+            let post = connect(addr).await;
+            HttpTunnel::establish(get, post, "/")
+                .await
+                .map(|_| ())
+                .unwrap_err();
+        };
+        let server = async {
+            let (mut get, _) = listener.accept().await.unwrap();
+            let (_post, _) = listener.accept().await.unwrap();
+            read_headers(&mut get).await;
+            get.write_all(b"HTTP/1.0 404 Not Found\r\n\r\n")
+                .await
+                .unwrap();
+        };
+        tokio::join!(client, server);
+    }
+}
@@ -100,6 +100,11 @@ impl ReceivedCompoundPacket {
     pub fn pkts(&self) -> impl Iterator<Item = PacketRef> {
         CompoundPacketIterator(&self.raw[..])
     }
+
+    /// Returns the first valid goodbye packet within this compound packet, if any.
+    pub fn goodbye(&self) -> Option<GoodbyeRef<'_>> {
+        self.pkts().find_map(|p| p.as_goodbye().ok().flatten())
+    }
 }
 
 impl std::fmt::Debug for ReceivedCompoundPacket {
@@ -135,6 +140,7 @@ impl<'a> Iterator for CompoundPacketIterator<'a> {
 #[non_exhaustive]
 pub enum TypedPacketRef<'a> {
     SenderReport(SenderReportRef<'a>),
+    Goodbye(GoodbyeRef<'a>),
 }
 
 /// A sender report, as defined in
@@ -216,6 +222,78 @@ impl<'a> std::ops::Deref for SenderReportRef<'a> {
     }
 }
 
+/// A goodbye packet, as defined in
+/// [RFC 3550 section 6.6](https://datatracker.ietf.org/doc/html/rfc3550#section-6.6).
+///
+/// ```text
+///        0                   1                   2                   3
+///        0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |V=2|P|    SC   |   PT=BYE=203  |             length            |
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       |                           SSRC/CSRC                           |
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///       :                              ...                              :
+///       +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+/// (opt) |     length    |               reason for leaving            ...
+///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+pub struct GoodbyeRef<'a>(PacketRef<'a>);
+
+impl<'a> GoodbyeRef<'a> {
+    fn validate(pkt: PacketRef<'a>) -> Result<Self, String> {
+        let ssrcs_end = COMMON_HEADER_LEN + 4 * usize::from(pkt.count());
+        if pkt.payload_end < ssrcs_end {
+            return Err(format!(
+                "RTCP BYE has invalid count={} with unpadded_byte_len={}",
+                pkt.count(),
+                pkt.payload_end
+            ));
+        }
+        if pkt.payload_end > ssrcs_end {
+            let reason_end = ssrcs_end + 1 + usize::from(pkt.buf[ssrcs_end]);
+            if pkt.payload_end < reason_end {
+                return Err(format!(
+                    "RTCP BYE has reason ending at {} with unpadded_byte_len={}",
+                    reason_end, pkt.payload_end
+                ));
+            }
+        }
+        Ok(GoodbyeRef(pkt))
+    }
+
+    fn ssrcs_end(&self) -> usize {
+        COMMON_HEADER_LEN + 4 * usize::from(self.0.count())
+    }
+
+    /// Returns the SSRC/CSRC identifiers of the sources which are leaving.
+    pub fn ssrcs(&self) -> impl Iterator<Item = u32> + 'a {
+        self.0.buf[COMMON_HEADER_LEN..self.ssrcs_end()]
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes(c.try_into().unwrap()))
+    }
+
+    /// Returns the raw reason for leaving, if supplied.
+    ///
+    /// RFC 3550 specifies UTF-8, but this isn't validated.
+    pub fn reason(&self) -> Option<&'a [u8]> {
+        let start = self.ssrcs_end();
+        if self.0.payload_end == start {
+            return None;
+        }
+        let len = usize::from(self.0.buf[start]);
+        Some(&self.0.buf[start + 1..start + 1 + len])
+    }
+}
+
+impl<'a> std::ops::Deref for GoodbyeRef<'a> {
+    type Target = PacketRef<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 /// A generic packet, not parsed as any particular payload type.
 ///
 /// This only interprets the leading four bytes:
@@ -304,6 +382,7 @@ impl<'a> PacketRef<'a> {
             200 => Ok(Some(TypedPacketRef::SenderReport(
                 SenderReportRef::validate(self)?,
             ))),
+            203 => Ok(Some(TypedPacketRef::Goodbye(GoodbyeRef::validate(self)?))),
             _ => Ok(None),
         }
     }
@@ -316,6 +395,14 @@ impl<'a> PacketRef<'a> {
         Ok(None)
     }
 
+    /// Parses as a goodbye, if the type matches.
+    pub fn as_goodbye(self) -> Result<Option<GoodbyeRef<'a>>, String> {
+        if self.payload_type() == 203 {
+            return Ok(Some(GoodbyeRef::validate(self)?));
+        }
+        Ok(None)
+    }
+
     /// Returns true iff this packet has padding.
     #[inline]
     pub fn has_padding(&self) -> bool {
@@ -359,6 +446,32 @@ mod tests {
         assert_eq!(buf.len(), 0);
     }
 
+    #[test]
+    fn goodbye() {
+        // Receiver report followed by a goodbye with a reason.
+        let buf = b"\x80\xc9\x00\x01\x12\x34\x56\x78\
+                    \x81\xcb\x00\x03\x66\x42\x6a\xe1\
+                    \x07restart";
+        let compound = ReceivedCompoundPacket::dummy(None, buf);
+        let bye = compound.goodbye().unwrap();
+        assert_eq!(bye.ssrcs().collect::<Vec<_>>(), [0x66426ae1]);
+        assert_eq!(bye.reason(), Some(&b"restart"[..]));
+
+        // Without a reason.
+        let (pkt, _) =
+            PacketRef::parse(b"\x82\xcb\x00\x02\x00\x00\x00\x01\x00\x00\x00\x02").unwrap();
+        let bye = match pkt.as_typed().unwrap() {
+            Some(TypedPacketRef::Goodbye(bye)) => bye,
+            _ => panic!(),
+        };
+        assert_eq!(bye.ssrcs().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(bye.reason(), None);
+
+        // Count too high.
+        let (pkt, _) = PacketRef::parse(b"\x82\xcb\x00\x01\x00\x00\x00\x01").unwrap();
+        assert!(pkt.as_goodbye().is_err());
+    }
+
     #[test]
     fn padding() {
         let buf = b"\xa7\x00\x00\x02asdf\x00\x00\x00\x04rest";
@@ -9,11 +9,25 @@ use std::ops::Range;
 
 use bytes::{Buf, Bytes};
 
-use crate::{PacketContext, Timestamp};
+use crate::{NtpTimestamp, PacketContext, Timestamp};
 
 /// The minimum length of an RTP header (no CSRCs or extensions).
 const MIN_HEADER_LEN: u16 = 12;
 
+/// The "defined by profile" field of the ONVIF replay header extension.
+///
+/// See the [ONVIF Streaming
+/// Specification](https://www.onvif.org/specs/stream/ONVIF-Streaming-Spec.pdf)
+/// section 6.3.
+const ONVIF_REPLAY_EXTENSION: u16 = 0xABAC;
+
+/// The "defined by profile" field of RFC 8285 one-byte header extensions.
+const ONE_BYTE_EXTENSION: u16 = 0xBEDE;
+
+/// The "defined by profile" field of RFC 8285 two-byte header extensions,
+/// ignoring the low 4 bits (`appbits`).
+const TWO_BYTE_EXTENSION: u16 = 0x1000;
+
 /// Raw packet without state-specific interpretation or metadata.
 ///
 /// This design is inspired by [`rtp-rs`](https://crates.io/crates/rtp-rs) in
@@ -189,6 +203,19 @@ impl RawPacket {
         assert!(self.0.len() >= usize::from(MIN_HEADER_LEN));
         u32::from_be_bytes([self.0[4], self.0[5], self.0[6], self.0[7]])
     }
+
+    /// Returns the header extension's "defined by profile" field and data, if present.
+    ///
+    /// The bounds were checked in [`RawPacket::new`].
+    pub fn extension(&self) -> Option<(u16, &[u8])> {
+        if (self.0[0] & 0b0001_0000) == 0 {
+            return None;
+        }
+        let start = usize::from(MIN_HEADER_LEN) + 4 * usize::from(self.0[0] & 0b0000_1111);
+        let profile = u16::from_be_bytes([self.0[start], self.0[start + 1]]);
+        let len = 4 * usize::from(u16::from_be_bytes([self.0[start + 2], self.0[start + 3]]));
+        Some((profile, &self.0[start + 4..start + 4 + len]))
+    }
 }
 
 #[derive(Debug)]
@@ -309,6 +336,27 @@ impl ReceivedPacket {
         self.loss
     }
 
+    /// Returns the header extension, if present.
+    #[inline]
+    pub fn header_extension(&self) -> Option<HeaderExtension<'_>> {
+        self.raw
+            .extension()
+            .map(|(profile, data)| HeaderExtension { profile, data })
+    }
+
+    /// Returns the absolute time from the ONVIF replay header extension, if present.
+    ///
+    /// ONVIF servers send this when playing back recordings; see
+    /// [`crate::client::PlayOptions::onvif_replay`].
+    pub fn ntp_timestamp(&self) -> Option<NtpTimestamp> {
+        match self.raw.extension() {
+            Some((ONVIF_REPLAY_EXTENSION, data)) if data.len() >= 8 => Some(NtpTimestamp(
+                u64::from_be_bytes(data[..8].try_into().expect("slice is 8 bytes")),
+            )),
+            _ => None,
+        }
+    }
+
     /// Consumes the `ReceivedPacket` and returns the `Payload` as a [`Bytes`].
     ///
     /// This is currently is very efficient (no copying or reference-counting),
@@ -322,6 +370,112 @@ impl ReceivedPacket {
     }
 }
 
+/// An RTP header extension, as described in
+/// [RFC 3550 section 5.3.1](https://datatracker.ietf.org/doc/html/rfc3550#section-5.3.1).
+///
+/// Its contents are defined by the RTP profile, as indicated by
+/// [`HeaderExtension::profile`]. Most commonly, they're a list of elements as
+/// described in [RFC 8285](https://datatracker.ietf.org/doc/html/rfc8285);
+/// see [`HeaderExtension::elements`]. Vendor-specific formats such as the
+/// ONVIF replay extension (`0xABAC`) are available via
+/// [`HeaderExtension::data`].
+#[derive(Copy, Clone)]
+pub struct HeaderExtension<'a> {
+    profile: u16,
+    data: &'a [u8],
+}
+
+impl<'a> std::fmt::Debug for HeaderExtension<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeaderExtension")
+            .field("profile", &format_args!("{:#06x}", self.profile))
+            .field("data", &crate::hex::LimitedHex::new(self.data, 64))
+            .finish()
+    }
+}
+
+impl<'a> HeaderExtension<'a> {
+    /// Returns the 16-bit "defined by profile" field.
+    #[inline]
+    pub fn profile(&self) -> u16 {
+        self.profile
+    }
+
+    /// Returns the extension's data, excluding the 4-byte extension header.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns an iterator over the RFC 8285 elements, or `None` if this
+    /// extension uses neither the one-byte nor the two-byte header format.
+    ///
+    /// Map element ids to URIs via [`crate::client::Stream::header_extensions`].
+    pub fn elements(&self) -> Option<HeaderExtensionElements<'a>> {
+        let two_byte = if self.profile == ONE_BYTE_EXTENSION {
+            false
+        } else if self.profile & 0xFFF0 == TWO_BYTE_EXTENSION {
+            true
+        } else {
+            return None;
+        };
+        Some(HeaderExtensionElements {
+            data: self.data,
+            two_byte,
+        })
+    }
+}
+
+/// Iterator over the elements of an RFC 8285 header extension, returned by
+/// [`HeaderExtension::elements`].
+///
+/// Yields an `(id, data)` pair for each element, skipping padding. After a
+/// malformed element, yields an error and stops.
+pub struct HeaderExtensionElements<'a> {
+    data: &'a [u8],
+    two_byte: bool,
+}
+
+impl<'a> Iterator for HeaderExtensionElements<'a> {
+    type Item = Result<(u8, &'a [u8]), &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (&first, rest) = self.data.split_first()?;
+            if first == 0 {
+                // Padding.
+                self.data = rest;
+                continue;
+            }
+            let (id, len, rest) = if self.two_byte {
+                let (&len, rest) = match rest.split_first() {
+                    Some(s) => s,
+                    None => {
+                        self.data = &[];
+                        return Some(Err("two-byte extension element is missing length"));
+                    }
+                };
+                (first, usize::from(len), rest)
+            } else {
+                let id = first >> 4;
+                if id == 15 {
+                    // RFC 8285 section 4.2: "processing of the entire extension MUST terminate".
+                    self.data = &[];
+                    return None;
+                }
+                (id, usize::from(first & 0x0F) + 1, rest)
+            };
+            if rest.len() < len {
+                self.data = &[];
+                return Some(Err("extension element extends beyond end of extension"));
+            }
+            let (element, rest) = rest.split_at(len);
+            self.data = rest;
+            return Some(Ok((id, element)));
+        }
+    }
+}
+
 /// Testing API; exposed for fuzz tests.
 #[doc(hidden)]
 pub struct ReceivedPacketBuilder {
@@ -375,5 +529,78 @@ mod tests {
         let payload = &pkt.0[payload_range.start as usize..payload_range.end as usize];
         assert_eq!(payload_range, 28..55);
         assert_eq!(payload[0], 0x67);
+        let (profile, extension) = pkt.extension().unwrap();
+        assert_eq!(profile, ONVIF_REPLAY_EXTENSION);
+        assert_eq!(extension.len(), 12);
+    }
+
+    #[test]
+    pub fn onvif_replay_ntp_timestamp() {
+        init_logging();
+        // ONVIF replay extension with NTP time 0xd2d5_7b68_7000_0000,
+        // C=1 E=0 D=0 T=0, CSeq=3.
+        let data = b"\x90\x60\x4c\x62\x01\xbb\x3c\xb5\x1c\x04\x15\xb1\xab\xac\x00\x03\
+                     \xd2\xd5\x7b\x68\x70\x00\x00\x00\x80\x03\x00\x00\x67\x64\x00\x32";
+        let (raw, payload_range) = RawPacket::new(Bytes::from_static(data)).unwrap();
+        let pkt = ReceivedPacket {
+            ctx: PacketContext::dummy(),
+            stream_id: 0,
+            timestamp: Timestamp::new(0x01bb3cb5, std::num::NonZeroU32::new(90_000).unwrap(), 0)
+                .unwrap(),
+            raw,
+            payload_range,
+            loss: 0,
+        };
+        assert_eq!(
+            pkt.ntp_timestamp(),
+            Some(NtpTimestamp(0xd2d5_7b68_7000_0000))
+        );
+        let ext = pkt.header_extension().unwrap();
+        assert_eq!(ext.profile(), ONVIF_REPLAY_EXTENSION);
+        assert_eq!(ext.data().len(), 12);
+        assert_eq!(pkt.payload(), b"\x67\x64\x00\x32");
+    }
+
+    #[test]
+    fn one_byte_elements() {
+        // Elements 1 (1 byte) and 2 (3 bytes) with padding between, then a
+        // reserved id 15 which terminates processing.
+        let ext = HeaderExtension {
+            profile: ONE_BYTE_EXTENSION,
+            data: b"\x10\xaa\x00\x22\x01\x02\x03\xf0\x30\x00",
+        };
+        let elements: Vec<_> = ext.elements().unwrap().collect();
+        assert_eq!(
+            elements,
+            [Ok((1, &b"\xaa"[..])), Ok((2, &b"\x01\x02\x03"[..]))]
+        );
+    }
+
+    #[test]
+    fn two_byte_elements() {
+        // Element 1 (empty), padding, element 200 (2 bytes), then a truncated one.
+        let ext = HeaderExtension {
+            profile: 0x1003,
+            data: b"\x01\x00\x00\xc8\x02\x01\x02\x05\x04\x00",
+        };
+        let elements: Vec<_> = ext.elements().unwrap().collect();
+        assert_eq!(
+            elements,
+            [
+                Ok((1, &b""[..])),
+                Ok((200, &b"\x01\x02"[..])),
+                Err("extension element extends beyond end of extension"),
+            ]
+        );
+    }
+
+    #[test]
+    fn vendor_extension() {
+        let ext = HeaderExtension {
+            profile: ONVIF_REPLAY_EXTENSION,
+            data: b"\xd2\xd5\x7b\x68\x70\x00\x00\x00",
+        };
+        assert!(ext.elements().is_none());
+        assert_eq!(ext.data().len(), 8);
     }
 }
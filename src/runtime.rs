@@ -0,0 +1,127 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Async runtime abstraction: the network I/O, timers, and task spawning used
+//! by the async client.
+//!
+//! The backend is chosen at compile time via cargo features. `tokio` (the
+//! default) takes precedence over `async-std` if both are enabled. Byte
+//! streams use tokio's `AsyncRead` and `AsyncWrite` traits regardless, as
+//! these are needed by `tokio_util`'s `Framed` and `tokio_rustls`. They don't
+//! require a tokio runtime.
+//!
+//! Deadlines are expressed as [`Instant`] everywhere. Outside a paused tokio
+//! test runtime, this is equivalent to [`std::time::Instant`].
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use futures::future::BoxFuture;
+use url::Host;
+
+pub(crate) use ::tokio::time::Instant;
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+mod async_std;
+#[cfg(feature = "tokio")]
+mod tokio;
+
+/// The backend selected by cargo features.
+#[cfg(feature = "tokio")]
+pub(crate) type Rt = self::tokio::Tokio;
+
+/// The backend selected by cargo features.
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+pub(crate) type Rt = self::async_std::AsyncStd;
+
+pub(crate) type TcpListener = <Rt as Runtime>::TcpListener;
+pub(crate) type TcpStream = <Rt as Runtime>::TcpStream;
+pub(crate) type UdpSocket = <Rt as Runtime>::UdpSocket;
+pub(crate) type Sleep = <Rt as Runtime>::Sleep;
+
+/// An async runtime backend.
+pub(crate) trait Runtime {
+    type TcpListener: AsyncTcpListener<Stream = Self::TcpStream>;
+    type TcpStream: AsyncTcpStream;
+    type UdpSocket: AsyncUdpSocket;
+    type Sleep: Timer;
+
+    /// Runs `future` to completion in the background.
+    fn spawn<F: Future<Output = ()> + Send + 'static>(future: F);
+
+    /// Returns a timer which fires at `deadline`.
+    fn sleep_until(deadline: Instant) -> Self::Sleep;
+}
+
+/// A connected TCP stream.
+pub(crate) trait AsyncTcpStream:
+    AsyncRead + AsyncWrite + Send + Sync + Unpin + 'static
+{
+    fn connect(host: Host<&str>, port: u16) -> BoxFuture<'_, io::Result<Self>>
+    where
+        Self: Sized;
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+}
+
+/// A TCP listener, as used by [`crate::server`].
+pub(crate) trait AsyncTcpListener: Send + Sync + 'static {
+    type Stream;
+
+    fn bind(addr: SocketAddr) -> BoxFuture<'static, io::Result<Self>>
+    where
+        Self: Sized;
+    fn accept(&self) -> BoxFuture<'_, io::Result<(Self::Stream, SocketAddr)>>;
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+/// A UDP socket, which is connected unless it receives multicast.
+pub(crate) trait AsyncUdpSocket: Send + Sync + 'static {
+    /// Converts a bound (and possibly connected) `std` socket.
+    fn from_std(socket: std::net::UdpSocket) -> io::Result<Self>
+    where
+        Self: Sized;
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>>;
+    fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>>;
+}
+
+/// A resettable timer, as in `tokio::time::Sleep`.
+pub(crate) trait Timer: Future<Output = ()> + Send + Sync {
+    fn deadline(&self) -> Instant;
+    fn reset(self: Pin<&mut Self>, deadline: Instant);
+}
+
+/// Runs `future` to completion in the background.
+pub(crate) fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+    Rt::spawn(future)
+}
+
+/// Returns a timer which fires at `deadline`.
+pub(crate) fn sleep_until(deadline: Instant) -> Sleep {
+    Rt::sleep_until(deadline)
+}
+
+/// Returns a timer which fires after `duration`.
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    Rt::sleep_until(Instant::now() + duration)
+}
+
+/// Runs `future` until `deadline`, returning `None` if it hasn't completed by then.
+pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Option<F::Output> {
+    futures::pin_mut!(future);
+    match futures::future::select(future, Box::pin(sleep_until(deadline))).await {
+        futures::future::Either::Left((output, _)) => Some(output),
+        futures::future::Either::Right(_) => None,
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! async-std [`Runtime`] backend.
+//!
+//! Timers and UDP sockets use `async-io` directly, so this also works from
+//! other executors built on it, such as smol. Background tasks are spawned on
+//! async-std's global executor.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_io::Async;
+use futures::future::BoxFuture;
+use futures::ready;
+use tokio::io::ReadBuf;
+use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt};
+use url::Host;
+
+use super::{AsyncTcpListener, AsyncTcpStream, AsyncUdpSocket, Instant, Runtime, Timer};
+
+pub(crate) struct AsyncStd;
+
+impl Runtime for AsyncStd {
+    type TcpListener = TcpListener;
+    type TcpStream = Compat<async_std::net::TcpStream>;
+    type UdpSocket = Async<std::net::UdpSocket>;
+    type Sleep = Sleep;
+
+    fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        async_std::task::spawn(future);
+    }
+
+    fn sleep_until(deadline: Instant) -> Self::Sleep {
+        Sleep {
+            timer: async_io::Timer::at(deadline.into_std()),
+            deadline,
+        }
+    }
+}
+
+impl AsyncTcpStream for Compat<async_std::net::TcpStream> {
+    fn connect(host: Host<&str>, port: u16) -> BoxFuture<'_, io::Result<Self>> {
+        use async_std::net::TcpStream;
+        Box::pin(async move {
+            let stream = match host {
+                Host::Domain(h) => TcpStream::connect((h, port)).await,
+                Host::Ipv4(h) => TcpStream::connect((h, port)).await,
+                Host::Ipv6(h) => TcpStream::connect((h, port)).await,
+            }?;
+            Ok(stream.compat())
+        })
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().local_addr()
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().peer_addr()
+    }
+}
+
+pub(crate) struct TcpListener(async_std::net::TcpListener);
+
+impl AsyncTcpListener for TcpListener {
+    type Stream = Compat<async_std::net::TcpStream>;
+
+    fn bind(addr: SocketAddr) -> BoxFuture<'static, io::Result<Self>> {
+        Box::pin(async move { Ok(Self(async_std::net::TcpListener::bind(addr).await?)) })
+    }
+
+    fn accept(&self) -> BoxFuture<'_, io::Result<(Self::Stream, SocketAddr)>> {
+        Box::pin(async move {
+            let (stream, addr) = self.0.accept().await?;
+            Ok((stream.compat(), addr))
+        })
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+}
+
+impl AsyncUdpSocket for Async<std::net::UdpSocket> {
+    fn from_std(socket: std::net::UdpSocket) -> io::Result<Self> {
+        Async::new(socket)
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.get_ref().recv(buf.initialize_unfilled()) {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+            ready!(self.poll_readable(cx))?;
+        }
+    }
+
+    fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        loop {
+            match self.get_ref().send(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                r => return Poll::Ready(r),
+            }
+            ready!(self.poll_writable(cx))?;
+        }
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match self.get_ref().send_to(buf, target) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                r => return Poll::Ready(r),
+            }
+            ready!(self.poll_writable(cx))?;
+        }
+    }
+}
+
+/// An `async_io::Timer` which yields `()` and remembers its deadline, as
+/// `tokio::time::Sleep` does.
+pub(crate) struct Sleep {
+    timer: async_io::Timer,
+    deadline: Instant,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.timer).poll(cx).map(|_| ())
+    }
+}
+
+impl Timer for Sleep {
+    fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    fn reset(mut self: Pin<&mut Self>, deadline: Instant) {
+        self.timer.set_at(deadline.into_std());
+        self.deadline = deadline;
+    }
+}
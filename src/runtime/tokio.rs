@@ -0,0 +1,123 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! tokio [`Runtime`] backend.
+//!
+//! Sessions must be used from within a tokio runtime with both time and I/O
+//! resource drivers enabled.
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use pin_project::pin_project;
+use tokio::io::ReadBuf;
+use tokio::net::TcpStream;
+use url::Host;
+
+use super::{AsyncTcpListener, AsyncTcpStream, AsyncUdpSocket, Instant, Runtime, Timer};
+
+pub(crate) struct Tokio;
+
+impl Runtime for Tokio {
+    type TcpListener = TcpListener;
+    type TcpStream = TcpStream;
+    type UdpSocket = UdpSocket;
+    type Sleep = Sleep;
+
+    fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        tokio::spawn(future);
+    }
+
+    fn sleep_until(deadline: Instant) -> Self::Sleep {
+        Sleep(tokio::time::sleep_until(deadline))
+    }
+}
+
+impl AsyncTcpStream for TcpStream {
+    fn connect(host: Host<&str>, port: u16) -> BoxFuture<'_, io::Result<Self>> {
+        Box::pin(async move {
+            match host {
+                Host::Domain(h) => TcpStream::connect((h, port)).await,
+                Host::Ipv4(h) => TcpStream::connect((h, port)).await,
+                Host::Ipv6(h) => TcpStream::connect((h, port)).await,
+            }
+        })
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::local_addr(self)
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::peer_addr(self)
+    }
+}
+
+pub(crate) struct TcpListener(tokio::net::TcpListener);
+
+impl AsyncTcpListener for TcpListener {
+    type Stream = TcpStream;
+
+    fn bind(addr: SocketAddr) -> BoxFuture<'static, io::Result<Self>> {
+        Box::pin(async move { Ok(Self(tokio::net::TcpListener::bind(addr).await?)) })
+    }
+
+    fn accept(&self) -> BoxFuture<'_, io::Result<(TcpStream, SocketAddr)>> {
+        Box::pin(self.0.accept())
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+}
+
+pub(crate) struct UdpSocket(tokio::net::UdpSocket);
+
+impl AsyncUdpSocket for UdpSocket {
+    fn from_std(socket: std::net::UdpSocket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self(tokio::net::UdpSocket::from_std(socket)?))
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        self.0.poll_recv(cx, buf)
+    }
+
+    fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.0.poll_send(cx, buf)
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        self.0.poll_send_to(cx, buf, target)
+    }
+}
+
+#[pin_project]
+pub(crate) struct Sleep(#[pin] tokio::time::Sleep);
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.project().0.poll(cx)
+    }
+}
+
+impl Timer for Sleep {
+    fn deadline(&self) -> Instant {
+        self.0.deadline()
+    }
+
+    fn reset(self: Pin<&mut Self>, deadline: Instant) {
+        self.project().0.reset(deadline)
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Generates SDP session descriptions, as returned in `DESCRIBE` responses
+//! and sent in `ANNOUNCE` requests.
+
+use std::net::IpAddr;
+use std::num::NonZeroU16;
+
+use bytes::Bytes;
+use sdp_types::{Attribute, Connection, Media, Origin, Session, Time};
+
+use crate::codec::ParametersRef;
+
+/// Describes a single media stream.
+#[derive(Clone, Debug)]
+pub(crate) struct MediaDescription {
+    /// The SDP media type, such as `video`.
+    pub(crate) media: &'static str,
+
+    /// The dynamic RTP payload type.
+    pub(crate) payload_type: u8,
+
+    /// The encoding name for the `rtpmap` attribute, such as `H264`.
+    pub(crate) encoding_name: &'static str,
+    pub(crate) clock_rate: u32,
+
+    /// The number of audio channels, if specified.
+    pub(crate) channels: Option<NonZeroU16>,
+
+    /// The `format-specific-params` for the `fmtp` attribute, if any.
+    pub(crate) format_specific_params: Option<String>,
+
+    /// The stream's URL, relative to the presentation's base URL.
+    pub(crate) control: String,
+}
+
+impl MediaDescription {
+    /// Describes a stream with the given parameters, as sent by the H.264 or
+    /// AAC packetizer.
+    pub(crate) fn new(
+        parameters: ParametersRef,
+        payload_type: u8,
+        control: String,
+    ) -> Result<Self, String> {
+        match parameters {
+            ParametersRef::Video(v) if v.rfc6381_codec().starts_with("avc1.") => {
+                Ok(MediaDescription {
+                    media: "video",
+                    payload_type,
+                    encoding_name: "H264",
+                    clock_rate: 90_000,
+                    channels: None,
+                    format_specific_params: Some(crate::codec::h264::format_specific_params(
+                        v.extra_data(),
+                    )?),
+                    control,
+                })
+            }
+            ParametersRef::Audio(a)
+                if a.rfc6381_codec()
+                    .map_or(false, |c| c.starts_with("mp4a.40.")) =>
+            {
+                Ok(MediaDescription {
+                    media: "audio",
+                    payload_type,
+                    encoding_name: "MPEG4-GENERIC",
+                    clock_rate: a.clock_rate(),
+                    channels: Some(crate::codec::aac::channels(a.extra_data())?),
+                    format_specific_params: Some(crate::codec::aac::format_specific_params(
+                        a.extra_data(),
+                    )?),
+                    control,
+                })
+            }
+            _ => Err("only H.264 video and AAC audio are supported".to_owned()),
+        }
+    }
+}
+
+fn attribute(attribute: &str, value: String) -> Attribute {
+    Attribute {
+        attribute: attribute.to_owned(),
+        value: Some(value),
+    }
+}
+
+/// Writes a session description with the given media streams.
+///
+/// `session_id` should be unique to this presentation; `addr` is the server's
+/// address as seen by the client.
+pub(crate) fn write(session_id: u64, addr: IpAddr, medias: &[MediaDescription]) -> Bytes {
+    let addrtype = match addr {
+        IpAddr::V4(_) => "IP4",
+        IpAddr::V6(_) => "IP6",
+    };
+    let session = Session {
+        origin: Origin {
+            username: None,
+            sess_id: session_id.to_string(),
+            sess_version: 1,
+            nettype: "IN".to_owned(),
+            addrtype: addrtype.to_owned(),
+            unicast_address: addr.to_string(),
+        },
+        session_name: "Retina".to_owned(),
+        session_description: None,
+        uri: None,
+        emails: Vec::new(),
+        phones: Vec::new(),
+        connection: Some(Connection {
+            nettype: "IN".to_owned(),
+            addrtype: addrtype.to_owned(),
+            connection_address: match addr {
+                IpAddr::V4(_) => "0.0.0.0".to_owned(),
+                IpAddr::V6(_) => "::".to_owned(),
+            },
+        }),
+        bandwidths: Vec::new(),
+        times: vec![Time {
+            start_time: 0,
+            stop_time: 0,
+            repeats: Vec::new(),
+        }],
+        time_zones: Vec::new(),
+        key: None,
+        attributes: vec![attribute("range", "npt=now-".to_owned())],
+        medias: medias
+            .iter()
+            .map(|m| {
+                let mut rtpmap = format!("{} {}/{}", m.payload_type, m.encoding_name, m.clock_rate);
+                if let Some(c) = m.channels {
+                    rtpmap.push_str(&format!("/{c}"));
+                }
+                let mut attributes = vec![attribute("rtpmap", rtpmap)];
+                if let Some(p) = &m.format_specific_params {
+                    attributes.push(attribute("fmtp", format!("{} {}", m.payload_type, p)));
+                }
+                attributes.push(attribute("control", m.control.clone()));
+                Media {
+                    media: m.media.to_owned(),
+                    port: 0,
+                    num_ports: None,
+                    proto: "RTP/AVP".to_owned(),
+                    fmt: m.payload_type.to_string(),
+                    media_title: None,
+                    connections: Vec::new(),
+                    bandwidths: Vec::new(),
+                    key: None,
+                    attributes,
+                }
+            })
+            .collect(),
+    };
+    let mut out = Vec::new();
+    session
+        .write(&mut out)
+        .expect("writing to a Vec is infallible");
+    out.into()
+}
@@ -0,0 +1,1164 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! RTSP server: serve frames from caller-supplied [`Source`]s via [`Server`].
+//!
+//! Each [`Source`] is a presentation of one or more streams which the caller
+//! feeds with frames, such as those received from a camera via
+//! [`crate::client::Session`]. The server packetizes these frames separately
+//! for each client session, so each gets its own SSRC, sequence numbers, and
+//! RTP timestamps.
+//!
+//! ```no_run
+//! # async fn f(params: retina::codec::VideoParameters) -> Result<(), retina::Error> {
+//! use retina::codec::ParametersRef;
+//! use retina::server::{Server, ServerOptions, Source};
+//!
+//! let source = Source::new([ParametersRef::Video(&params)])?;
+//! let server = Server::new(ServerOptions::default());
+//! server.mount("/camera", source.clone());
+//!
+//! // Elsewhere, feed the source with frames via `Source::push_video`.
+//! server.listen("0.0.0.0:8554".parse().unwrap()).await
+//! # }
+//! ```
+//!
+//! Currently there are some limitations:
+//!
+//! *   Only H.264 video streams are supported.
+//! *   Only RTSP/1.0 is supported, without authentication, `PAUSE`, or RTCP
+//!     sender reports.
+//! *   Each session belongs to the connection which created it. It ends on
+//!     `TEARDOWN` or when that connection closes.
+//!
+//! Sources cache the frames since each stream's most recent random access
+//! point, so a session starts with a burst of these frames rather than
+//! waiting for the next random access point.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use log::{debug, trace, warn};
+use rtsp_types::{headers, Data, Message, Method, Request, Response, StatusCode, Version};
+use tokio::sync::broadcast;
+use url::Url;
+
+use crate::client::channel_mapping::ChannelMappings;
+use crate::codec::{ParametersRef, MAX_PAYLOAD_SIZE};
+use crate::runtime::{AsyncTcpListener, AsyncUdpSocket, TcpListener};
+use crate::sdp::{self, MediaDescription};
+use crate::{Error, ErrorInt, Timestamp};
+
+mod proxy;
+
+pub use self::proxy::{Proxy, ProxyOptions};
+
+/// The dynamic RTP payload type used for every stream.
+const PAYLOAD_TYPE: u8 = 96;
+
+/// The number of frames buffered for each playing session. A session which
+/// falls further behind skips ahead to the next random access point.
+const FRAME_QUEUE_LEN: usize = 64;
+
+/// The maximum total size of frames a [`Source`] caches for sessions which
+/// start playing mid-stream. If a stream's group of pictures is larger, these
+/// sessions instead wait for the next random access point.
+const MAX_CACHE_BYTES: usize = 8 << 20;
+
+/// The session timeout advertised in `Session` headers.
+const SESSION_TIMEOUT_SEC: u32 = 60;
+
+const PUBLIC_METHODS: &str = "OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN, GET_PARAMETER";
+
+/// Options for a [`Server`].
+#[derive(Clone, Default)]
+pub struct ServerOptions {
+    server_name: Option<Box<str>>,
+}
+
+impl ServerOptions {
+    /// Sends the given `Server` header with each response.
+    pub fn server_name(mut self, server_name: String) -> Self {
+        self.server_name = if server_name.is_empty() {
+            None
+        } else {
+            Some(server_name.into_boxed_str())
+        };
+        self
+    }
+}
+
+/// A presentation served by a [`Server`], fed with frames by the caller.
+///
+/// Cloning a `Source` is cheap; clones refer to the same presentation.
+#[derive(Clone)]
+pub struct Source(Arc<SourceInner>);
+
+struct SourceInner {
+    streams: Vec<MediaDescription>,
+    frames: broadcast::Sender<SourceFrame>,
+    state: Mutex<SourceState>,
+}
+
+struct SourceState {
+    /// The most recently pushed timestamp of each stream, used to supply
+    /// `rtptime` in `PLAY` responses.
+    latest_timestamps: Vec<Option<i64>>,
+
+    /// The frames since each stream's most recent random access point, in the
+    /// order they were pushed.
+    cache: Vec<SourceFrame>,
+    cache_bytes: usize,
+
+    /// True for each stream whose most recent random access point is in `cache`.
+    cached: Vec<bool>,
+}
+
+impl SourceState {
+    fn uncache(&mut self, stream_id: usize) {
+        let mut removed = 0;
+        self.cache.retain(|f| {
+            let keep = f.stream_id != stream_id;
+            if !keep {
+                removed += f.data.len();
+            }
+            keep
+        });
+        self.cache_bytes -= removed;
+        self.cached[stream_id] = false;
+    }
+}
+
+#[derive(Clone)]
+struct SourceFrame {
+    stream_id: usize,
+    timestamp: i64,
+    is_random_access_point: bool,
+    data: Bytes,
+}
+
+impl Source {
+    /// Creates a source with the given streams, in order.
+    ///
+    /// Currently only H.264 video parameters are supported. Stream ids as
+    /// passed to [`Source::push_video`] are indices into `streams`.
+    pub fn new<'a>(streams: impl IntoIterator<Item = ParametersRef<'a>>) -> Result<Self, Error> {
+        let streams = streams
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| match p {
+                ParametersRef::Video(v) if v.rfc6381_codec().starts_with("avc1.") => {
+                    MediaDescription::new(p, PAYLOAD_TYPE, format!("trackID={i}"))
+                        .map_err(|e| wrap!(ErrorInt::InvalidArgument(format!("stream {i}: {e}"))))
+                }
+                _ => Err(wrap!(ErrorInt::InvalidArgument(format!(
+                    "stream {i}: only H.264 video is supported"
+                )))),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if streams.is_empty() {
+            bail!(ErrorInt::InvalidArgument("source has no streams".into()));
+        }
+        let (frames, _) = broadcast::channel(FRAME_QUEUE_LEN);
+        let state = Mutex::new(SourceState {
+            latest_timestamps: vec![None; streams.len()],
+            cache: Vec::new(),
+            cache_bytes: 0,
+            cached: vec![false; streams.len()],
+        });
+        Ok(Source(Arc::new(SourceInner {
+            streams,
+            frames,
+            state,
+        })))
+    }
+
+    /// Sends a video frame to all sessions playing the given stream.
+    ///
+    /// `data` should be in the same form as [`crate::codec::VideoFrame::data`]:
+    /// for H.264, NAL units each preceded by a 4-byte big-endian length.
+    /// `timestamp` must use the stream's clock rate (90 kHz for H.264). Its
+    /// start is ignored; each session uses its own random RTP timestamp offset.
+    ///
+    /// This never blocks. Frames since the stream's most recent random access
+    /// point are cached for sessions which start playing later; others are
+    /// dropped if no session is playing.
+    pub fn push_video(
+        &self,
+        stream_id: usize,
+        timestamp: Timestamp,
+        is_random_access_point: bool,
+        data: Bytes,
+    ) -> Result<(), Error> {
+        let stream = self.0.streams.get(stream_id).ok_or_else(|| {
+            wrap!(ErrorInt::InvalidArgument(format!(
+                "no stream {stream_id}; source has {}",
+                self.0.streams.len()
+            )))
+        })?;
+        if timestamp.clock_rate().get() != stream.clock_rate {
+            bail!(ErrorInt::InvalidArgument(format!(
+                "timestamp has clock rate {}; stream {} expects {}",
+                timestamp.clock_rate(),
+                stream_id,
+                stream.clock_rate
+            )));
+        }
+        let frame = SourceFrame {
+            stream_id,
+            timestamp: timestamp.timestamp(),
+            is_random_access_point,
+            data,
+        };
+
+        // Update the cache and send while holding the lock, so that
+        // `subscribe` sees each frame in exactly one of the two.
+        let mut state = self.0.state.lock().unwrap();
+        state.latest_timestamps[stream_id] = Some(frame.timestamp);
+        if is_random_access_point {
+            state.uncache(stream_id);
+            state.cached[stream_id] = true;
+        }
+        if state.cached[stream_id] {
+            if state.cache_bytes + frame.data.len() > MAX_CACHE_BYTES {
+                debug!(
+                    "Group of pictures for stream {} exceeds {} bytes; not caching",
+                    stream_id, MAX_CACHE_BYTES
+                );
+                state.uncache(stream_id);
+            } else {
+                state.cache_bytes += frame.data.len();
+                state.cache.push(frame.clone());
+            }
+        }
+
+        // An error here just means no session is currently playing.
+        let _ = self.0.frames.send(frame);
+        Ok(())
+    }
+
+    /// Returns the cached frames and a receiver for subsequent frames.
+    fn subscribe(&self) -> (VecDeque<SourceFrame>, broadcast::Receiver<SourceFrame>) {
+        let state = self.0.state.lock().unwrap();
+        (
+            state.cache.iter().cloned().collect(),
+            self.0.frames.subscribe(),
+        )
+    }
+
+    /// Returns the number of streams.
+    pub fn num_streams(&self) -> usize {
+        self.0.streams.len()
+    }
+}
+
+impl std::fmt::Debug for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Source")
+            .field("streams", &self.0.streams)
+            .field("sessions", &self.0.frames.receiver_count())
+            .finish()
+    }
+}
+
+/// An RTSP server, serving [`Source`]s at mounted paths.
+///
+/// Cloning a `Server` is cheap; clones share the same mounts.
+#[derive(Clone)]
+pub struct Server(Arc<ServerInner>);
+
+struct ServerInner {
+    options: ServerOptions,
+    mounts: Mutex<HashMap<String, Source>>,
+}
+
+/// Normalizes a URL path to have a single leading `/` and no trailing `/`,
+/// except that the root is `/`.
+fn normalize_path(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+impl Server {
+    pub fn new(options: ServerOptions) -> Self {
+        Server(Arc::new(ServerInner {
+            options,
+            mounts: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// Serves `source` at `path`, such as `/camera`, returning the source
+    /// previously mounted there, if any.
+    ///
+    /// Leading and trailing slashes in `path` are ignored. Sessions which are
+    /// already playing a replaced source continue to play it.
+    pub fn mount(&self, path: &str, source: Source) -> Option<Source> {
+        self.0
+            .mounts
+            .lock()
+            .unwrap()
+            .insert(normalize_path(path), source)
+    }
+
+    /// Stops serving the source at `path`, returning it if present.
+    ///
+    /// Sessions which are already playing it continue to play it.
+    pub fn unmount(&self, path: &str) -> Option<Source> {
+        self.0.mounts.lock().unwrap().remove(&normalize_path(path))
+    }
+
+    /// Listens for TCP connections on `addr`, serving each in the background.
+    ///
+    /// This only returns if unable to listen on `addr`.
+    pub async fn listen(&self, addr: SocketAddr) -> Result<(), Error> {
+        let listener = <TcpListener as AsyncTcpListener>::bind(addr)
+            .await
+            .map_err(|e| wrap!(ErrorInt::ListenError(e)))?;
+        debug!(
+            "Listening for RTSP connections on {:?}",
+            listener.local_addr()
+        );
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // Likely a transient error such as running out of file
+                    // descriptors. Back off briefly rather than spinning.
+                    warn!("Unable to accept RTSP connection: {}", e);
+                    crate::runtime::sleep(std::time::Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let conn = match crate::net::Connection::from_stream(stream) {
+                Ok(c) => c,
+                Err(e) => {
+                    debug!("Unable to set up accepted RTSP connection: {}", e);
+                    continue;
+                }
+            };
+            let inner = self.0.clone();
+            crate::runtime::spawn(async move {
+                let peer_addr = conn.ctx().peer_addr;
+                if let Err(e) = ServerConnection::new(inner, conn).run().await {
+                    debug!("RTSP connection from {} failed: {}", peer_addr, e);
+                }
+            });
+        }
+    }
+
+    /// Serves a single connection over a caller-supplied byte stream, such as
+    /// one accepted from a Unix socket, until the peer closes it.
+    ///
+    /// `local_addr` and `peer_addr` are used as with a TCP connection: for
+    /// error messages, for the SDP origin, and as the addresses for UDP
+    /// transport. They're otherwise unused.
+    pub async fn serve_connection<S>(
+        &self,
+        stream: S,
+        local_addr: SocketAddr,
+        peer_addr: SocketAddr,
+    ) -> Result<(), Error>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + Unpin + 'static,
+    {
+        let conn =
+            crate::net::Connection::from_caller_stream(Box::new(stream), local_addr, peer_addr);
+        ServerConnection::new(self.0.clone(), conn).run().await
+    }
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("mounts", &*self.0.mounts.lock().unwrap())
+            .finish()
+    }
+}
+
+/// A transport requested in a `SETUP` request's `Transport` header.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum RequestedTransport {
+    Tcp { channel_id: Option<u8> },
+    Udp { rtp_port: u16, rtcp_port: u16 },
+}
+
+/// Parses a `Transport` header, returning the first supported alternative.
+///
+/// See [RFC 2326 section 12.39](https://datatracker.ietf.org/doc/html/rfc2326#section-12.39).
+pub(crate) fn parse_transport(header: &str) -> Option<RequestedTransport> {
+    header.split(',').find_map(|spec| {
+        let mut parts = spec.split(';');
+        let tcp = match parts.next()?.trim() {
+            "RTP/AVP" | "RTP/AVP/UDP" => false,
+            "RTP/AVP/TCP" => true,
+            _ => return None,
+        };
+        let mut channel_id = None;
+        let mut ports = None;
+        for part in parts {
+            match part.trim().split_once('=') {
+                None if part.trim() == "multicast" => return None,
+                None => {}
+                Some(("interleaved", v)) => {
+                    let n = v
+                        .split('-')
+                        .next()
+                        .expect("split returns at least one part");
+                    channel_id = Some(u8::from_str_radix(n, 10).ok()?);
+                }
+                Some(("client_port", v)) => {
+                    let mut v = v.splitn(2, '-');
+                    let rtp = v.next().expect("splitn returns at least one part");
+                    let rtp_port = u16::from_str_radix(rtp, 10).ok()?;
+                    let rtcp_port = match v.next() {
+                        Some(rtcp) => u16::from_str_radix(rtcp, 10).ok()?,
+                        None => rtp_port.checked_add(1)?,
+                    };
+                    ports = Some((rtp_port, rtcp_port));
+                }
+                Some(("mode", v)) if !v.trim_matches('"').eq_ignore_ascii_case("play") => {
+                    return None
+                }
+                Some(_) => {}
+            }
+        }
+        if tcp {
+            Some(RequestedTransport::Tcp { channel_id })
+        } else {
+            let (rtp_port, rtcp_port) = ports?;
+            Some(RequestedTransport::Udp {
+                rtp_port,
+                rtcp_port,
+            })
+        }
+    })
+}
+
+/// A session, as created by `SETUP`.
+struct ServerSession {
+    id: Box<str>,
+
+    /// The normalized path at which `source` was mounted.
+    path: String,
+    source: Source,
+    channels: ChannelMappings,
+
+    /// The set-up streams, indexed by stream id within `source`.
+    streams: Vec<Option<SessionStream>>,
+
+    /// Frames to send, once playing: first cached frames, then new frames.
+    backlog: VecDeque<SourceFrame>,
+    frames: Option<broadcast::Receiver<SourceFrame>>,
+}
+
+struct SessionStream {
+    transport: SessionTransport,
+    packetizer: crate::codec::h264::Packetizer,
+    ssrc: u32,
+    initial_seq: u16,
+
+    /// Added to the source's timestamps to produce RTP timestamps.
+    timestamp_offset: u32,
+
+    /// True iff a random access point has been sent, so that subsequent
+    /// frames can be decoded.
+    started: bool,
+}
+
+impl SessionStream {
+    fn rtp_timestamp(&self, timestamp: i64) -> u32 {
+        (timestamp as u32).wrapping_add(self.timestamp_offset)
+    }
+}
+
+enum SessionTransport {
+    Tcp {
+        channel_id: u8,
+    },
+    Udp {
+        sockets: crate::net::UdpPair,
+        rtp_addr: SocketAddr,
+    },
+}
+
+enum Event {
+    Message(Option<Result<crate::ReceivedMessage, Error>>),
+    Frame(Result<SourceFrame, broadcast::error::RecvError>),
+}
+
+/// A single RTSP connection, with at most one session.
+struct ServerConnection {
+    server: Arc<ServerInner>,
+    conn: crate::net::Connection,
+    session: Option<ServerSession>,
+}
+
+impl ServerConnection {
+    fn new(server: Arc<ServerInner>, conn: crate::net::Connection) -> Self {
+        ServerConnection {
+            server,
+            conn,
+            session: None,
+        }
+    }
+
+    async fn run(mut self) -> Result<(), Error> {
+        loop {
+            if let Some(frame) = self.session.as_mut().and_then(|s| s.backlog.pop_front()) {
+                self.send_frame(frame).await?;
+                continue;
+            }
+            let event = match self.session.as_mut().and_then(|s| s.frames.as_mut()) {
+                Some(frames) => tokio::select! {
+                    biased;
+                    msg = self.conn.next() => Event::Message(msg),
+                    frame = frames.recv() => Event::Frame(frame),
+                },
+                None => Event::Message(self.conn.next().await),
+            };
+            match event {
+                Event::Message(None) => return Ok(()),
+                Event::Message(Some(msg)) => match msg?.msg {
+                    Message::Request(req) => {
+                        let resp = self.handle_request(&req);
+                        self.conn
+                            .send(Message::Response(resp))
+                            .await
+                            .map_err(|e| wrap!(e))?;
+                    }
+                    Message::Data(data) => {
+                        // Most likely an RTCP receiver report; ignore it.
+                        trace!(
+                            "Ignoring {}-byte data message on channel {}",
+                            data.as_slice().len(),
+                            data.channel_id()
+                        );
+                    }
+                    Message::Response(r) => {
+                        debug!("Ignoring unexpected {} response", u16::from(r.status()));
+                    }
+                },
+                Event::Frame(Ok(frame)) => self.send_frame(frame).await?,
+                Event::Frame(Err(broadcast::error::RecvError::Lagged(n))) => {
+                    warn!(
+                        "RTSP session to {} fell {} frames behind; skipping to next random access point",
+                        self.conn.ctx().peer_addr,
+                        n
+                    );
+                    if let Some(session) = &mut self.session {
+                        for s in session.streams.iter_mut().flatten() {
+                            s.started = false;
+                        }
+                    }
+                }
+                Event::Frame(Err(broadcast::error::RecvError::Closed)) => return Ok(()),
+            }
+        }
+    }
+
+    /// Packetizes a frame and sends it to the session, if it's playing the stream.
+    async fn send_frame(&mut self, frame: SourceFrame) -> Result<(), Error> {
+        let session = match self.session.as_mut() {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        let stream = match session.streams.get_mut(frame.stream_id) {
+            Some(Some(s)) => s,
+            _ => return Ok(()),
+        };
+        if !stream.started {
+            if !frame.is_random_access_point {
+                return Ok(());
+            }
+            stream.started = true;
+        }
+        let clock_rate = NonZeroU32::new(session.source.0.streams[frame.stream_id].clock_rate)
+            .expect("clock rate is non-zero");
+        let timestamp = Timestamp::new(
+            i64::from(stream.rtp_timestamp(frame.timestamp)),
+            clock_rate,
+            0,
+        )
+        .expect("non-negative timestamp with zero start is valid");
+        stream.packetizer.push(timestamp, frame.data)?;
+        loop {
+            let pkt = match stream.packetizer.pull() {
+                Ok(Some(pkt)) => pkt.raw.0,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!(
+                        "Unable to packetize frame for stream {}: {}",
+                        frame.stream_id, e
+                    );
+                    stream.started = false;
+                    break;
+                }
+            };
+            match &stream.transport {
+                SessionTransport::Tcp { channel_id } => {
+                    self.conn
+                        .feed(Message::Data(Data::new(*channel_id, pkt)))
+                        .await
+                        .map_err(|e| wrap!(e))?;
+                }
+                SessionTransport::Udp { sockets, rtp_addr } => {
+                    let r = futures::future::poll_fn(|cx| {
+                        sockets.rtp_socket.poll_send_to(cx, &pkt[..], *rtp_addr)
+                    })
+                    .await;
+                    if let Err(e) = r {
+                        // UDP is lossy anyway; keep going.
+                        debug!("Unable to send RTP packet to {}: {}", rtp_addr, e);
+                    }
+                }
+            }
+        }
+        self.conn.flush().await.map_err(|e| wrap!(e))
+    }
+
+    /// Returns a response with the headers common to all responses.
+    fn response(&self, req: &Request<Bytes>, status: StatusCode, body: Bytes) -> Response<Bytes> {
+        let mut resp = Response::builder(Version::V1_0, status).build(body);
+        if let Some(cseq) = req.header(&headers::CSEQ) {
+            resp.insert_header(headers::CSEQ, cseq.as_str());
+        }
+        if let Some(server_name) = &self.server.options.server_name {
+            resp.insert_header(headers::SERVER, &**server_name);
+        }
+        resp
+    }
+
+    fn error(&self, req: &Request<Bytes>, status: StatusCode) -> Response<Bytes> {
+        self.response(req, status, Bytes::new())
+    }
+
+    /// Checks the request's `Session` header against this connection's
+    /// session, returning true iff it names that session.
+    fn check_session(&self, req: &Request<Bytes>) -> Result<bool, StatusCode> {
+        match (req.header(&headers::SESSION), &self.session) {
+            (None, _) => Ok(false),
+            (Some(h), Some(s))
+                if h.as_str()
+                    .split(';')
+                    .next()
+                    .expect("split returns at least one part")
+                    .trim()
+                    == &*s.id =>
+            {
+                Ok(true)
+            }
+            (Some(_), _) => Err(StatusCode::SessionNotFound),
+        }
+    }
+
+    /// Looks up the source and, for stream URLs, the stream id for `url`.
+    fn resolve(&self, url: &Url) -> Option<(String, Source, Option<usize>)> {
+        let path = normalize_path(url.path());
+        let mounts = self.server.mounts.lock().unwrap();
+        if let Some(source) = mounts.get(&path) {
+            return Some((path, source.clone(), None));
+        }
+        let (base, last) = path.rsplit_once('/')?;
+        let stream_id = usize::from_str_radix(last.strip_prefix("trackID=")?, 10).ok()?;
+        let base = normalize_path(base);
+        let source = mounts.get(&base)?;
+        if stream_id >= source.0.streams.len() {
+            return None;
+        }
+        Some((base, source.clone(), Some(stream_id)))
+    }
+
+    fn handle_request(&mut self, req: &Request<Bytes>) -> Response<Bytes> {
+        trace!(
+            "{} request from {}",
+            Into::<&str>::into(req.method()),
+            self.conn.ctx().peer_addr
+        );
+        if req.version() != Version::V1_0 {
+            return self.error(req, StatusCode::RTSPVersionNotSupported);
+        }
+        if req.header(&headers::CSEQ).is_none() {
+            return self.error(req, StatusCode::BadRequest);
+        }
+        let has_session = match self.check_session(req) {
+            Ok(s) => s,
+            Err(status) => return self.error(req, status),
+        };
+        match req.method() {
+            Method::Options => {
+                let mut resp = self.response(req, StatusCode::Ok, Bytes::new());
+                resp.insert_header(headers::PUBLIC, PUBLIC_METHODS);
+                resp
+            }
+            Method::GetParameter => {
+                let mut resp = self.response(req, StatusCode::Ok, Bytes::new());
+                if has_session {
+                    resp.insert_header(headers::SESSION, self.session_header());
+                }
+                resp
+            }
+            Method::Describe => self.describe(req),
+            Method::Setup => self.setup(req, has_session),
+            Method::Play if has_session => self.play(req),
+            Method::Teardown if has_session => {
+                self.session = None;
+                self.response(req, StatusCode::Ok, Bytes::new())
+            }
+            Method::Play | Method::Teardown => self.error(req, StatusCode::SessionNotFound),
+            _ => {
+                let mut resp = self.response(req, StatusCode::NotImplemented, Bytes::new());
+                resp.insert_header(headers::PUBLIC, PUBLIC_METHODS);
+                resp
+            }
+        }
+    }
+
+    fn session_header(&self) -> String {
+        let session = self.session.as_ref().expect("session exists");
+        format!("{};timeout={}", session.id, SESSION_TIMEOUT_SEC)
+    }
+
+    fn describe(&self, req: &Request<Bytes>) -> Response<Bytes> {
+        let url = match req.request_uri() {
+            Some(u) => u,
+            None => return self.error(req, StatusCode::BadRequest),
+        };
+        let source = match self.resolve(url) {
+            Some((_, source, None)) => source,
+            _ => return self.error(req, StatusCode::NotFound),
+        };
+        let mut base_url = url.clone();
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        let body = sdp::write(
+            rand::random::<u32>().into(),
+            self.conn.ctx().local_addr.ip(),
+            &source.0.streams,
+        );
+        let mut resp = self.response(req, StatusCode::Ok, body);
+        resp.insert_header(headers::CONTENT_BASE, base_url.as_str());
+        resp.insert_header(headers::CONTENT_TYPE, "application/sdp");
+        resp
+    }
+
+    fn setup(&mut self, req: &Request<Bytes>, has_session: bool) -> Response<Bytes> {
+        let (path, source, stream_id) = match req.request_uri().and_then(|u| self.resolve(u)) {
+            Some((path, source, Some(stream_id))) => (path, source, stream_id),
+            _ => return self.error(req, StatusCode::NotFound),
+        };
+        let requested = match req
+            .header(&headers::TRANSPORT)
+            .and_then(|t| parse_transport(t.as_str()))
+        {
+            Some(t) => t,
+            None => return self.error(req, StatusCode::UnsupportedTransport),
+        };
+        match &self.session {
+            // Each of the session's streams may be set up once, before `PLAY`.
+            Some(s)
+                if has_session
+                    && s.path == path
+                    && s.frames.is_none()
+                    && s.streams[stream_id].is_none() => {}
+
+            // Only one session per connection is supported.
+            Some(_) => return self.error(req, StatusCode::MethodNotValidInThisState),
+            None => {}
+        }
+        // A new session is only kept if the SETUP succeeds.
+        let mut new_session = None;
+        let session = match &mut self.session {
+            Some(s) => s,
+            None => new_session.insert(ServerSession {
+                id: format!("{:016X}", rand::random::<u64>()).into_boxed_str(),
+                path,
+                streams: (0..source.0.streams.len()).map(|_| None).collect(),
+                source,
+                channels: ChannelMappings::default(),
+                backlog: VecDeque::new(),
+                frames: None,
+            }),
+        };
+        let ssrc = rand::random();
+        let (transport, transport_header) = match requested {
+            RequestedTransport::Tcp { channel_id } => {
+                let channel_id = match channel_id.or_else(|| session.channels.next_unassigned()) {
+                    Some(c) => c,
+                    None => return self.error(req, StatusCode::UnsupportedTransport),
+                };
+                if let Err(e) = session.channels.assign(channel_id, stream_id) {
+                    debug!("Rejecting SETUP: {}", e);
+                    return self.error(req, StatusCode::UnsupportedTransport);
+                }
+                (
+                    SessionTransport::Tcp { channel_id },
+                    format!(
+                        "RTP/AVP/TCP;unicast;interleaved={}-{};ssrc={:08X}",
+                        channel_id,
+                        channel_id + 1,
+                        ssrc
+                    ),
+                )
+            }
+            RequestedTransport::Udp {
+                rtp_port,
+                rtcp_port,
+            } => {
+                let local_ip = self.conn.ctx().local_addr.ip();
+                let pair = match crate::UdpPair::for_ip(local_ip) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        warn!("Unable to bind UDP ports on {}: {}", local_ip, e);
+                        return self.error(req, StatusCode::InternalServerError);
+                    }
+                };
+                let server_port = pair.rtp_port;
+                let sockets = match crate::net::UdpPair::from_std(pair) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        warn!("Unable to register UDP sockets: {}", e);
+                        return self.error(req, StatusCode::InternalServerError);
+                    }
+                };
+                (
+                    SessionTransport::Udp {
+                        sockets,
+                        rtp_addr: SocketAddr::new(self.conn.ctx().peer_addr.ip(), rtp_port),
+                    },
+                    format!(
+                        "RTP/AVP;unicast;client_port={}-{};server_port={}-{};ssrc={:08X}",
+                        rtp_port,
+                        rtcp_port,
+                        server_port,
+                        server_port + 1,
+                        ssrc
+                    ),
+                )
+            }
+        };
+        let initial_seq = rand::random();
+        session.streams[stream_id] = Some(SessionStream {
+            transport,
+            packetizer: crate::codec::h264::Packetizer::new(
+                MAX_PAYLOAD_SIZE,
+                stream_id,
+                initial_seq,
+                PAYLOAD_TYPE,
+                ssrc,
+            )
+            .expect("MAX_PAYLOAD_SIZE is valid"),
+            ssrc,
+            initial_seq,
+            timestamp_offset: rand::random(),
+            started: false,
+        });
+        if new_session.is_some() {
+            self.session = new_session;
+        }
+        let mut resp = self.response(req, StatusCode::Ok, Bytes::new());
+        resp.insert_header(headers::SESSION, self.session_header());
+        resp.insert_header(headers::TRANSPORT, transport_header);
+        resp
+    }
+
+    fn play(&mut self, req: &Request<Bytes>) -> Response<Bytes> {
+        let url = match req.request_uri() {
+            Some(u) => u.clone(),
+            None => return self.error(req, StatusCode::BadRequest),
+        };
+        let session = self.session.as_mut().expect("has_session implies session");
+        if session.streams.iter().all(Option::is_none) {
+            return self.error(req, StatusCode::MethodNotValidInThisState);
+        }
+        if session.frames.is_none() {
+            let (backlog, frames) = session.source.subscribe();
+            session.backlog = backlog;
+            session.frames = Some(frames);
+            debug!(
+                "Session {} to {} is playing {} with ssrcs {:08x?}",
+                session.id,
+                self.conn.ctx().peer_addr,
+                session.path,
+                session
+                    .streams
+                    .iter()
+                    .flatten()
+                    .map(|s| s.ssrc)
+                    .collect::<Vec<_>>()
+            );
+        }
+        let latest_timestamps = session
+            .source
+            .0
+            .state
+            .lock()
+            .unwrap()
+            .latest_timestamps
+            .clone();
+        let mut rtp_info = String::new();
+        for (i, stream) in session.streams.iter().enumerate() {
+            let stream = match stream {
+                Some(s) => s,
+                None => continue,
+            };
+            let mut stream_url = url.clone();
+            stream_url.set_path(&format!(
+                "{}/{}",
+                session.path.trim_end_matches('/'),
+                session.source.0.streams[i].control
+            ));
+            if !rtp_info.is_empty() {
+                rtp_info.push(',');
+            }
+            rtp_info.push_str(&format!("url={};seq={}", stream_url, stream.initial_seq));
+
+            // The first frame sent will be the first cached one, if any.
+            let first = session
+                .backlog
+                .iter()
+                .find(|f| f.stream_id == i)
+                .map(|f| f.timestamp)
+                .or(latest_timestamps[i]);
+            if let Some(t) = first {
+                rtp_info.push_str(&format!(";rtptime={}", stream.rtp_timestamp(t)));
+            }
+        }
+        let mut resp = self.response(req, StatusCode::Ok, Bytes::new());
+        resp.insert_header(headers::SESSION, self.session_header());
+        resp.insert_header(headers::RANGE, "npt=now-");
+        resp.insert_header(headers::RTP_INFO, rtp_info);
+        resp
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+    use crate::client::{PlayOptions, SessionOptions, SetupOptions, Transport};
+    use crate::codec::CodecItem;
+    use crate::testutil::init_logging;
+
+    const FMTP: &str = "packetization-mode=1;profile-level-id=640033;sprop-parameter-sets=Z2QAM6wVFKCgL/lQ,aO48sA==";
+
+    fn video_parameters() -> crate::codec::VideoParameters {
+        let d = crate::codec::Depacketizer::new("video", "h264", 90_000, None, Some(FMTP)).unwrap();
+        match d.parameters() {
+            Some(ParametersRef::Video(v)) => v.clone(),
+            o => panic!("unexpected parameters {o:?}"),
+        }
+    }
+
+    pub(crate) fn source() -> Source {
+        Source::new([ParametersRef::Video(&video_parameters())]).unwrap()
+    }
+
+    /// Returns an access unit with a single IDR slice NAL of the given length.
+    pub(crate) fn idr_frame(nal_len: usize) -> Bytes {
+        let mut data = Vec::with_capacity(4 + nal_len);
+        data.extend_from_slice(&u32::try_from(nal_len).unwrap().to_be_bytes());
+        data.push(0x65);
+        data.extend((1..nal_len).map(|i| i as u8));
+        data.into()
+    }
+
+    #[test]
+    fn transport() {
+        assert_eq!(
+            parse_transport("RTP/AVP/TCP;unicast;interleaved=2-3"),
+            Some(RequestedTransport::Tcp {
+                channel_id: Some(2)
+            })
+        );
+        assert_eq!(
+            parse_transport("RTP/AVP/TCP;unicast"),
+            Some(RequestedTransport::Tcp { channel_id: None })
+        );
+        assert_eq!(
+            parse_transport("RTP/AVP/UDP;unicast;client_port=5000-5001"),
+            Some(RequestedTransport::Udp {
+                rtp_port: 5000,
+                rtcp_port: 5001
+            })
+        );
+        assert_eq!(
+            parse_transport(
+                "RTP/SAVP/UDP;unicast;client_port=5000-5001,RTP/AVP;unicast;client_port=6000"
+            ),
+            Some(RequestedTransport::Udp {
+                rtp_port: 6000,
+                rtcp_port: 6001
+            })
+        );
+        assert_eq!(parse_transport("RTP/AVP;multicast;port=5000-5001"), None);
+        assert_eq!(
+            parse_transport("RTP/AVP/TCP;unicast;interleaved=0-1;mode=record"),
+            None
+        );
+        assert_eq!(parse_transport("RTP/AVP;unicast"), None);
+    }
+
+    /// Plays `/camera` via the Retina client over an in-memory pipe or UDP.
+    pub(crate) async fn start(
+        server: &Server,
+        transport: Transport,
+    ) -> (
+        crate::client::Demuxed,
+        tokio::task::JoinHandle<Result<(), Error>>,
+    ) {
+        let (client, server_stream) = tokio::io::duplex(1 << 16);
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 554);
+        let server_task = tokio::spawn({
+            let server = server.clone();
+            async move { server.serve_connection(server_stream, addr, addr).await }
+        });
+        let mut session = crate::client::Session::describe_with_stream(
+            client,
+            addr,
+            addr,
+            Url::parse("rtsp://127.0.0.1/camera").unwrap(),
+            SessionOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(session.streams().len(), 1);
+        assert_eq!(
+            session.streams()[0].parameters(),
+            Some(ParametersRef::Video(&video_parameters()))
+        );
+        session
+            .setup(0, SetupOptions::default().transport(transport))
+            .await
+            .unwrap();
+        let session = session
+            .play(PlayOptions::default())
+            .await
+            .unwrap()
+            .demuxed()
+            .unwrap();
+        (session, server_task)
+    }
+
+    async fn play(transport: Transport) {
+        init_logging();
+        let source = source();
+        let server = Server::new(ServerOptions::default().server_name("test".to_owned()));
+        assert!(server.mount("/camera/", source.clone()).is_none());
+        let (mut session, server_task) = start(&server, transport).await;
+
+        // The first frame isn't a random access point, so it's skipped. The
+        // second is large enough to need fragmenting.
+        let clock_rate = NonZeroU32::new(90_000).unwrap();
+        let big = idr_frame(5000);
+        source
+            .push_video(
+                0,
+                Timestamp::new(0, clock_rate, 0).unwrap(),
+                false,
+                idr_frame(10),
+            )
+            .unwrap();
+        source
+            .push_video(
+                0,
+                Timestamp::new(3000, clock_rate, 0).unwrap(),
+                true,
+                big.clone(),
+            )
+            .unwrap();
+        match session.next().await {
+            Some(Ok(CodecItem::VideoFrame(f))) => {
+                assert!(f.is_random_access_point());
+                assert_eq!(f.data(), &big[..]);
+            }
+            o => panic!("unexpected item {o:#?}"),
+        }
+
+        // Dropping the session sends a TEARDOWN, and the server keeps the
+        // connection open until the client closes it.
+        drop(session);
+        server_task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn play_tcp() {
+        play(Transport::Tcp(Default::default())).await;
+    }
+
+    #[tokio::test]
+    async fn play_udp() {
+        play(Transport::Udp(Default::default())).await;
+    }
+
+    /// A session which starts mid-stream gets the frames since the most recent
+    /// random access point.
+    #[tokio::test]
+    async fn late_joiner() {
+        init_logging();
+        let source = source();
+        let server = Server::new(ServerOptions::default());
+        server.mount("/camera", source.clone());
+        let clock_rate = NonZeroU32::new(90_000).unwrap();
+        let mut non_idr = idr_frame(20).to_vec();
+        non_idr[4] = 0x41;
+        let frames = [
+            (0, true, idr_frame(30)),
+            (3000, true, idr_frame(10)),
+            (6000, false, Bytes::from(non_idr)),
+        ];
+        for (ts, rap, data) in &frames {
+            source
+                .push_video(
+                    0,
+                    Timestamp::new(*ts, clock_rate, 0).unwrap(),
+                    *rap,
+                    data.clone(),
+                )
+                .unwrap();
+        }
+        let (mut session, server_task) = start(&server, Transport::Tcp(Default::default())).await;
+        for (_, rap, data) in &frames[1..] {
+            match session.next().await {
+                Some(Ok(CodecItem::VideoFrame(f))) => {
+                    assert_eq!(f.is_random_access_point(), *rap);
+                    assert_eq!(f.data(), &data[..]);
+                }
+                o => panic!("unexpected item {o:#?}"),
+            }
+        }
+        drop(session);
+        server_task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        init_logging();
+        let server = Server::new(ServerOptions::default());
+        server.mount("/camera", source());
+        let (client, server_stream) = tokio::io::duplex(1 << 16);
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 554);
+        tokio::spawn(async move { server.serve_connection(server_stream, addr, addr).await });
+        let r = crate::client::Session::describe_with_stream(
+            client,
+            addr,
+            addr,
+            Url::parse("rtsp://127.0.0.1/other").unwrap(),
+            SessionOptions::default(),
+        )
+        .await;
+        match r {
+            Err(e) => match &*e.0 {
+                ErrorInt::RtspResponseError { status, .. } => {
+                    assert_eq!(*status, StatusCode::NotFound)
+                }
+                _ => panic!("unexpected error: {e}"),
+            },
+            Ok(_) => panic!("DESCRIBE of unmounted path succeeded"),
+        }
+    }
+}
@@ -0,0 +1,367 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Restreaming proxy; see [`Proxy`].
+
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use log::{debug, warn};
+use url::Url;
+
+use super::Source;
+use crate::client::{ReconnectItem, ReconnectOptions, Reconnecting, SetupOptions, Stream};
+use crate::codec::CodecItem;
+use crate::{Error, ErrorInt, Timestamp};
+
+/// Options for a [`Proxy`].
+#[derive(Clone, Default)]
+pub struct ProxyOptions {
+    reconnect: ReconnectOptions,
+    setup: SetupOptions,
+}
+
+impl ProxyOptions {
+    /// Uses the given options for the upstream session.
+    pub fn reconnect(mut self, reconnect: ReconnectOptions) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Uses the given options to set up each upstream stream.
+    pub fn setup(mut self, setup: SetupOptions) -> Self {
+        self.setup = setup;
+        self
+    }
+}
+
+/// Relays a single upstream session to any number of downstream sessions.
+///
+/// Many cameras can't handle more than a few simultaneous sessions. A proxy
+/// keeps one upstream session, reconnecting as described at [`Reconnecting`],
+/// and feeds its H.264 video streams into a [`Source`]. Mount this source on a
+/// [`super::Server`] to serve it. Each downstream session gets its own SDP,
+/// SSRC, sequence numbers, and RTP timestamps, and starts at the most recent
+/// random access point.
+///
+/// ```no_run
+/// # async fn f() -> Result<(), retina::Error> {
+/// use retina::server::{Proxy, ProxyOptions, Server, ServerOptions};
+///
+/// let url = url::Url::parse("rtsp://camera/stream").unwrap();
+/// let proxy = Proxy::connect(url, ProxyOptions::default()).await?;
+/// let server = Server::new(ServerOptions::default());
+/// server.mount("/camera", proxy.source().clone());
+/// tokio::spawn(proxy.run());
+/// server.listen("0.0.0.0:8554".parse().unwrap()).await
+/// # }
+/// ```
+///
+/// Output timestamps continue across upstream reconnections, advancing by
+/// the downtime. The downstream SDP is fixed when the proxy connects, so if
+/// the upstream streams change after a reconnection, streams are matched by
+/// position and any extras are dropped.
+pub struct Proxy {
+    upstream: Reconnecting,
+    source: Source,
+
+    /// The source stream id of each upstream stream, if it's relayed.
+    stream_ids: Vec<Option<usize>>,
+
+    /// Indexed by source stream id.
+    timestamps: Vec<StreamTimestamps>,
+}
+
+impl Proxy {
+    /// Connects to `url`, returning once the parameters of each upstream H.264
+    /// video stream are known.
+    ///
+    /// Fails without retrying if the upstream session has no H.264 video
+    /// streams. Otherwise fails only with the final error once [`ReconnectOptions::max_attempts`] is exhausted.
+    pub async fn connect(url: Url, options: ProxyOptions) -> Result<Self, Error> {
+        let setup = options.setup;
+        let mut upstream = Reconnecting::new(url, options.reconnect, move |session| {
+            session
+                .streams()
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.media() == "video" && s.encoding_name() == "h264")
+                .map(|(i, _)| (i, setup.clone()))
+                .collect()
+        });
+        while let Some(item) = upstream.next().await {
+            let item = item?;
+            let streams = upstream.streams().unwrap_or(&[]);
+            let params = match set_up(streams)
+                .map(|(_, s)| s.parameters())
+                .collect::<Option<Vec<_>>>()
+            {
+                Some(p) => p,
+                None => continue, // wait for in-band parameters.
+            };
+            let source = Source::new(params)?;
+            let mut proxy = Proxy {
+                stream_ids: Vec::new(),
+                timestamps: (0..source.num_streams())
+                    .map(|_| StreamTimestamps::default())
+                    .collect(),
+                upstream,
+                source,
+            };
+            proxy.map_streams();
+            proxy.handle(item)?;
+            return Ok(proxy);
+        }
+        bail!(ErrorInt::FailedPrecondition(
+            "upstream session ended before its parameters were known".into()
+        ))
+    }
+
+    /// Returns the source to mount on a [`super::Server`].
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    /// Relays frames until the upstream session fails permanently.
+    pub async fn run(mut self) -> Result<(), Error> {
+        while let Some(item) = self.upstream.next().await {
+            self.handle(item?)?;
+        }
+        Ok(())
+    }
+
+    /// Maps the current upstream session's set-up streams to source streams.
+    fn map_streams(&mut self) {
+        let streams = self.upstream.streams().unwrap_or(&[]);
+        let mut stream_ids = vec![None; streams.len()];
+        let mut n = 0;
+        for (i, _) in set_up(streams) {
+            if n < self.source.num_streams() {
+                stream_ids[i] = Some(n);
+            }
+            n += 1;
+        }
+        if n != self.source.num_streams() {
+            warn!(
+                "Upstream session has {} H.264 streams; proxy has {}",
+                n,
+                self.source.num_streams()
+            );
+        }
+        self.stream_ids = stream_ids;
+    }
+
+    fn handle(&mut self, item: ReconnectItem) -> Result<(), Error> {
+        match item {
+            ReconnectItem::Discontinuity(d) => {
+                debug!(
+                    "Upstream reconnected after {:?}; continuing timestamps",
+                    d.downtime()
+                );
+                self.map_streams();
+                for t in &mut self.timestamps {
+                    t.discontinuity(d.downtime());
+                }
+                Ok(())
+            }
+            ReconnectItem::Codec(CodecItem::VideoFrame(f)) => {
+                let id = match self.stream_ids.get(f.stream_id()) {
+                    Some(Some(id)) => *id,
+                    _ => return Ok(()),
+                };
+                if f.has_new_parameters() {
+                    debug!(
+                        "Upstream stream {} has new parameters; downstream sessions \
+                         rely on in-band parameter sets",
+                        f.stream_id()
+                    );
+                }
+                let timestamp = self.timestamps[id].map(f.timestamp());
+                let is_random_access_point = f.is_random_access_point();
+                self.source.push_video(
+                    id,
+                    timestamp,
+                    is_random_access_point,
+                    Bytes::from(f.into_data()),
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the streams which have been set up, with their upstream indices.
+fn set_up(streams: &[Stream]) -> impl Iterator<Item = (usize, &Stream)> {
+    streams
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.ctx().is_some())
+}
+
+/// Maps one stream's upstream timestamps to source timestamps which continue
+/// across reconnections.
+#[derive(Default)]
+struct StreamTimestamps {
+    /// Added to the upstream session's elapsed time.
+    offset: i64,
+
+    /// The most recent output timestamp and its clock rate.
+    last: Option<(i64, NonZeroU32)>,
+
+    /// The output timestamp of the first frame after a discontinuity.
+    next: Option<i64>,
+}
+
+impl StreamTimestamps {
+    fn map(&mut self, upstream: Timestamp) -> Timestamp {
+        if let Some(next) = self.next.take() {
+            self.offset = next - upstream.elapsed();
+        }
+        let out = upstream.elapsed() + self.offset;
+        self.last = Some((out, upstream.clock_rate()));
+        Timestamp::new(out, upstream.clock_rate(), 0).expect("zero start is valid")
+    }
+
+    fn discontinuity(&mut self, downtime: Duration) {
+        if let Some((last, clock_rate)) = self.last {
+            let ticks = (downtime.as_secs_f64() * f64::from(clock_rate.get())) as i64;
+            self.next = Some(last + std::cmp::max(ticks, 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::SinkExt;
+
+    use super::*;
+    use crate::client::Transport;
+    use crate::runtime::AsyncTcpListener;
+    use crate::server::tests::{idr_frame, source, start};
+    use crate::server::{Server, ServerOptions};
+    use crate::testutil::{init_logging, listen};
+
+    #[test]
+    fn timestamps() {
+        let clock_rate = NonZeroU32::new(90_000).unwrap();
+        let ts = |t, start| Timestamp::new(t, clock_rate, start).unwrap();
+        let mut t = StreamTimestamps::default();
+        assert_eq!(t.map(ts(1_000, 1_000)).timestamp(), 0);
+        assert_eq!(t.map(ts(4_000, 1_000)).timestamp(), 3_000);
+
+        // After a two-second outage, the new session's timestamps continue
+        // from where the old one's left off.
+        t.discontinuity(Duration::from_secs(2));
+        assert_eq!(t.map(ts(50_000, 50_000)).timestamp(), 183_000);
+        assert_eq!(t.map(ts(53_000, 50_000)).timestamp(), 186_000);
+    }
+
+    /// Relays an upstream server's source to two downstream sessions.
+    #[tokio::test]
+    async fn relay() {
+        init_logging();
+        let upstream_source = source();
+        let upstream = Server::new(ServerOptions::default());
+        upstream.mount("/camera", upstream_source.clone());
+        let (listener, upstream_addr) = listen().await;
+        let accept_task = tokio::spawn(async move {
+            loop {
+                let (stream, peer_addr) = listener.accept().await.unwrap();
+                let upstream = upstream.clone();
+                tokio::spawn(async move {
+                    upstream
+                        .serve_connection(stream, upstream_addr, peer_addr)
+                        .await
+                });
+            }
+        });
+        let frame = idr_frame(3000);
+        let push_task = tokio::spawn({
+            let frame = frame.clone();
+            async move {
+                let clock_rate = NonZeroU32::new(90_000).unwrap();
+                for i in 0.. {
+                    upstream_source
+                        .push_video(
+                            0,
+                            Timestamp::new(i * 3000, clock_rate, 0).unwrap(),
+                            true,
+                            frame.clone(),
+                        )
+                        .unwrap();
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        });
+
+        let url = Url::parse(&format!("rtsp://{upstream_addr}/camera")).unwrap();
+        let proxy = Proxy::connect(url, ProxyOptions::default()).await.unwrap();
+        let downstream = Server::new(ServerOptions::default());
+        downstream.mount("/camera", proxy.source().clone());
+        let proxy_task = tokio::spawn(proxy.run());
+        let (mut a, _) = start(&downstream, Transport::Tcp(Default::default())).await;
+        let (mut b, _) = start(&downstream, Transport::Udp(Default::default())).await;
+        for session in [&mut a, &mut b] {
+            match session.next().await {
+                Some(Ok(CodecItem::VideoFrame(f))) => {
+                    assert!(f.is_random_access_point());
+                    assert_eq!(f.data(), &frame[..]);
+                }
+                o => panic!("unexpected item {o:#?}"),
+            }
+        }
+        proxy_task.abort();
+        push_task.abort();
+        accept_task.abort();
+    }
+
+    /// An upstream session without H.264 video fails rather than being
+    /// retried forever.
+    #[tokio::test]
+    async fn no_video() {
+        init_logging();
+        let (listener, addr) = listen().await;
+        let serve = async {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut server = crate::net::Connection::from_stream(tcp).unwrap();
+            let cseq = match server.next().await.unwrap().unwrap().msg {
+                rtsp_types::Message::Request(r) => {
+                    assert_eq!(r.method(), rtsp_types::Method::Describe);
+                    r.header(&rtsp_types::headers::CSEQ).unwrap().clone()
+                }
+                o => panic!("unexpected message {o:#?}"),
+            };
+            server
+                .send(rtsp_types::Message::Response(
+                    rtsp_types::Response::builder(
+                        rtsp_types::Version::V1_0,
+                        rtsp_types::StatusCode::Ok,
+                    )
+                    .header(rtsp_types::headers::CSEQ, cseq)
+                    .header(rtsp_types::headers::CONTENT_TYPE, "application/sdp")
+                    .build(Bytes::from_static(
+                        b"v=0\r\n\
+                          o=- 1 1 IN IP4 127.0.0.1\r\n\
+                          s=Audio\r\n\
+                          t=0 0\r\n\
+                          a=control:*\r\n\
+                          m=audio 0 RTP/AVP 0\r\n\
+                          a=control:trackID=1\r\n",
+                    )),
+                ))
+                .await
+                .unwrap();
+        };
+        let url = Url::parse(&format!("rtsp://{addr}/audio")).unwrap();
+        let (r, ()) = tokio::time::timeout(
+            Duration::from_secs(10),
+            futures::future::join(Proxy::connect(url, ProxyOptions::default()), serve),
+        )
+        .await
+        .unwrap();
+        let e = r.map(|_| ()).unwrap_err();
+        assert!(matches!(*e.0, ErrorInt::InvalidArgument(_)), "{e}");
+    }
+}
@@ -0,0 +1,676 @@
+// Copyright (C) 2026 Scott Lamb <slamb@slamb.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Test support: a scriptable mock RTSP server; see [`MockServer`].
+//!
+//! Requires the `testing` cargo feature.
+//!
+//! ```no_run
+//! # #[cfg(feature = "testing")]
+//! # async fn f(describe: &'static [u8], setup: &'static [u8], play: &'static [u8]) -> Result<(), retina::Error> {
+//! use retina::testing::{MockServer, Script};
+//!
+//! let server = MockServer::start([
+//!     // The first connection closes mid-handshake...
+//!     Script::new().respond("DESCRIBE", describe).close(),
+//!     // ...and the second succeeds.
+//!     Script::new()
+//!         .respond("DESCRIBE", describe)
+//!         .respond("SETUP", setup)
+//!         .respond("PLAY", play),
+//! ])
+//! .await?;
+//! let url = server.url("/camera");
+//! // ...run the code under test against `url`...
+//! # drop(url);
+//! server.finish().await
+//! # }
+//! ```
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::future::RemoteHandle;
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, SinkExt, StreamExt};
+use log::debug;
+use rtsp_types::{Data, Message, Method, Response};
+use url::Url;
+
+use crate::rtp::RawPacketBuilder;
+use crate::runtime::{AsyncTcpListener, AsyncUdpSocket, TcpListener};
+use crate::server::{parse_transport, RequestedTransport};
+use crate::{Error, ErrorInt};
+
+#[derive(Clone)]
+enum Step {
+    /// Expects a request with the given method (or any method, if `None`) and
+    /// sends the response, with the request's `CSeq` plus `cseq_offset`.
+    Respond {
+        method: Option<Method>,
+        response: Response<Bytes>,
+        cseq_offset: u32,
+    },
+    Interleaved {
+        channel_id: u8,
+        data: Bytes,
+    },
+    Udp {
+        setup_i: usize,
+        rtcp: bool,
+        data: Bytes,
+    },
+    Sleep(Duration),
+    Close,
+}
+
+/// The scripted behavior of one connection to a [`MockServer`].
+///
+/// Steps run in order. Once they're exhausted, the connection answers any
+/// further requests (such as keepalives or `TEARDOWN`) with a bare `200 OK`
+/// and discards data, until the client closes it.
+#[derive(Clone, Default)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+impl Script {
+    /// Creates an empty script, which just answers requests with `200 OK`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects a request with the given method, or any method if `method` is
+    /// `*`, and replies with `response`.
+    ///
+    /// `response` is a raw RTSP response, as in Retina's `testdata`
+    /// directory. Its `CSeq` is replaced with the request's. If the request
+    /// is a `SETUP` for RTP over UDP, the `client_port` and `server_port`
+    /// parameters of its `Transport` header are replaced with the client's
+    /// ports and ports bound by the mock server, and `source` is removed.
+    ///
+    /// Interleaved data received while waiting for the request is discarded.
+    ///
+    /// Panics if `response` isn't a valid RTSP response.
+    pub fn respond(self, method: &str, response: impl Into<Bytes>) -> Self {
+        self.respond_with_cseq_offset(method, response.into(), 0)
+    }
+
+    /// As in [`Script::respond`], but with a `CSeq` which doesn't match the request's.
+    pub fn respond_bad_cseq(self, method: &str, response: impl Into<Bytes>) -> Self {
+        self.respond_with_cseq_offset(method, response.into(), 1)
+    }
+
+    /// Expects a request with the given method, or any method if `method` is
+    /// `*`, and replies `401 Unauthorized` with a new digest challenge.
+    ///
+    /// This can simulate a server which rotates its nonce mid-stream, in
+    /// reply to a keepalive.
+    pub fn unauthorized(self, method: &str, realm: &str, nonce: &str) -> Self {
+        let mut response = Response::builder(
+            rtsp_types::Version::V1_0,
+            rtsp_types::StatusCode::Unauthorized,
+        )
+        .build(Bytes::new());
+        response.insert_header(
+            rtsp_types::headers::WWW_AUTHENTICATE,
+            format!("Digest realm=\"{realm}\", nonce=\"{nonce}\""),
+        );
+        self.push_response(method, response, 0)
+    }
+
+    /// Sends RTSP interleaved data on the given channel.
+    pub fn interleaved(mut self, channel_id: u8, data: impl Into<Bytes>) -> Self {
+        self.steps.push(Step::Interleaved {
+            channel_id,
+            data: data.into(),
+        });
+        self
+    }
+
+    /// Sends an RTP packet on an interleaved channel which hasn't been set up,
+    /// as live555 servers do when a previous session on the same connection
+    /// is stale.
+    pub fn stale_data(self, channel_id: u8) -> Self {
+        self.interleaved(
+            channel_id,
+            rtp_packet(96, 0, 0, 0x5354_414c, true, b"stale"),
+        )
+    }
+
+    /// Sends an RTP packet over UDP to the client's RTP port from the
+    /// `setup_i`th UDP `SETUP` on this connection, counting from 0.
+    pub fn udp_rtp(mut self, setup_i: usize, data: impl Into<Bytes>) -> Self {
+        self.steps.push(Step::Udp {
+            setup_i,
+            rtcp: false,
+            data: data.into(),
+        });
+        self
+    }
+
+    /// As in [`Script::udp_rtp`], but to the client's RTCP port.
+    pub fn udp_rtcp(mut self, setup_i: usize, data: impl Into<Bytes>) -> Self {
+        self.steps.push(Step::Udp {
+            setup_i,
+            rtcp: true,
+            data: data.into(),
+        });
+        self
+    }
+
+    /// Waits before continuing.
+    pub fn sleep(mut self, duration: Duration) -> Self {
+        self.steps.push(Step::Sleep(duration));
+        self
+    }
+
+    /// Closes the connection.
+    pub fn close(mut self) -> Self {
+        self.steps.push(Step::Close);
+        self
+    }
+
+    fn respond_with_cseq_offset(self, method: &str, response: Bytes, cseq_offset: u32) -> Self {
+        let (msg, len) = Message::parse(&response[..]).expect("response should be valid RTSP");
+        assert_eq!(len, response.len(), "response should be a single message");
+        let response = match msg {
+            Message::Response(r) => r.map_body(|b| response.slice_ref(b)),
+            _ => panic!("expected an RTSP response"),
+        };
+        self.push_response(method, response, cseq_offset)
+    }
+
+    fn push_response(mut self, method: &str, response: Response<Bytes>, cseq_offset: u32) -> Self {
+        let method = match method {
+            "*" => None,
+            m => Some(Method::from(m)),
+        };
+        self.steps.push(Step::Respond {
+            method,
+            response,
+            cseq_offset,
+        });
+        self
+    }
+}
+
+/// Returns an RTP packet with the given header fields and payload.
+///
+/// Useful with [`Script::interleaved`] and [`Script::udp_rtp`], e.g. to send
+/// a packet with an SSRC other than the one advertised in `SETUP` or `PLAY`.
+///
+/// Panics if `payload_type` is at least 128 or the packet is too long.
+pub fn rtp_packet(
+    payload_type: u8,
+    sequence_number: u16,
+    timestamp: u32,
+    ssrc: u32,
+    mark: bool,
+    payload: &[u8],
+) -> Bytes {
+    let (raw, _) = RawPacketBuilder {
+        sequence_number,
+        timestamp,
+        payload_type,
+        ssrc,
+        mark,
+    }
+    .build(payload.iter().copied())
+    .expect("packet should be valid");
+    raw.0
+}
+
+/// An in-process RTSP server on localhost which runs a [`Script`] for each
+/// accepted connection.
+///
+/// The server runs in the background on the async runtime until
+/// [`MockServer::finish`] or until dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    done: RemoteHandle<Result<(), Error>>,
+}
+
+impl MockServer {
+    /// Starts listening on an ephemeral localhost port.
+    ///
+    /// The `i`th accepted connection runs the `i`th script. Further
+    /// connections aren't accepted.
+    pub async fn start(scripts: impl IntoIterator<Item = Script>) -> Result<Self, Error> {
+        let listener = TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .map_err(|e| wrap!(ErrorInt::ListenError(e)))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| wrap!(ErrorInt::ListenError(e)))?;
+        let scripts: Vec<Script> = scripts.into_iter().collect();
+        let (serve, done) = serve(listener, scripts).remote_handle();
+        crate::runtime::spawn(serve);
+        Ok(MockServer { addr, done })
+    }
+
+    /// Returns the address on which the server is listening.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns an `rtsp` URL for the given path on this server.
+    pub fn url(&self, path: &str) -> Url {
+        Url::parse(&format!(
+            "rtsp://{}/{}",
+            self.addr,
+            path.trim_start_matches('/')
+        ))
+        .expect("URL should be valid")
+    }
+
+    /// Waits for every script to run and each connection to close.
+    ///
+    /// Returns an error if a connection failed or didn't follow its script,
+    /// e.g. if the client sent an unexpected request.
+    pub async fn finish(self) -> Result<(), Error> {
+        self.done.await
+    }
+}
+
+async fn serve(listener: TcpListener, scripts: Vec<Script>) -> Result<(), Error> {
+    let mut scripts = scripts.into_iter().enumerate();
+    let mut pending = scripts.len();
+    let mut conns = FuturesUnordered::new();
+    loop {
+        if pending == 0 && conns.is_empty() {
+            return Ok(());
+        }
+        tokio::select! {
+            r = listener.accept(), if pending > 0 => {
+                let (stream, peer_addr) = r.map_err(|e| wrap!(ErrorInt::ListenError(e)))?;
+                let (i, script) = scripts.next().expect("pending > 0");
+                pending -= 1;
+                debug!("Mock server running script {} for {}", i, peer_addr);
+                let conn = crate::net::Connection::from_stream(stream)
+                    .map_err(|e| wrap!(ErrorInt::ListenError(e)))?;
+                conns.push(run(i, conn, script));
+            }
+            Some(r) = conns.next(), if !conns.is_empty() => r?,
+        }
+    }
+}
+
+fn script_error(script_i: usize, step_i: usize, description: String) -> Error {
+    wrap!(ErrorInt::FailedPrecondition(format!(
+        "mock script {script_i} step {step_i}: {description}"
+    )))
+}
+
+/// Runs `script` on a single connection.
+async fn run(
+    script_i: usize,
+    mut conn: crate::net::Connection,
+    script: Script,
+) -> Result<(), Error> {
+    let mut udp = Vec::new();
+    for (step_i, step) in script.steps.into_iter().enumerate() {
+        let err = |description| script_error(script_i, step_i, description);
+        match step {
+            Step::Respond {
+                method,
+                mut response,
+                cseq_offset,
+            } => {
+                let req = loop {
+                    match conn.next().await {
+                        Some(Ok(m)) => match m.msg {
+                            Message::Request(r) => break r,
+                            Message::Data(_) => continue,
+                            Message::Response(r) => {
+                                return Err(err(format!("unexpected {:?} response", r.status())))
+                            }
+                        },
+                        Some(Err(e)) => return Err(err(e.to_string())),
+                        None => return Err(err("connection closed".to_owned())),
+                    }
+                };
+                if matches!(&method, Some(m) if m != req.method()) {
+                    return Err(err(format!(
+                        "expected {:?} request, got {:?}",
+                        method.unwrap(),
+                        req.method()
+                    )));
+                }
+                let cseq = req
+                    .header(&rtsp_types::headers::CSEQ)
+                    .and_then(|c| u32::from_str_radix(c.as_str().trim(), 10).ok())
+                    .ok_or_else(|| err("request has no valid CSeq".to_owned()))?;
+                response.insert_header(
+                    rtsp_types::headers::CSEQ,
+                    cseq.wrapping_add(cseq_offset).to_string(),
+                );
+                if req.method() == Method::Setup {
+                    if let Some(transport) = setup_udp(&conn, &req, &response, &mut udp)
+                        .map_err(|e| err(e.to_string()))?
+                    {
+                        response.insert_header(rtsp_types::headers::TRANSPORT, transport);
+                    }
+                }
+                conn.send(Message::Response(response))
+                    .await
+                    .map_err(|e| err(e.to_string()))?;
+            }
+            Step::Interleaved { channel_id, data } => {
+                conn.send(Message::Data(Data::new(channel_id, data)))
+                    .await
+                    .map_err(|e| err(e.to_string()))?;
+            }
+            Step::Udp {
+                setup_i,
+                rtcp,
+                data,
+            } => {
+                let (sockets, rtp_addr): &(crate::net::UdpPair, SocketAddr) = udp
+                    .get(setup_i)
+                    .ok_or_else(|| err(format!("no UDP SETUP {setup_i}")))?;
+                let (socket, addr) = if rtcp {
+                    let rtcp_addr = SocketAddr::new(rtp_addr.ip(), rtp_addr.port() + 1);
+                    (&sockets.rtcp_socket, rtcp_addr)
+                } else {
+                    (&sockets.rtp_socket, *rtp_addr)
+                };
+                futures::future::poll_fn(|cx| socket.poll_send_to(cx, &data[..], addr))
+                    .await
+                    .map_err(|e| err(e.to_string()))?;
+            }
+            Step::Sleep(d) => crate::runtime::sleep(d).await,
+            Step::Close => return Ok(()),
+        }
+    }
+
+    // The script is exhausted; answer anything further.
+    while let Some(Ok(m)) = conn.next().await {
+        if let Message::Request(req) = m.msg {
+            let mut response =
+                Response::builder(rtsp_types::Version::V1_0, rtsp_types::StatusCode::Ok)
+                    .build(Bytes::new());
+            if let Some(cseq) = req.header(&rtsp_types::headers::CSEQ) {
+                response.insert_header(rtsp_types::headers::CSEQ, cseq.as_str());
+            }
+            if conn.send(Message::Response(response)).await.is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// If `req` is a `SETUP` for RTP over UDP, binds sockets for it and returns
+/// the `Transport` header to send with `response`.
+fn setup_udp(
+    conn: &crate::net::Connection,
+    req: &rtsp_types::Request<Bytes>,
+    response: &Response<Bytes>,
+    udp: &mut Vec<(crate::net::UdpPair, SocketAddr)>,
+) -> Result<Option<String>, std::io::Error> {
+    let rtp_port = match req
+        .header(&rtsp_types::headers::TRANSPORT)
+        .and_then(|t| parse_transport(t.as_str()))
+    {
+        Some(RequestedTransport::Udp { rtp_port, .. }) => rtp_port,
+        _ => return Ok(None),
+    };
+    let pair = crate::UdpPair::for_ip(conn.ctx().local_addr.ip())?;
+    let server_port = pair.rtp_port;
+    udp.push((
+        crate::net::UdpPair::from_std(pair)?,
+        SocketAddr::new(conn.ctx().peer_addr.ip(), rtp_port),
+    ));
+
+    // Keep `ssrc` last, after the ports.
+    let mut params: Vec<String> = vec!["RTP/AVP".to_owned(), "unicast".to_owned()];
+    let mut ssrc = None;
+    if let Some(t) = response.header(&rtsp_types::headers::TRANSPORT) {
+        for p in t.as_str().split(';').skip(1).map(str::trim) {
+            let key = p.split_once('=').map_or(p, |(k, _)| k);
+            match key {
+                "unicast" | "client_port" | "server_port" | "source" | "interleaved" => {}
+                "ssrc" => ssrc = Some(p.to_owned()),
+                _ => params.push(p.to_owned()),
+            }
+        }
+    }
+    params.push(format!("client_port={}-{}", rtp_port, rtp_port + 1));
+    params.push(format!("server_port={}-{}", server_port, server_port + 1));
+    params.extend(ssrc);
+    Ok(Some(params.join(";")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        PacketItem, PlayOptions, ReconnectItem, ReconnectOptions, Reconnecting, Session,
+        SessionOptions, SetupOptions, Transport,
+    };
+    use crate::testutil::init_logging;
+
+    const DESCRIBE: &[u8] = include_bytes!("client/testdata/reolink_describe.txt");
+    const SETUP: &[u8] = include_bytes!("client/testdata/reolink_setup.txt");
+    const PLAY: &[u8] = include_bytes!("client/testdata/reolink_play.txt");
+
+    /// The first packet of stream 0, matching `PLAY`'s `RTP-Info`.
+    fn first_packet(ssrc: u32) -> Bytes {
+        rtp_packet(96, 16852, 1070938629, ssrc, true, b"\x06\x05\x01\x00")
+    }
+
+    async fn play(
+        server: &MockServer,
+        transport: Transport,
+    ) -> Result<Session<crate::client::Playing>, Error> {
+        let mut session = Session::describe(
+            server.url("/h264Preview_01_main"),
+            SessionOptions::default(),
+        )
+        .await?;
+        session
+            .setup(0, SetupOptions::default().transport(transport))
+            .await?;
+        session.play(PlayOptions::default()).await
+    }
+
+    #[tokio::test]
+    async fn udp() {
+        init_logging();
+        let server = MockServer::start([Script::new()
+            .respond("DESCRIBE", DESCRIBE)
+            .respond("SETUP", SETUP)
+            .respond("PLAY", PLAY)
+            .udp_rtp(0, first_packet(0xdcc4a0d8))])
+        .await
+        .unwrap();
+        let mut session = play(&server, Transport::Udp(Default::default()))
+            .await
+            .unwrap();
+        match session.next().await {
+            Some(Ok(PacketItem::Rtp(p))) => assert_eq!(p.sequence_number(), 16852),
+            o => panic!("unexpected item {o:#?}"),
+        }
+        drop(session);
+        server.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn wrong_ssrc() {
+        init_logging();
+        let server = MockServer::start([Script::new()
+            .respond("DESCRIBE", DESCRIBE)
+            .respond("SETUP", SETUP)
+            .respond("PLAY", PLAY)
+            .interleaved(0, first_packet(0x1234_5678))])
+        .await
+        .unwrap();
+        let mut session = play(&server, Transport::Tcp(Default::default()))
+            .await
+            .unwrap();
+        match session.next().await {
+            Some(Err(e)) => assert!(e.to_string().contains("Wrong ssrc"), "{e}"),
+            o => panic!("unexpected item {o:#?}"),
+        }
+        drop(session);
+        server.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_data() {
+        init_logging();
+        let server = MockServer::start([Script::new()
+            .respond("DESCRIBE", DESCRIBE)
+            .respond("SETUP", SETUP)
+            .respond("PLAY", PLAY)
+            .stale_data(4)
+            .interleaved(0, first_packet(0xdcc4a0d8))])
+        .await
+        .unwrap();
+        let mut session = Session::describe(
+            server.url("/h264Preview_01_main"),
+            SessionOptions::default()
+                .unassigned_channel_data(crate::client::UnassignedChannelDataPolicy::Ignore),
+        )
+        .await
+        .unwrap();
+        session.setup(0, SetupOptions::default()).await.unwrap();
+        let mut session = session.play(PlayOptions::default()).await.unwrap();
+        match session.next().await {
+            Some(Ok(PacketItem::Rtp(p))) => assert_eq!(p.sequence_number(), 16852),
+            o => panic!("unexpected item {o:#?}"),
+        }
+        drop(session);
+        server.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn bad_cseq() {
+        init_logging();
+        let server = MockServer::start([Script::new().respond_bad_cseq("DESCRIBE", DESCRIBE)])
+            .await
+            .unwrap();
+        match play(&server, Transport::Tcp(Default::default())).await {
+            Err(e) => assert!(e.to_string().contains("CSeq"), "{e}"),
+            Ok(_) => panic!("DESCRIBE with bad CSeq succeeded"),
+        }
+        server.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unexpected_request() {
+        init_logging();
+        let server = MockServer::start([Script::new().respond("OPTIONS", DESCRIBE)])
+            .await
+            .unwrap();
+        let (_, r) = tokio::join!(
+            Session::describe(server.url("/"), SessionOptions::default()),
+            server.finish()
+        );
+        let e = r.unwrap_err().to_string();
+        assert!(e.contains("mock script 0 step 0"), "{e}");
+    }
+
+    #[tokio::test]
+    async fn unauthorized() {
+        init_logging();
+        let server = MockServer::start([Script::new()
+            .unauthorized("DESCRIBE", "Surveillance Server", "98481030")
+            .respond("DESCRIBE", DESCRIBE)])
+        .await
+        .unwrap();
+        let creds = crate::client::Credentials {
+            username: "admin".to_owned(),
+            password: "secret".to_owned(),
+        };
+        let session = Session::describe(
+            server.url("/h264Preview_01_main"),
+            SessionOptions::default().creds(Some(creds)),
+        )
+        .await
+        .unwrap();
+        drop(session);
+        server.finish().await.unwrap();
+    }
+
+    /// Recovers from a keepalive rejected with a new digest challenge.
+    #[tokio::test]
+    async fn unauthorized_keepalive() {
+        init_logging();
+        // A short session timeout, for keepalives every second.
+        const SETUP: &[u8] = b"RTSP/1.0 200 OK\r\n\
+            CSeq: 1\r\n\
+            Transport: RTP/AVP/TCP;unicast;interleaved=0-1\r\n\
+            Session: F8F8E425;timeout=2\r\n\r\n";
+        // Advertise `GET_PARAMETER` so it's used for keepalives after the first.
+        const OPTIONS: &[u8] = b"RTSP/1.0 200 OK\r\n\
+            CSeq: 1\r\n\
+            Public: OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN, GET_PARAMETER\r\n\r\n";
+        const OK: &[u8] = b"RTSP/1.0 200 OK\r\nCSeq: 1\r\n\r\n";
+        let server = MockServer::start([Script::new()
+            .unauthorized("DESCRIBE", "Surveillance Server", "98481030")
+            .respond("DESCRIBE", DESCRIBE)
+            .respond("SETUP", SETUP)
+            .respond("PLAY", PLAY)
+            .interleaved(0, first_packet(0xdcc4a0d8))
+            .respond("OPTIONS", OPTIONS)
+            .unauthorized("GET_PARAMETER", "Surveillance Server", "12345678")
+            .respond("GET_PARAMETER", OK)
+            .interleaved(
+                0,
+                rtp_packet(96, 16853, 1070938629, 0xdcc4a0d8, true, b"\x06\x05\x01\x00"),
+            )])
+        .await
+        .unwrap();
+        let creds = crate::client::Credentials {
+            username: "admin".to_owned(),
+            password: "secret".to_owned(),
+        };
+        let mut session = Session::describe(
+            server.url("/h264Preview_01_main"),
+            SessionOptions::default().creds(Some(creds)),
+        )
+        .await
+        .unwrap();
+        session.setup(0, SetupOptions::default()).await.unwrap();
+        let mut session = session.play(PlayOptions::default()).await.unwrap();
+        for expected_seq in [16852, 16853] {
+            match session.next().await {
+                Some(Ok(PacketItem::Rtp(p))) => assert_eq!(p.sequence_number(), expected_seq),
+                o => panic!("unexpected item {o:#?}"),
+            }
+        }
+        drop(session);
+        server.finish().await.unwrap();
+    }
+
+    /// Reconnects after a failed attempt and after a session ends.
+    #[tokio::test]
+    async fn reconnect() {
+        init_logging();
+        let session = Script::new()
+            .respond("DESCRIBE", DESCRIBE)
+            .respond("SETUP", SETUP)
+            .respond("PLAY", PLAY);
+        let server = MockServer::start([
+            Script::new().respond("DESCRIBE", DESCRIBE).close(),
+            session.clone().close(),
+            session,
+        ])
+        .await
+        .unwrap();
+        let mut session = Reconnecting::new(
+            server.url("/h264Preview_01_main"),
+            ReconnectOptions::default().initial_backoff(Duration::from_millis(1)),
+            |_| vec![(0, SetupOptions::default())],
+        );
+        match session.next().await {
+            Some(Ok(ReconnectItem::Discontinuity(d))) => assert_eq!(d.attempts(), 1),
+            o => panic!("unexpected item {o:#?}"),
+        }
+        drop(session);
+        server.finish().await.unwrap();
+    }
+}
@@ -5,6 +5,12 @@ use std::str::FromStr;
 
 use bytes::Bytes;
 
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use {
+    crate::runtime::{AsyncTcpListener, AsyncTcpStream, TcpListener, TcpStream},
+    std::net::{Ipv4Addr, SocketAddr},
+};
+
 pub(crate) fn init_logging() {
     let h = mylog::Builder::new()
         .set_format(
@@ -26,3 +32,35 @@ pub(crate) fn response(raw: &'static [u8]) -> rtsp_types::Response<Bytes> {
         _ => panic!("unexpected message type"),
     }
 }
+
+/// Binds a listener to an ephemeral loopback port via the [`crate::runtime`] backend.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub(crate) async fn listen() -> (TcpListener, SocketAddr) {
+    let listener = <TcpListener as AsyncTcpListener>::bind((Ipv4Addr::LOCALHOST, 0).into())
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    (listener, addr)
+}
+
+/// Connects to `addr` via the [`crate::runtime`] backend.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub(crate) async fn connect(addr: SocketAddr) -> TcpStream {
+    let host = match addr.ip() {
+        std::net::IpAddr::V4(ip) => url::Host::Ipv4(ip),
+        std::net::IpAddr::V6(ip) => url::Host::Ipv6(ip),
+    };
+    <TcpStream as AsyncTcpStream>::connect(host, addr.port())
+        .await
+        .unwrap()
+}
+
+/// Cross-platform, runtime-agnostic equivalent of `socketpair(2)`.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub(crate) async fn socketpair() -> (TcpStream, TcpStream) {
+    // Another process on the machine could connect to the server and mess
+    // this up, but that's unlikely enough to ignore in test code.
+    let (listener, addr) = listen().await;
+    let (client, server) = futures::join!(connect(addr), listener.accept());
+    (client, server.unwrap().0)
+}